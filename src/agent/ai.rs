@@ -1,20 +1,27 @@
-use std::sync::Arc;
+use std::collections::VecDeque;
+use std::sync::{Arc, Weak};
+use std::time::Instant;
 
 use anyhow::Result;
 use async_trait::async_trait;
 use tokio::sync::{mpsc, RwLock};
+use tokio_util::sync::CancellationToken;
 use tracing::{error, info, warn};
 
-use crate::ai::{AnthropicClient, CreateMessageRequest};
+use crate::ai::{AnthropicClient, CreateMessageRequest, RateLimited, StreamEvent, SystemPrompt};
+use crate::audit::AuditLogger;
 use crate::config::MyAgentEnv;
+use crate::metrics::Metrics;
+use crate::thread_manager::{ThreadContext, ThreadManager, ThreadOverrides};
 use crate::protocol::{
     AgentEvent, AgentStatus, ContentBlock, Message, Submission,
     tool_result_block, user_message, user_message_with_tool_results,
 };
 use crate::tools;
 use crate::tools::shell::Shell;
+use crate::tools::ToolStatsMap;
 
-use super::Agent;
+use super::{next_submission, run_turn, Agent, Cancelled};
 
 const SYSTEM_PROMPT_BASE: &str = "\
 You are a helpful AI coding assistant running on the user's local machine. \
@@ -35,23 +42,134 @@ For Feishu operations, use:\n\
 When the user mentions a file, use `myagent feishu files` with the chat_id from the context \
 to find the file_key and message_id, then download it.\n\
 You can proactively send messages to notify the user of important results or task completion.\n\
-The chat_id is available in the <feishu_context> tag of each message.";
+The chat_id and the user's open_id are in the <feishu_context> tag below.";
 
 const SYSTEM_PROMPT_TAIL: &str = "\n\n\
 Always explain what you're doing before executing commands. \
 Be concise in your responses.";
 
+const DEFAULT_MAX_TOKENS: u32 = 16384;
+/// How many 429 responses in a row a turn waits out before failing.
+const MAX_RATE_LIMIT_RETRIES: usize = 5;
+
+/// Longest `input_summary` sent with `ToolStart`.
+const MAX_INPUT_SUMMARY: usize = 100;
+
+/// Characters of tool output sent to frontends in `ToolResult` events.
+const RESULT_PREVIEW_CHARS: usize = 200;
+
+/// A turn stopped after `max_turns` model calls; not an API failure.
+#[derive(Debug)]
+struct MaxTurnsReached(u32);
+
+impl std::fmt::Display for MaxTurnsReached {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Max turns reached ({})", self.0)
+    }
+}
+
+impl std::error::Error for MaxTurnsReached {}
+
 pub struct AiAgent {
     config: MyAgentEnv,
+    max_tokens: u32,
+    system_prompt_suffix: Option<String>,
+    context: ThreadContext,
     workspace: String,
     shell: Shell,
     has_feishu: bool,
+    metrics: Arc<Metrics>,
+    audit: Option<Arc<AuditLogger>>,
+    tool_stats: ToolStatsMap,
+    allowed_hosts: Vec<String>,
+    clipboard: bool,
+    /// Weak so a thread doesn't keep its own manager alive.
+    manager: Option<Weak<ThreadManager>>,
+    http: reqwest::Client,
 }
 
 impl AiAgent {
-    pub fn new(config: MyAgentEnv, workspace: String, has_feishu: bool) -> Self {
-        let shell = Shell::detect();
-        Self { config, workspace, shell, has_feishu }
+    pub fn new(
+        config: MyAgentEnv,
+        workspace: String,
+        has_feishu: bool,
+        metrics: Arc<Metrics>,
+        overrides: ThreadOverrides,
+        extra_path: &[String],
+        audit: Option<Arc<AuditLogger>>,
+    ) -> Self {
+        let mut config = config;
+        if let Some(model) = overrides.model {
+            config.model = model;
+        }
+        let shell = Shell::detect(extra_path);
+        Self {
+            config,
+            max_tokens: overrides.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            system_prompt_suffix: overrides.system_prompt_suffix,
+            context: ThreadContext::default(),
+            workspace,
+            shell,
+            has_feishu,
+            metrics,
+            audit,
+            tool_stats: ToolStatsMap::default(),
+            allowed_hosts: Vec::new(),
+            clipboard: false,
+            manager: None,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Tell the agent which thread, chat and user it is serving.
+    pub fn with_context(mut self, context: ThreadContext) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// Count this agent's tool calls in `stats` (shared across threads).
+    pub fn with_tool_stats(mut self, stats: ToolStatsMap) -> Self {
+        self.tool_stats = stats;
+        self
+    }
+
+    /// Hosts the `remote_shell` tool may reach; none disables it.
+    pub fn with_allowed_hosts(mut self, hosts: Vec<String>) -> Self {
+        self.allowed_hosts = hosts;
+        self
+    }
+
+    /// Offer the `clipboard` tool.
+    pub fn with_clipboard(mut self, enabled: bool) -> Self {
+        self.clipboard = enabled;
+        self
+    }
+
+    /// Let the agent run subtasks on new threads with `delegate_to_agent`;
+    /// `None` leaves the tool out.
+    pub fn with_manager(mut self, manager: Option<Weak<ThreadManager>>) -> Self {
+        self.manager = manager;
+        self
+    }
+
+    /// Send API requests with `http`, e.g. one routed through the configured proxy.
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// What this turn's tool calls run with.
+    fn tool_context(&self, cancel: CancellationToken) -> tools::ToolContext {
+        tools::ToolContext {
+            work_dir: self.workspace.clone(),
+            shell: self.shell.clone(),
+            audit: self.audit.clone(),
+            stats: self.tool_stats.clone(),
+            allowed_hosts: self.allowed_hosts.clone(),
+            manager: self.manager.as_ref().and_then(Weak::upgrade),
+            shell_timeout_ms: self.config.shell_timeout_ms,
+            cancel,
+        }
     }
 }
 
@@ -66,10 +184,24 @@ impl Agent for AiAgent {
         mut rx_sub: mpsc::Receiver<Submission>,
         tx_event: mpsc::Sender<AgentEvent>,
     ) {
-        let client = AnthropicClient::new(&self.config.api_key, &self.config.base_url);
+        let client = AnthropicClient::with_http_client(
+            self.http.clone(),
+            &self.config.api_key,
+            &self.config.base_url,
+        )
+        .with_api_keys(self.config.api_keys.clone())
+        .with_prompt_caching(self.config.use_prompt_caching)
+        .with_timeout_ms(self.config.api_timeout_ms)
+        .with_headers(self.config.attribution_headers());
         let mut messages: Vec<Message> = Vec::new();
-        let tool_defs = tools::build_tool_definitions(&self.shell);
-        let mut system_prompt = SYSTEM_PROMPT_BASE.to_string();
+        let tool_defs = tools::build_tool_definitions(
+            &self.shell,
+            &self.allowed_hosts,
+            self.manager.is_some(),
+            self.clipboard,
+            self.config.shell_timeout_ms,
+        );
+        let mut system_prompt = base_system_prompt(self.config.system_prompt_file.as_deref()).await;
         if self.has_feishu {
             system_prompt.push_str(SYSTEM_PROMPT_FEISHU);
         }
@@ -78,22 +210,56 @@ impl Agent for AiAgent {
             "\n\nYour current working directory is: {}",
             self.workspace
         ));
+        system_prompt.push_str("\n\n");
+        system_prompt.push_str(&self.context.to_prompt());
+        if let Some(suffix) = &self.system_prompt_suffix {
+            system_prompt.push_str("\n\n");
+            system_prompt.push_str(suffix);
+        }
 
-        while let Some(sub) = rx_sub.recv().await {
+        let mut turn_suffix: Option<String> = None;
+        let mut pending = VecDeque::new();
+        while let Some(sub) = next_submission(&mut rx_sub, &mut pending).await {
             match sub {
+                Submission::Configure { system_suffix } => {
+                    turn_suffix = Some(system_suffix);
+                }
                 Submission::UserMessage(text) | Submission::FollowUp(text) => {
                     info!("AiAgent received message: {}", truncate(&text, 100));
                     messages.push(user_message(&text));
+                    let turn_prompt = match turn_suffix.take() {
+                        Some(suffix) => format!("{system_prompt}\n\n{suffix}"),
+                        None => system_prompt.clone(),
+                    };
                     emit(&tx_event, AgentEvent::StatusChange(AgentStatus::Working)).await;
-                    match ai_loop(&client, &self.config, &mut messages, &tool_defs, &system_prompt, &self.workspace, &self.shell, &tx_event).await
-                    {
+                    let cancel = CancellationToken::new();
+                    let tool_ctx = Arc::new(self.tool_context(cancel.clone()));
+                    let turn = self.ai_loop(
+                        &client,
+                        &mut messages,
+                        &tool_defs,
+                        &turn_prompt,
+                        &tool_ctx,
+                        &tx_event,
+                    );
+                    match run_turn(turn, &mut rx_sub, &cancel, &mut pending).await {
                         Ok(()) => {
                             info!("AiAgent turn completed");
                             emit(&tx_event, AgentEvent::StatusChange(AgentStatus::Completed))
                                 .await;
                         }
+                        Err(e) if e.is::<Cancelled>() => {
+                            info!("AiAgent turn cancelled");
+                            emit(&tx_event, AgentEvent::StatusChange(AgentStatus::Cancelled))
+                                .await;
+                        }
+                        Err(e) if e.is::<MaxTurnsReached>() => {
+                            warn!("AiAgent turn stopped: {e}");
+                            emit(&tx_event, AgentEvent::Error(e.to_string())).await;
+                        }
                         Err(e) => {
                             error!("AiAgent error: {e}");
+                            self.metrics.inc_api_errors();
                             emit(&tx_event, AgentEvent::Error(e.to_string())).await;
                         }
                     }
@@ -108,6 +274,35 @@ impl Agent for AiAgent {
     }
 }
 
+/// The contents of `path` when given and readable, else the built-in
+/// `SYSTEM_PROMPT_BASE`.
+async fn base_system_prompt(path: Option<&std::path::Path>) -> String {
+    let Some(path) = path else {
+        return SYSTEM_PROMPT_BASE.to_string();
+    };
+    match tokio::fs::read_to_string(path).await {
+        Ok(prompt) => {
+            info!("Using system prompt from {}", path.display());
+            prompt.trim_end().to_string()
+        }
+        Err(e) => {
+            warn!("Failed to read system prompt file {}: {e}; using the built-in prompt", path.display());
+            SYSTEM_PROMPT_BASE.to_string()
+        }
+    }
+}
+
+/// The most telling argument of a tool call (the command, path, pattern or
+/// git action), else the whole input as JSON.
+fn input_summary(input: &serde_json::Value) -> String {
+    let summary = ["command", "file_path", "dir_path", "path", "pattern", "action"]
+        .iter()
+        .find_map(|key| input[key].as_str())
+        .map(|s| s.lines().next().unwrap_or("").to_string())
+        .unwrap_or_else(|| input.to_string());
+    truncate(&summary, MAX_INPUT_SUMMARY)
+}
+
 fn truncate(s: &str, max: usize) -> String {
     if s.len() <= max {
         s.to_string()
@@ -120,223 +315,353 @@ fn truncate(s: &str, max: usize) -> String {
     }
 }
 
-async fn ai_loop(
-    client: &AnthropicClient,
-    config: &MyAgentEnv,
-    messages: &mut Vec<Message>,
-    tool_defs: &[crate::ai::ToolDef],
-    system_prompt: &str,
-    workspace: &str,
-    shell: &Shell,
-    tx_event: &mpsc::Sender<AgentEvent>,
-) -> Result<()> {
-    loop {
-        let request = CreateMessageRequest {
-            model: config.model.clone(),
-            max_tokens: 16384,
-            messages: messages.clone(),
-            tools: tool_defs.to_vec(),
-            stream: true,
-            system: Some(system_prompt.to_string()),
-        };
-
-        let mut stream_rx = client.stream_message(request).await?;
-        let mut assistant_content: Vec<ContentBlock> = Vec::new();
-        let mut current_text = String::new();
-        let mut current_tool_json = String::new();
-        let mut current_tool_block: Option<ContentBlock> = None;
-        let mut stop_reason: Option<String> = None;
-        let mut block_index: usize = 0;
-
-        while let Some(event) = stream_rx.recv().await {
-            match event {
-                crate::ai::StreamEvent::ContentBlockStart { content_block, .. } => {
-                    // Defensive: finalize any pending block before starting a new one.
-                    // This handles proxies that may not emit ContentBlockStop between blocks.
-                    if !current_text.is_empty() {
-                        assistant_content.push(ContentBlock::Text {
-                            text: current_text.clone(),
-                        });
-                        current_text.clear();
+impl AiAgent {
+    /// Run one turn: call the model and execute the tools it asks for until
+    /// it answers without tool calls. Fails with `Cancelled` once the
+    /// context's token fires, after recording the results of any tool calls
+    /// already made so the history stays valid.
+    async fn ai_loop(
+        &self,
+        client: &AnthropicClient,
+        messages: &mut Vec<Message>,
+        tool_defs: &[crate::ai::ToolDef],
+        system_prompt: &str,
+        tool_ctx: &Arc<tools::ToolContext>,
+        tx_event: &mpsc::Sender<AgentEvent>,
+    ) -> Result<()> {
+        let config = &self.config;
+        let max_tokens = self.max_tokens;
+        let metrics = &self.metrics;
+        let cancel = &tool_ctx.cancel;
+        let mut turn: u32 = 0;
+        let warn_at = config.max_turns * 4 / 5;
+        loop {
+            if turn >= config.max_turns {
+                return Err(MaxTurnsReached(config.max_turns).into());
+            }
+            let request = CreateMessageRequest {
+                model: config.model.clone(),
+                max_tokens,
+                messages: messages.clone(),
+                tools: tool_defs.to_vec(),
+                stream: true,
+                system: Some(SystemPrompt::new(system_prompt, config.use_prompt_caching)),
+            };
+
+            let mut stream_rx = tokio::select! {
+                stream = stream_with_retry(client, request, tx_event) => stream?,
+                _ = cancel.cancelled() => return Err(Cancelled.into()),
+            };
+            let mut assistant_content: Vec<ContentBlock> = Vec::new();
+            let mut current_text = String::new();
+            let mut current_tool_json = String::new();
+            let mut current_tool_block: Option<ContentBlock> = None;
+            let mut stop_reason: Option<String> = None;
+            let mut block_index: usize = 0;
+            let mut input_tokens: u32 = 0;
+            let mut output_tokens: u32 = 0;
+
+            loop {
+                let event = tokio::select! {
+                    event = stream_rx.recv() => event,
+                    _ = cancel.cancelled() => return Err(Cancelled.into()),
+                };
+                let Some(event) = event else {
+                    break;
+                };
+                match event {
+                    crate::ai::StreamEvent::MessageStart { input_tokens: n } => {
+                        input_tokens = n;
                     }
-                    if let Some(mut block) = current_tool_block.take() {
-                        if let ContentBlock::ToolUse { ref mut input, .. } = block {
-                            *input = serde_json::from_str(&current_tool_json)
-                                .unwrap_or(serde_json::Value::Object(Default::default()));
+                    crate::ai::StreamEvent::ContentBlockStart { content_block, .. } => {
+                        // Defensive: finalize any pending block before starting a new one.
+                        // This handles proxies that may not emit ContentBlockStop between blocks.
+                        if !current_text.is_empty() {
+                            assistant_content.push(ContentBlock::Text {
+                                text: current_text.clone(),
+                            });
+                            current_text.clear();
                         }
-                        assistant_content.push(block);
-                        current_tool_json.clear();
-                        block_index += 1;
-                    }
-
-                    match &content_block {
-                        ContentBlock::ToolUse { .. } => {
-                            emit(
-                                tx_event,
-                                AgentEvent::ContentBlockStart {
-                                    index: block_index,
-                                    content_block: content_block.clone(),
-                                },
-                            )
-                            .await;
-                            current_tool_block = Some(content_block);
+                        if let Some(mut block) = current_tool_block.take() {
+                            if let ContentBlock::ToolUse { ref mut input, .. } = block {
+                                *input = serde_json::from_str(&current_tool_json)
+                                    .unwrap_or(serde_json::Value::Object(Default::default()));
+                            }
+                            assistant_content.push(block);
                             current_tool_json.clear();
+                            block_index += 1;
                         }
-                        ContentBlock::Text { .. } => {
-                            emit(
-                                tx_event,
-                                AgentEvent::ContentBlockStart {
-                                    index: block_index,
-                                    content_block: content_block,
-                                },
-                            )
-                            .await;
+
+                        match &content_block {
+                            ContentBlock::ToolUse { .. } => {
+                                emit(
+                                    tx_event,
+                                    AgentEvent::ContentBlockStart {
+                                        index: block_index,
+                                        content_block: content_block.clone(),
+                                    },
+                                )
+                                .await;
+                                current_tool_block = Some(content_block);
+                                current_tool_json.clear();
+                            }
+                            ContentBlock::Text { .. } => {
+                                emit(
+                                    tx_event,
+                                    AgentEvent::ContentBlockStart {
+                                        index: block_index,
+                                        content_block: content_block,
+                                    },
+                                )
+                                .await;
+                                current_text.clear();
+                            }
+                            _ => {}
+                        }
+                    }
+                    crate::ai::StreamEvent::TextDelta { text, .. } => {
+                        current_text.push_str(&text);
+                        emit(
+                            tx_event,
+                            AgentEvent::TextDelta {
+                                index: block_index,
+                                text,
+                            },
+                        )
+                        .await;
+                    }
+                    crate::ai::StreamEvent::InputJsonDelta { partial_json, .. } => {
+                        current_tool_json.push_str(&partial_json);
+                        emit(
+                            tx_event,
+                            AgentEvent::InputJsonDelta {
+                                index: block_index,
+                                partial_json,
+                            },
+                        )
+                        .await;
+                    }
+                    crate::ai::StreamEvent::ContentBlockStop { .. } => {
+                        emit(
+                            tx_event,
+                            AgentEvent::ContentBlockStop {
+                                index: block_index,
+                            },
+                        )
+                        .await;
+                        if !current_text.is_empty() {
+                            assistant_content.push(ContentBlock::Text {
+                                text: current_text.clone(),
+                            });
                             current_text.clear();
                         }
-                        _ => {}
+                        if let Some(mut block) = current_tool_block.take() {
+                            if let ContentBlock::ToolUse { ref mut input, .. } = block {
+                                *input = serde_json::from_str(&current_tool_json)
+                                    .unwrap_or(serde_json::Value::Object(Default::default()));
+                            }
+                            assistant_content.push(block);
+                            current_tool_json.clear();
+                        }
+                        block_index += 1;
+                    }
+                    crate::ai::StreamEvent::MessageDelta {
+                        stop_reason: sr,
+                        output_tokens: n,
+                    } => {
+                        // Cumulative for the message, so keep the latest count
+                        if let Some(n) = n {
+                            output_tokens = n;
+                        }
+                        stop_reason = sr.clone();
+                        emit(tx_event, AgentEvent::MessageDelta { stop_reason: sr }).await;
+                    }
+                    crate::ai::StreamEvent::MessageStop => {
+                        emit(
+                            tx_event,
+                            AgentEvent::Usage {
+                                input_tokens: input_tokens.into(),
+                                output_tokens: output_tokens.into(),
+                                cost_usd: None,
+                            },
+                        )
+                        .await;
+                        emit(tx_event, AgentEvent::MessageStop).await;
+                        break;
                     }
                 }
-                crate::ai::StreamEvent::TextDelta { text, .. } => {
-                    current_text.push_str(&text);
-                    emit(
-                        tx_event,
-                        AgentEvent::TextDelta {
-                            index: block_index,
-                            text,
-                        },
-                    )
-                    .await;
-                }
-                crate::ai::StreamEvent::InputJsonDelta { partial_json, .. } => {
-                    current_tool_json.push_str(&partial_json);
-                    emit(
-                        tx_event,
-                        AgentEvent::InputJsonDelta {
-                            index: block_index,
-                            partial_json,
-                        },
-                    )
-                    .await;
-                }
-                crate::ai::StreamEvent::ContentBlockStop { .. } => {
+            }
+
+            messages.push(Message {
+                role: "assistant".to_string(),
+                content: assistant_content.clone(),
+            });
+            turn += 1;
+            if turn == warn_at {
+                warn!("{turn} of {} turns used", config.max_turns);
+            }
+
+            let tool_uses: Vec<_> = assistant_content
+                .iter()
+                .filter_map(|b| match b {
+                    ContentBlock::ToolUse { id, name, input } => {
+                        Some((id.clone(), name.clone(), input.clone()))
+                    }
+                    _ => None,
+                })
+                .collect();
+
+            if tool_uses.is_empty()
+                || stop_reason.as_deref() != Some(crate::ai::STOP_REASON_TOOL_USE)
+            {
+                return Ok(());
+            }
+
+            // Any other calls in the same response are dropped with the history
+            let reset = tool_uses.iter().find(|(_, name, _)| name == tools::RESET_CONTEXT);
+            if let Some((_, _, input)) = reset {
+                let reason = input["reason"].as_str().filter(|r| !r.trim().is_empty());
+                info!("Resetting context: {}", reason.unwrap_or("no reason given"));
+                messages.clear();
+                let confirmation = match reason {
+                    Some(reason) => format!(
+                        "The conversation history was cleared at your request. Your note: {reason}"
+                    ),
+                    None => "The conversation history was cleared at your request.".to_string(),
+                };
+                messages.push(user_message(&confirmation));
+                emit(tx_event, AgentEvent::StatusChange(AgentStatus::Idle)).await;
+                emit(tx_event, AgentEvent::StatusChange(AgentStatus::Working)).await;
+                continue;
+            }
+
+            info!("Executing {} tool call(s)", tool_uses.len());
+
+            // Parallel execution: read-only tools share a read lock,
+            // write tools take an exclusive write lock.
+            let lock = Arc::new(RwLock::new(()));
+            let mut handles = Vec::new();
+
+            for (_id, name, input) in &tool_uses {
+                metrics.inc_tool_calls(name);
+                let offered = tool_defs.iter().any(|def| def.name == *name);
+                let lock = lock.clone();
+                let name = name.clone();
+                let input = input.clone();
+                let tool_ctx = tool_ctx.clone();
+                let tx_event = tx_event.clone();
+
+                handles.push(tokio::spawn(async move {
+                    // A tool left out of the request (e.g. one disabled in the
+                    // config) must not run even if the model asks for it
+                    if !offered {
+                        anyhow::bail!("Tool {name} is not available");
+                    }
+                    let (_read, _write);
+                    if tools::supports_parallel(&name, &input) {
+                        _read = lock.read().await;
+                    } else {
+                        _write = lock.write().await;
+                    }
+                    let input_summary = input_summary(&input);
+                    emit(&tx_event, AgentEvent::ToolStart { name: name.clone(), input_summary }).await;
+                    let start = Instant::now();
+                    // Dropping the call on cancel also kills its child processes
+                    let result = tokio::select! {
+                        result = tools::execute_tool(&name, &input, &tool_ctx) => result,
+                        _ = tool_ctx.cancel.cancelled() => Err(Cancelled.into()),
+                    };
+                    let duration_ms = start.elapsed().as_millis() as u64;
+                    let is_error = result.is_err();
+                    let end = AgentEvent::ToolEnd { name: name.clone(), duration_ms, is_error };
+                    emit(&tx_event, end).await;
+                    let error_text;
+                    let output = match &result {
+                        Ok(out) => out.as_str(),
+                        Err(e) => {
+                            error_text = format!("Error: {e}");
+                            error_text.as_str()
+                        }
+                    };
                     emit(
-                        tx_event,
-                        AgentEvent::ContentBlockStop {
-                            index: block_index,
+                        &tx_event,
+                        AgentEvent::ToolResult {
+                            tool_name: name,
+                            output_preview: output.chars().take(RESULT_PREVIEW_CHARS).collect(),
+                            line_count: output.lines().count(),
+                            is_error,
                         },
                     )
                     .await;
-                    if !current_text.is_empty() {
-                        assistant_content.push(ContentBlock::Text {
-                            text: current_text.clone(),
-                        });
-                        current_text.clear();
-                    }
-                    if let Some(mut block) = current_tool_block.take() {
-                        if let ContentBlock::ToolUse { ref mut input, .. } = block {
-                            *input = serde_json::from_str(&current_tool_json)
-                                .unwrap_or(serde_json::Value::Object(Default::default()));
-                        }
-                        assistant_content.push(block);
-                        current_tool_json.clear();
-                    }
-                    block_index += 1;
-                }
-                crate::ai::StreamEvent::MessageDelta {
-                    stop_reason: sr, ..
-                } => {
-                    stop_reason = sr.clone();
-                    emit(tx_event, AgentEvent::MessageDelta { stop_reason: sr }).await;
-                }
-                crate::ai::StreamEvent::MessageStop => {
-                    emit(tx_event, AgentEvent::MessageStop).await;
-                    break;
-                }
+                    result
+                }));
             }
-        }
 
-        messages.push(Message {
-            role: "assistant".to_string(),
-            content: assistant_content.clone(),
-        });
+            let join_results = futures_util::future::join_all(handles).await;
 
-        let tool_uses: Vec<_> = assistant_content
-            .iter()
-            .filter_map(|b| match b {
-                ContentBlock::ToolUse { id, name, input } => {
-                    Some((id.clone(), name.clone(), input.clone()))
-                }
-                _ => None,
-            })
-            .collect();
-
-        if tool_uses.is_empty()
-            || stop_reason.as_deref() != Some(crate::ai::STOP_REASON_TOOL_USE)
-        {
-            return Ok(());
-        }
-
-        info!("Executing {} tool call(s)", tool_uses.len());
-
-        // Parallel execution: read-only tools share a read lock,
-        // write tools take an exclusive write lock.
-        let lock = Arc::new(RwLock::new(()));
-        let mut handles = Vec::new();
-
-        for (_id, name, input) in &tool_uses {
-            let lock = lock.clone();
-            let name = name.clone();
-            let input = input.clone();
-            let workspace = workspace.to_string();
-            let shell = shell.clone();
-
-            handles.push(tokio::spawn(async move {
-                if tools::supports_parallel(&name) {
-                    let _g = lock.read().await;
-                    tools::execute_tool(&name, &input, &workspace, &shell).await
-                } else {
-                    let _g = lock.write().await;
-                    tools::execute_tool(&name, &input, &workspace, &shell).await
-                }
-            }));
+            let mut tool_results = Vec::new();
+            for ((id, name, _), join_result) in tool_uses.iter().zip(join_results) {
+                let result =
+                    join_result.map_err(|e| anyhow::anyhow!("Task join error: {e}"))?;
+                let (output, is_error) = match result {
+                    Ok(out) => {
+                        info!("Tool {name} succeeded, {} bytes", out.len());
+                        (out, false)
+                    }
+                    Err(e) => {
+                        warn!("Tool {name} failed: {e}");
+                        (format!("Error: {e}"), true)
+                    }
+                };
+                let result_block = tool_result_block(id, &output, is_error);
+                emit(
+                    tx_event,
+                    AgentEvent::ContentBlockStart {
+                        index: block_index,
+                        content_block: result_block.clone(),
+                    },
+                )
+                .await;
+                emit(
+                    tx_event,
+                    AgentEvent::ContentBlockStop {
+                        index: block_index,
+                    },
+                )
+                .await;
+                block_index += 1;
+                tool_results.push(tool_result_block(id, &output, is_error));
+            }
+            messages.push(user_message_with_tool_results(tool_results));
+            if cancel.is_cancelled() {
+                return Err(Cancelled.into());
+            }
         }
+    }
+}
 
-        let join_results = futures_util::future::join_all(handles).await;
-
-        let mut tool_results = Vec::new();
-        for ((id, name, _), join_result) in tool_uses.iter().zip(join_results) {
-            let result =
-                join_result.map_err(|e| anyhow::anyhow!("Task join error: {e}"))?;
-            let (output, is_error) = match result {
-                Ok(out) => {
-                    info!("Tool {name} succeeded, {} bytes", out.len());
-                    (out, false)
-                }
-                Err(e) => {
-                    warn!("Tool {name} failed: {e}");
-                    (format!("Error: {e}"), true)
-                }
-            };
-            let result_block = tool_result_block(id, &output, is_error);
-            emit(
-                tx_event,
-                AgentEvent::ContentBlockStart {
-                    index: block_index,
-                    content_block: result_block.clone(),
-                },
-            )
-            .await;
-            emit(
-                tx_event,
-                AgentEvent::ContentBlockStop {
-                    index: block_index,
-                },
-            )
-            .await;
-            block_index += 1;
-            tool_results.push(tool_result_block(id, &output, is_error));
+/// Open the response stream, waiting out rate limits. Each 429 is reported
+/// to the frontend as `RateLimited` before sleeping.
+async fn stream_with_retry(
+    client: &AnthropicClient,
+    request: CreateMessageRequest,
+    tx_event: &mpsc::Sender<AgentEvent>,
+) -> Result<mpsc::Receiver<StreamEvent>> {
+    let mut retries = 0;
+    loop {
+        match client.stream_message(request.clone()).await {
+            Err(e) if retries < MAX_RATE_LIMIT_RETRIES => {
+                let Some(retry_after_ms) = e.downcast_ref::<RateLimited>().map(|r| r.retry_after_ms)
+                else {
+                    return Err(e);
+                };
+                retries += 1;
+                warn!("Rate limited, retrying in {retry_after_ms}ms ({retries}/{MAX_RATE_LIMIT_RETRIES})");
+                emit(tx_event, AgentEvent::RateLimited { retry_after_ms }).await;
+                tokio::time::sleep(std::time::Duration::from_millis(retry_after_ms)).await;
+            }
+            result => return result,
         }
-        messages.push(user_message_with_tool_results(tool_results));
     }
 }
 
@@ -1,25 +1,30 @@
+use std::collections::VecDeque;
+
 use anyhow::Result;
 use async_trait::async_trait;
 use serde_json::Value;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 use tracing::{error, info, warn};
 
 use crate::config::ClaudeEnv;
 use crate::protocol::{AgentEvent, AgentStatus, ContentBlock, Submission};
+use crate::thread_manager::ThreadContext;
 
-use super::Agent;
+use super::{next_submission, run_turn, Agent, Cancelled};
 
 pub struct ClaudeAgent {
     config: ClaudeEnv,
     workspace: String,
     has_feishu: bool,
+    context: ThreadContext,
 }
 
 impl ClaudeAgent {
-    pub fn new(config: ClaudeEnv, workspace: String, has_feishu: bool) -> Self {
-        Self { config, workspace, has_feishu }
+    pub fn new(config: ClaudeEnv, workspace: String, has_feishu: bool, context: ThreadContext) -> Self {
+        Self { config, workspace, has_feishu, context }
     }
 }
 
@@ -34,8 +39,14 @@ impl Agent for ClaudeAgent {
         mut rx_sub: mpsc::Receiver<Submission>,
         tx_event: mpsc::Sender<AgentEvent>,
     ) {
-        while let Some(sub) = rx_sub.recv().await {
+        let mut turn_suffix: Option<String> = None;
+        let mut pending = VecDeque::new();
+        while let Some(sub) = next_submission(&mut rx_sub, &mut pending).await {
             let prompt = match sub {
+                Submission::Configure { system_suffix } => {
+                    turn_suffix = Some(system_suffix);
+                    continue;
+                }
                 Submission::UserMessage(text) | Submission::FollowUp(text) => text,
                 Submission::Cancel => {
                     emit(&tx_event, AgentEvent::StatusChange(AgentStatus::Cancelled)).await;
@@ -46,7 +57,16 @@ impl Agent for ClaudeAgent {
 
             emit(&tx_event, AgentEvent::StatusChange(AgentStatus::Working)).await;
 
-            match run_claude_process(&prompt, &self.config, &self.workspace, self.has_feishu, &tx_event).await {
+            let suffix = turn_suffix.take();
+            let cancel = CancellationToken::new();
+            // Dropping the process future on cancel kills `claude`
+            let turn = async {
+                tokio::select! {
+                    result = run_claude_process(&prompt, &self.config, &self.workspace, self.has_feishu, &self.context, suffix.as_deref(), &tx_event) => result,
+                    _ = cancel.cancelled() => Err(Cancelled.into()),
+                }
+            };
+            match run_turn(turn, &mut rx_sub, &cancel, &mut pending).await {
                 Ok(()) => {
                     info!("Claude agent completed");
                     emit(
@@ -55,6 +75,10 @@ impl Agent for ClaudeAgent {
                     )
                     .await;
                 }
+                Err(e) if e.is::<Cancelled>() => {
+                    info!("Claude agent cancelled");
+                    emit(&tx_event, AgentEvent::StatusChange(AgentStatus::Cancelled)).await;
+                }
                 Err(e) => {
                     error!("Claude agent error: {e}");
                     emit(&tx_event, AgentEvent::Error(e.to_string())).await;
@@ -76,13 +100,15 @@ For Feishu operations, use:\n\
 When the user mentions a file, use `myagent feishu files` with the chat_id from the context \
 to find the file_key and message_id, then download it.\n\
 You can proactively send messages to notify the user of important results or task completion.\n\
-The chat_id is available in the <feishu_context> tag of each message.";
+The chat_id and the user's open_id are in the <feishu_context> tag below.";
 
 async fn run_claude_process(
     prompt: &str,
     config: &ClaudeEnv,
     workspace: &str,
     has_feishu: bool,
+    context: &ThreadContext,
+    suffix: Option<&str>,
     tx_event: &mpsc::Sender<AgentEvent>,
 ) -> Result<()> {
     let mut cmd = Command::new("claude");
@@ -97,8 +123,16 @@ async fn run_claude_process(
         .stdin(std::process::Stdio::null())
         .kill_on_drop(true)
         .current_dir(workspace);
+    let mut append = context.to_prompt();
     if has_feishu {
-        cmd.arg("--append-system-prompt").arg(FEISHU_SYSTEM_PROMPT);
+        append = format!("{FEISHU_SYSTEM_PROMPT}\n\n{append}");
+    }
+    if let Some(suffix) = suffix {
+        append = format!("{append}\n\n{suffix}");
+    }
+    cmd.arg("--append-system-prompt").arg(append);
+    if let Some(max_turns) = config.max_turns {
+        cmd.arg("--max-turns").arg(max_turns.to_string());
     }
     if let Some(base_url) = &config.base_url {
         cmd.env("ANTHROPIC_BASE_URL", base_url);
@@ -307,6 +341,15 @@ async fn handle_result(json: &Value, tx_event: &mpsc::Sender<AgentEvent>) {
             "Claude result: {subtype}, turns={num_turns}, duration={duration}ms, cost=${cost:.4}"
         );
     }
+    emit(
+        tx_event,
+        AgentEvent::Usage {
+            input_tokens: json["usage"]["input_tokens"].as_u64().unwrap_or(0),
+            output_tokens: json["usage"]["output_tokens"].as_u64().unwrap_or(0),
+            cost_usd: json["total_cost_usd"].as_f64(),
+        },
+    )
+    .await;
     // "success" is handled by the Agent::run method after run_claude_process returns Ok
 }
 
@@ -1,8 +1,13 @@
 pub mod ai;
 pub mod claude;
 
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+
 use async_trait::async_trait;
 use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 
 use crate::protocol::{AgentEvent, Submission};
 
@@ -21,3 +26,53 @@ pub trait Agent: Send + 'static {
         tx_event: mpsc::Sender<AgentEvent>,
     );
 }
+
+/// A turn stopped by `Submission::Cancel` before it finished.
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Turn cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Await a turn while still reading the SQ: a `Cancel` or a closed SQ fires
+/// `cancel`, which the turn is expected to notice and return `Cancelled` for.
+/// Anything else that arrives meanwhile, `Shutdown` included, is queued in
+/// `pending` for after the turn.
+async fn run_turn(
+    turn: impl Future<Output = anyhow::Result<()>>,
+    rx_sub: &mut mpsc::Receiver<Submission>,
+    cancel: &CancellationToken,
+    pending: &mut VecDeque<Submission>,
+) -> anyhow::Result<()> {
+    tokio::pin!(turn);
+    loop {
+        tokio::select! {
+            result = &mut turn => return result,
+            sub = rx_sub.recv(), if !cancel.is_cancelled() => match sub {
+                Some(Submission::Cancel) => cancel.cancel(),
+                None => {
+                    cancel.cancel();
+                    pending.push_back(Submission::Shutdown);
+                }
+                Some(sub) => pending.push_back(sub),
+            },
+        }
+    }
+}
+
+/// The next submission: queued ones first, then the SQ. `None` once the SQ
+/// is closed.
+async fn next_submission(
+    rx_sub: &mut mpsc::Receiver<Submission>,
+    pending: &mut VecDeque<Submission>,
+) -> Option<Submission> {
+    match pending.pop_front() {
+        Some(sub) => Some(sub),
+        None => rx_sub.recv().await,
+    }
+}
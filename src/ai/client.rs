@@ -1,3 +1,8 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
 use anyhow::{Context, Result};
 use futures_util::StreamExt;
 use reqwest::Client;
@@ -7,54 +12,189 @@ use tracing::{debug, warn};
 use super::types::*;
 
 const API_VERSION: &str = "2023-06-01";
+const PROMPT_CACHING_BETA: &str = "prompt-caching-2024-07-31";
+
+/// Wait used when a 429 response has no usable retry-after header.
+const DEFAULT_RETRY_AFTER_MS: u64 = 5000;
+
+/// Longest part of an SSE chunk logged with `MYAGENT_DEBUG_HTTP`.
+const MAX_DEBUG_CHUNK_BYTES: usize = 4096;
+
+/// Error returned when the API answers 429 Too Many Requests.
+#[derive(Debug)]
+pub struct RateLimited {
+    pub retry_after_ms: u64,
+}
+
+impl std::fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Anthropic API rate limited, retry after {}ms", self.retry_after_ms)
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+/// Staggers the first key each new client uses, so threads don't all start on key 0.
+static CLIENT_KEY_OFFSET: AtomicUsize = AtomicUsize::new(0);
 
 pub struct AnthropicClient {
     http: Client,
-    api_key: String,
+    /// API keys used round-robin; a rate-limited or rejected key is skipped.
+    keys: Arc<Vec<String>>,
+    next_key: AtomicUsize,
     base_url: String,
+    prompt_caching: bool,
+    timeout_ms: u64,
+    /// Sent with every request in addition to the auth headers.
+    headers: HashMap<String, String>,
 }
 
 impl AnthropicClient {
-    pub fn new(api_key: &str, base_url: &str) -> Self {
+    /// Use a preconfigured HTTP client (e.g. one routed through a proxy).
+    pub fn with_http_client(http: Client, api_key: &str, base_url: &str) -> Self {
         Self {
-            http: Client::new(),
-            api_key: api_key.to_string(),
+            http,
+            keys: Arc::new(vec![api_key.to_string()]),
+            next_key: AtomicUsize::new(CLIENT_KEY_OFFSET.fetch_add(1, Ordering::Relaxed)),
             base_url: base_url.to_string(),
+            prompt_caching: false,
+            timeout_ms: crate::config::DEFAULT_API_TIMEOUT_MS,
+            headers: HashMap::new(),
         }
     }
 
-    /// Send a streaming messages request.
-    /// Parsed SSE events are sent to the returned channel.
-    pub async fn stream_message(
-        &self,
-        request: CreateMessageRequest,
-    ) -> Result<mpsc::Receiver<StreamEvent>> {
-        let (tx, rx) = mpsc::channel(256);
+    /// Rotate between several API keys instead of the single one given to `new`.
+    pub fn with_api_keys(mut self, keys: Vec<String>) -> Self {
+        if !keys.is_empty() {
+            self.keys = Arc::new(keys);
+        }
+        self
+    }
 
+    /// Give up on sending a request after `timeout_ms`.
+    pub fn with_timeout_ms(mut self, timeout_ms: u64) -> Self {
+        self.timeout_ms = timeout_ms;
+        self
+    }
+
+    /// Send the prompt-caching beta header with every request.
+    pub fn with_prompt_caching(mut self, enabled: bool) -> Self {
+        self.prompt_caching = enabled;
+        self
+    }
+
+    /// Add headers to every request, e.g. OpenRouter's `HTTP-Referer` and `X-Title`.
+    pub fn with_headers(mut self, headers: HashMap<String, String>) -> Self {
+        self.headers.extend(headers);
+        self
+    }
+
+    /// Build a POST to the messages endpoint with the right auth headers.
+    fn messages_request(&self, api_key: &str) -> reqwest::RequestBuilder {
         let url = format!("{}/v1/messages", self.base_url.trim_end_matches('/'));
         let is_anthropic = self.base_url.contains("anthropic.com");
 
-        let mut req = self.http.post(&url);
-        if is_anthropic {
-            req = req
-                .header("x-api-key", &self.api_key)
-                .header("anthropic-version", API_VERSION);
+        let req = self.http.post(&url);
+        let req = if is_anthropic {
+            req.header("x-api-key", api_key)
+                .header("anthropic-version", API_VERSION)
+        } else {
+            req.header("authorization", format!("Bearer {api_key}"))
+        };
+        let mut req = if self.prompt_caching {
+            req.header("anthropic-beta", PROMPT_CACHING_BETA)
         } else {
-            req = req.header("authorization", format!("Bearer {}", self.api_key));
+            req
+        };
+        for (name, value) in &self.headers {
+            req = req.header(name, value);
         }
+        req.header("content-type", "application/json")
+    }
 
-        let resp = req
-            .header("content-type", "application/json")
-            .json(&request)
-            .send()
-            .await
-            .context("Failed to send request to AI API")?;
+    /// POST the request with the next key in rotation. A 429 or auth error
+    /// moves on to the next key; the last key's response is returned as-is.
+    async fn send(&self, request: &CreateMessageRequest) -> Result<reqwest::Response> {
+        let attempts = self.keys.len();
+        for attempt in 0..attempts {
+            let key = &self.keys[self.next_key.fetch_add(1, Ordering::Relaxed) % attempts];
+            debug!("Using API key {}...", key_prefix(key));
+            let resp = self.send_with_key(request, key).await?;
 
-        if !resp.status().is_success() {
             let status = resp.status();
-            let body = resp.text().await.unwrap_or_default();
-            anyhow::bail!("Anthropic API error {status}: {body}");
+            let rejected = status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                || status == reqwest::StatusCode::UNAUTHORIZED
+                || status == reqwest::StatusCode::FORBIDDEN;
+            if rejected && attempt + 1 < attempts {
+                warn!("API key {}... rejected ({status}), trying next key", key_prefix(key));
+                continue;
+            }
+            return Ok(resp);
         }
+        anyhow::bail!("No API key configured")
+    }
+
+    /// POST the request, failing if no response arrives within the timeout.
+    async fn send_with_key(
+        &self,
+        request: &CreateMessageRequest,
+        api_key: &str,
+    ) -> Result<reqwest::Response> {
+        let req = self
+            .messages_request(api_key)
+            .json(request)
+            .build()
+            .context("Failed to build AI API request")?;
+        if debug_http() {
+            debug!(
+                url = %req.url(),
+                headers = %masked_headers(req.headers()),
+                request_body = %serde_json::to_string(request)?,
+                "AI API request"
+            );
+        }
+        let send = self.http.execute(req);
+        match tokio::time::timeout(Duration::from_millis(self.timeout_ms), send).await {
+            Ok(resp) => resp.context("Failed to send request to AI API"),
+            Err(_) => Err(anyhow::anyhow!(
+                "Anthropic API request timed out after {}ms",
+                self.timeout_ms
+            )),
+        }
+    }
+
+    /// Send a non-streaming messages request and wait for the full response.
+    /// Returns the content blocks, the stop reason and the token usage.
+    pub async fn send_message(
+        &self,
+        mut request: CreateMessageRequest,
+    ) -> Result<(Vec<ContentBlock>, String, Usage)> {
+        request.stream = false;
+        let resp = check_status(self.send(&request).await?).await?;
+
+        let body = resp.text().await.context("Failed to read AI API response")?;
+        if debug_http() {
+            debug!(response_body = %body, "AI API response");
+        }
+        let response: AnthropicResponse =
+            serde_json::from_str(&body).context("Failed to parse AI API response")?;
+        debug!(
+            "Message complete: {} input / {} output tokens",
+            response.usage.input_tokens, response.usage.output_tokens
+        );
+        Ok((response.content, response.stop_reason, response.usage))
+    }
+
+    /// Send a streaming messages request.
+    /// Parsed SSE events are sent to the returned channel.
+    pub async fn stream_message(
+        &self,
+        request: CreateMessageRequest,
+    ) -> Result<mpsc::Receiver<StreamEvent>> {
+        let (tx, rx) = mpsc::channel(256);
+
+        let resp = check_status(self.send(&request).await?).await?;
+        let debug = debug_http();
 
         // Spawn a task to read SSE events from the response body
         tokio::spawn(async move {
@@ -69,6 +209,10 @@ impl AnthropicClient {
                         break;
                     }
                 };
+                if debug {
+                    let shown = &chunk[..chunk.len().min(MAX_DEBUG_CHUNK_BYTES)];
+                    debug!(chunk = %String::from_utf8_lossy(shown), "AI API SSE chunk");
+                }
                 buffer.push_str(&String::from_utf8_lossy(&chunk));
 
                 // Process complete SSE events from buffer
@@ -93,6 +237,69 @@ impl AnthropicClient {
     }
 }
 
+/// Turn a non-success response into an error; 429 becomes [`RateLimited`].
+async fn check_status(resp: reqwest::Response) -> Result<reqwest::Response> {
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(RateLimited {
+            retry_after_ms: retry_after_ms(resp.headers()),
+        }
+        .into());
+    }
+    let body = resp.text().await.unwrap_or_default();
+    anyhow::bail!("Anthropic API error {status}: {body}");
+}
+
+/// Delay requested by `retry-after-ms` or `retry-after` (seconds).
+fn retry_after_ms(headers: &reqwest::header::HeaderMap) -> u64 {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<f64>().ok())
+    };
+    header("retry-after-ms")
+        .or_else(|| header("retry-after").map(|secs| secs * 1000.0))
+        .map(|ms| ms as u64)
+        .unwrap_or(DEFAULT_RETRY_AFTER_MS)
+}
+
+/// Whether `MYAGENT_DEBUG_HTTP` asks for request and response bodies to be
+/// logged. Always off in release builds so bodies can't leak from production.
+#[cfg(debug_assertions)]
+fn debug_http() -> bool {
+    std::env::var("MYAGENT_DEBUG_HTTP").is_ok()
+}
+
+#[cfg(not(debug_assertions))]
+fn debug_http() -> bool {
+    false
+}
+
+/// Request headers for the debug log, with API keys masked.
+fn masked_headers(headers: &reqwest::header::HeaderMap) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = match name.as_str() {
+                "authorization" => "Bearer ***...",
+                "x-api-key" => "***...",
+                _ => value.to_str().unwrap_or("<binary>"),
+            };
+            format!("{name}: {value}")
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// First 8 characters of a key, safe to log.
+fn key_prefix(key: &str) -> String {
+    key.chars().take(8).collect()
+}
+
 /// Parse a single SSE event block into a StreamEvent.
 fn parse_sse_event(raw: &str) -> Option<StreamEvent> {
     let mut event_type = String::new();
@@ -149,10 +356,20 @@ fn parse_sse_event(raw: &str) -> Option<StreamEvent> {
             let stop_reason = json["delta"]["stop_reason"]
                 .as_str()
                 .map(|s| s.to_string());
-            Some(StreamEvent::MessageDelta { stop_reason })
+            let output_tokens = json["usage"]["output_tokens"].as_u64().map(|n| n as u32);
+            Some(StreamEvent::MessageDelta {
+                stop_reason,
+                output_tokens,
+            })
         }
         "message_stop" => Some(StreamEvent::MessageStop),
-        "message_start" | "ping" => None,
+        "message_start" => {
+            let input_tokens = json["message"]["usage"]["input_tokens"].as_u64().unwrap_or(0);
+            Some(StreamEvent::MessageStart {
+                input_tokens: input_tokens as u32,
+            })
+        }
+        "ping" => None,
         other => {
             debug!("Unknown SSE event type: {other}");
             None
@@ -173,3 +390,55 @@ fn parse_content_block(val: &serde_json::Value) -> Option<ContentBlock> {
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn send_message_posts_request_and_parses_response() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/v1/messages")
+            .match_header("authorization", "Bearer test-key")
+            .match_header("content-type", "application/json")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "model": "test-model",
+                "max_tokens": 64,
+                "stream": false,
+                "messages": [{ "role": "user", "content": [{ "type": "text", "text": "hi" }] }],
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                serde_json::json!({
+                    "content": [{ "type": "text", "text": "hello" }],
+                    "stop_reason": "end_turn",
+                    "usage": { "input_tokens": 3, "output_tokens": 1 },
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let client = AnthropicClient::with_http_client(Client::new(), "test-key", &server.url());
+        let request = CreateMessageRequest {
+            model: "test-model".to_string(),
+            max_tokens: 64,
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: vec![ContentBlock::Text { text: "hi".to_string() }],
+            }],
+            tools: Vec::new(),
+            // send_message must turn streaming off
+            stream: true,
+            system: None,
+        };
+        let (content, stop_reason, usage) = client.send_message(request).await.unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(stop_reason, "end_turn");
+        assert_eq!((usage.input_tokens, usage.output_tokens), (3, 1));
+        assert!(matches!(content.as_slice(), [ContentBlock::Text { text }] if text == "hello"));
+    }
+}
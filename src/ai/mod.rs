@@ -1,5 +1,5 @@
 pub mod client;
 pub mod types;
 
-pub use client::AnthropicClient;
+pub use client::{AnthropicClient, RateLimited};
 pub use types::*;
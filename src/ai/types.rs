@@ -1,4 +1,4 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 // Re-export core types from protocol
 pub use crate::protocol::{ContentBlock, Message};
@@ -12,7 +12,7 @@ pub struct ToolDef {
 }
 
 /// Request body for the Messages API.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct CreateMessageRequest {
     pub model: String,
     #[serde(default = "default_max_tokens")]
@@ -22,7 +22,72 @@ pub struct CreateMessageRequest {
     pub tools: Vec<ToolDef>,
     pub stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub system: Option<String>,
+    pub system: Option<SystemPrompt>,
+}
+
+/// The system prompt. Serialized as a plain string, or as a single text
+/// block carrying `cache_control` when prompt caching is requested.
+#[derive(Debug, Clone)]
+pub struct SystemPrompt {
+    pub text: String,
+    pub cache_control: Option<CacheControl>,
+}
+
+impl SystemPrompt {
+    pub fn new(text: impl Into<String>, cache: bool) -> Self {
+        Self {
+            text: text.into(),
+            cache_control: cache.then_some(CacheControl::Ephemeral),
+        }
+    }
+}
+
+impl Serialize for SystemPrompt {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct TextBlock<'a> {
+            #[serde(rename = "type")]
+            kind: &'static str,
+            text: &'a str,
+            cache_control: &'a CacheControl,
+        }
+
+        match &self.cache_control {
+            None => serializer.serialize_str(&self.text),
+            Some(cache_control) => [TextBlock {
+                kind: "text",
+                text: &self.text,
+                cache_control,
+            }]
+            .serialize(serializer),
+        }
+    }
+}
+
+/// Anthropic prompt-cache marker, e.g. `{"type": "ephemeral"}`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum CacheControl {
+    Ephemeral,
+}
+
+/// Response body of a non-streaming Messages API call.
+#[derive(Debug, Deserialize)]
+pub struct AnthropicResponse {
+    pub content: Vec<ContentBlock>,
+    #[serde(default)]
+    pub stop_reason: String,
+    #[serde(default)]
+    pub usage: Usage,
+}
+
+/// Token accounting reported by the API.
+#[derive(Debug, Default, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    pub input_tokens: u32,
+    #[serde(default)]
+    pub output_tokens: u32,
 }
 
 fn default_max_tokens() -> u32 {
@@ -32,6 +97,9 @@ fn default_max_tokens() -> u32 {
 /// Streamed SSE event types from the Messages API.
 #[derive(Debug, Clone)]
 pub enum StreamEvent {
+    MessageStart {
+        input_tokens: u32,
+    },
     ContentBlockStart {
         index: usize,
         content_block: ContentBlock,
@@ -49,6 +117,7 @@ pub enum StreamEvent {
     },
     MessageDelta {
         stop_reason: Option<String>,
+        output_tokens: Option<u32>,
     },
     MessageStop,
 }
@@ -0,0 +1,121 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+use tracing::warn;
+
+use crate::config;
+
+/// Longest tool input kept in an audit entry.
+const MAX_INPUT_CHARS: usize = 500;
+
+/// Appends one JSON line per tool call to `~/.myagent/logs/audit.jsonl`.
+pub struct AuditLogger {
+    path: PathBuf,
+    thread_id: String,
+}
+
+#[derive(Serialize)]
+struct AuditEntry<'a> {
+    timestamp: String,
+    thread_id: &'a str,
+    tool_name: &'a str,
+    tool_input: String,
+    work_dir: &'a str,
+    exit_code: Option<i32>,
+    output_truncated: bool,
+    duration_ms: u64,
+}
+
+#[derive(Serialize)]
+struct UpdateEntry<'a> {
+    timestamp: String,
+    thread_id: &'a str,
+    event: &'static str,
+    from_version: &'a str,
+    to_version: &'a str,
+    status: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+}
+
+/// Outcome of a finished tool call, as recorded in the audit log.
+pub struct ToolCallRecord<'a> {
+    pub tool_name: &'a str,
+    pub tool_input: &'a serde_json::Value,
+    pub work_dir: &'a str,
+    pub exit_code: Option<i32>,
+    pub output_truncated: bool,
+    pub duration_ms: u64,
+}
+
+/// A step of an automatic update, as recorded in the audit log.
+pub struct UpdateRecord<'a> {
+    pub from_version: &'a str,
+    pub to_version: &'a str,
+    /// "available", "installed", "skipped" or "failed".
+    pub status: &'a str,
+    pub error: Option<&'a str>,
+}
+
+impl AuditLogger {
+    pub fn new(thread_id: &str) -> Self {
+        Self {
+            path: config::log_dir().join("audit.jsonl"),
+            thread_id: thread_id.to_string(),
+        }
+    }
+
+    /// Record a tool call. Failures are logged, never returned to the agent.
+    pub async fn record(&self, call: &ToolCallRecord<'_>) {
+        let entry = AuditEntry {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            thread_id: &self.thread_id,
+            tool_name: call.tool_name,
+            tool_input: call.tool_input.to_string().chars().take(MAX_INPUT_CHARS).collect(),
+            work_dir: call.work_dir,
+            exit_code: call.exit_code,
+            output_truncated: call.output_truncated,
+            duration_ms: call.duration_ms,
+        };
+        self.write(&entry).await;
+    }
+
+    /// Record a step of an automatic update.
+    pub async fn record_update(&self, update: &UpdateRecord<'_>) {
+        let entry = UpdateEntry {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            thread_id: &self.thread_id,
+            event: "auto_update",
+            from_version: update.from_version,
+            to_version: update.to_version,
+            status: update.status,
+            error: update.error,
+        };
+        self.write(&entry).await;
+    }
+
+    async fn write(&self, entry: &impl Serialize) {
+        if let Err(e) = self.append(entry).await {
+            warn!("Failed to write audit log {}: {e}", self.path.display());
+        }
+    }
+
+    async fn append(&self, entry: &impl Serialize) -> Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        if let Some(dir) = self.path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        // One write per line so concurrent threads don't interleave entries
+        file.write_all(line.as_bytes()).await?;
+        Ok(())
+    }
+}
@@ -1,20 +1,34 @@
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
 use anyhow::{bail, Result};
 use serde_json::Value;
 
 use crate::config;
+use crate::update_check::CURRENT_VERSION;
 use crate::ConfigAction;
 
+/// Envelope key that marks a file written by `config export`.
+const EXPORT_KEY: &str = "myagent_export";
+
 pub fn run(action: &ConfigAction, config_path: &PathBuf) -> Result<()> {
     match action {
         ConfigAction::Init => cmd_init(config_path),
         ConfigAction::Show => cmd_show(config_path),
+        ConfigAction::List => cmd_list(config_path),
         ConfigAction::Set { key, value } => cmd_set(config_path, key, value),
+        ConfigAction::Get { key, raw } => cmd_get(config_path, key, *raw),
+        ConfigAction::Alias { name, template } => cmd_alias(config_path, name, template),
         ConfigAction::Path => {
             println!("{}", config_path.display());
             Ok(())
         }
+        ConfigAction::Export {
+            output,
+            include_secrets,
+        } => cmd_export(config_path, output.as_deref(), *include_secrets),
+        ConfigAction::Import { input, merge } => cmd_import(config_path, input, *merge),
+        ConfigAction::ImportEnv { path } => cmd_import_env(config_path, path),
     }
 }
 
@@ -62,22 +76,406 @@ fn cmd_show(config_path: &PathBuf) -> Result<()> {
     Ok(())
 }
 
+/// A documented config key, shown by `myagent config list`.
+struct ConfigKey {
+    path: &'static str,
+    type_name: &'static str,
+    description: &'static str,
+}
+
+const CONFIG_KEYS: &[ConfigKey] = &[
+    ConfigKey { path: "port", type_name: "u16", description: "Health/RPC server port" },
+    ConfigKey { path: "health_bind", type_name: "string", description: "Health server bind address (127.0.0.1)" },
+    ConfigKey { path: "workspace", type_name: "string", description: "Working directory for serve mode" },
+    ConfigKey { path: "workspace_auto_detect", type_name: "bool", description: "Use the git root as workspace when unset" },
+    ConfigKey { path: "default_agent", type_name: "string", description: "Agent used when none is given (myagent, claude)" },
+    ConfigKey { path: "shutdown_timeout_secs", type_name: "u64", description: "Seconds to let threads finish on shutdown" },
+    ConfigKey { path: "log_format", type_name: "string", description: "Log output format: text or json" },
+    ConfigKey { path: "log_filters", type_name: "string", description: "Log filter directives when RUST_LOG is unset" },
+    ConfigKey { path: "audit_log", type_name: "bool", description: "Write tool calls to logs/audit.jsonl" },
+    ConfigKey { path: "extra_path", type_name: "[string]", description: "Dirs prepended to PATH for shell commands" },
+    ConfigKey { path: "allowed_hosts", type_name: "[string]", description: "Hosts the remote_shell tool may ssh into" },
+    ConfigKey { path: "allow_clipboard_tool", type_name: "bool", description: "Let the agent read and write the clipboard" },
+    ConfigKey { path: "aliases", type_name: "{string}", description: "Prompt shorthands; {args} is what follows" },
+    ConfigKey { path: "update_channel", type_name: "string", description: "Update release channel: stable or beta" },
+    ConfigKey { path: "auto_update", type_name: "bool", description: "Install new releases and restart (serve)" },
+    ConfigKey { path: "proxy.http_url", type_name: "string", description: "Proxy for HTTP requests" },
+    ConfigKey { path: "proxy.https_url", type_name: "string", description: "Proxy for HTTPS requests" },
+    ConfigKey { path: "proxy.no_proxy", type_name: "string", description: "Comma-separated hosts that bypass the proxy" },
+    ConfigKey { path: "agents.myagent.timeout_ms", type_name: "u64", description: "Default shell and remote_shell timeout in milliseconds" },
+    ConfigKey { path: "agents.myagent.env.MYAGENT_API_KEY", type_name: "string", description: "API key for the built-in agent" },
+    ConfigKey { path: "agents.myagent.env.MYAGENT_API_KEYS", type_name: "string", description: "Comma-separated keys to rotate between" },
+    ConfigKey { path: "agents.myagent.env.MYAGENT_BASE_URL", type_name: "string", description: "Messages API base URL" },
+    ConfigKey { path: "agents.myagent.env.MYAGENT_MODEL", type_name: "string", description: "Model for the built-in agent" },
+    ConfigKey { path: "agents.myagent.env.MYAGENT_PROMPT_CACHING", type_name: "bool", description: "Cache the system prompt (Anthropic)" },
+    ConfigKey { path: "agents.myagent.env.MYAGENT_API_TIMEOUT_MS", type_name: "u64", description: "API request timeout in milliseconds" },
+    ConfigKey { path: "agents.myagent.env.MYAGENT_MAX_TURNS", type_name: "u32", description: "API round trips per message (default 50)" },
+    ConfigKey { path: "agents.myagent.env.MYAGENT_SYSTEM_PROMPT_FILE", type_name: "string", description: "File replacing the built-in base system prompt" },
+    ConfigKey { path: "agents.myagent.env.MYAGENT_HTTP_REFERER", type_name: "string", description: "HTTP-Referer header (OpenRouter attribution)" },
+    ConfigKey { path: "agents.myagent.env.MYAGENT_X_TITLE", type_name: "string", description: "X-Title header (default myagent on OpenRouter)" },
+    ConfigKey { path: "agents.claude.env.ANTHROPIC_BASE_URL", type_name: "string", description: "Base URL for Claude Code" },
+    ConfigKey { path: "agents.claude.env.ANTHROPIC_API_KEY", type_name: "string", description: "API key for Claude Code" },
+    ConfigKey { path: "agents.claude.env.ANTHROPIC_AUTH_TOKEN", type_name: "string", description: "Auth token for Claude Code" },
+    ConfigKey { path: "agents.claude.env.CLAUDE_MAX_TURNS", type_name: "u32", description: "Passed to claude --max-turns" },
+    ConfigKey { path: "channels.feishu.app_id", type_name: "string", description: "Feishu app ID" },
+    ConfigKey { path: "channels.feishu.app_secret", type_name: "string", description: "Feishu app secret" },
+    ConfigKey { path: "channels.feishu.per_chat_workspace", type_name: "bool", description: "Separate workspace dir per chat" },
+    ConfigKey { path: "channels.feishu.show_usage_in_card", type_name: "bool", description: "Token usage footer on finished cards" },
+    ConfigKey { path: "channels.feishu.persist_threads", type_name: "bool", description: "Keep card reply routing across restarts" },
+    ConfigKey { path: "channels.feishu.transport_mode", type_name: "string", description: "Event transport: websocket or polling" },
+    ConfigKey { path: "channels.feishu.max_card_chars", type_name: "usize", description: "Card length before continuing on a new card" },
+    ConfigKey { path: "channels.slack.bot_token", type_name: "string", description: "Slack bot token (xoxb-...)" },
+    ConfigKey { path: "channels.slack.signing_secret", type_name: "string", description: "Slack request signing secret" },
+    ConfigKey { path: "channels.slack.port", type_name: "u16", description: "Port for POST /slack/events" },
+    ConfigKey { path: "channels.webhook.token", type_name: "string", description: "Bearer token for POST /prompt" },
+    ConfigKey { path: "channels.webhook.port", type_name: "u16", description: "Port for the webhook server" },
+    ConfigKey { path: "channels.discord.token", type_name: "string", description: "Discord bot token" },
+    ConfigKey { path: "channels.discord.guild_ids", type_name: "[u64]", description: "Guilds to answer everyone in" },
+    ConfigKey { path: "channels.discord.allowed_user_ids", type_name: "[u64]", description: "Users to answer anywhere, including DMs" },
+    ConfigKey { path: "channels.discord.command_prefix", type_name: "string", description: "Prefix that addresses the bot" },
+    ConfigKey { path: "channels.telegram.bot_token", type_name: "string", description: "Telegram bot token" },
+    ConfigKey { path: "channels.telegram.allowed_chat_ids", type_name: "[i64]", description: "Chats to answer in (required)" },
+];
+
+/// Longest value shown in the VALUE column.
+const MAX_VALUE_WIDTH: usize = 18;
+
+fn cmd_list(config_path: &PathBuf) -> Result<()> {
+    // Load through AppConfig so unset keys show their defaults
+    let config = if config_path.exists() {
+        config::AppConfig::load(config_path)?
+    } else {
+        config::AppConfig::default()
+    };
+    let json = mask_secrets(&serde_json::to_value(&config)?);
+
+    println!("{:<45} {:<8} {:<18} DESCRIPTION", "KEY PATH", "TYPE", "VALUE");
+    for key in CONFIG_KEYS {
+        let value = key
+            .path
+            .split('.')
+            .try_fold(&json, |cur, part| cur.get(part))
+            .map(display_value)
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "{:<45} {:<8} {:<18} {}",
+            key.path,
+            key.type_name,
+            truncate_value(&value),
+            key.description
+        );
+    }
+    Ok(())
+}
+
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::Null => "-".to_string(),
+        Value::String(s) if s.is_empty() => "\"\"".to_string(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn truncate_value(value: &str) -> String {
+    if value.chars().count() <= MAX_VALUE_WIDTH {
+        value.to_string()
+    } else {
+        let head: String = value.chars().take(MAX_VALUE_WIDTH - 3).collect();
+        format!("{head}...")
+    }
+}
+
+/// The config file as JSON, or a new one (creating its directory) if it
+/// doesn't exist yet.
+fn read_or_new(config_path: &Path) -> Result<Value> {
+    if config_path.exists() {
+        return Ok(serde_json::from_str(&std::fs::read_to_string(config_path)?)?);
+    }
+    if let Some(p) = config_path.parent() {
+        std::fs::create_dir_all(p)?;
+    }
+    Ok(serde_json::json!({ "version": 1 }))
+}
+
 fn cmd_set(config_path: &PathBuf, key: &str, value: &str) -> Result<()> {
-    let mut json: Value = if config_path.exists() {
-        serde_json::from_str(&std::fs::read_to_string(config_path)?)?
+    let mut json = read_or_new(config_path)?;
+    let typed = parse_value(value)
+        .map_err(|e| anyhow::anyhow!("Invalid JSON value for {key}: {e}"))?;
+    let complex = typed.is_object() || typed.is_array();
+    let shown = mask_secrets(&serde_json::json!({ "value": typed }))["value"].clone();
+    set_nested(&mut json, key, typed)?;
+    let parsed: config::AppConfig = serde_json::from_value(json.clone())
+        .map_err(|e| anyhow::anyhow!("Invalid value for {key}: {e}"))?;
+    parsed
+        .validate()
+        .map_err(|e| anyhow::anyhow!("Invalid value for {key}: {e}"))?;
+    std::fs::write(config_path, serde_json::to_string_pretty(&json)?)?;
+    if complex {
+        println!("Set {key} = {}", serde_json::to_string_pretty(&shown)?);
     } else {
-        if let Some(p) = config_path.parent() {
-            std::fs::create_dir_all(p)?;
+        println!("Set {} = {}", key, mask_value(key, value));
+    }
+    Ok(())
+}
+
+/// Save `template` as the alias `name`. Unlike `set`, the template is always
+/// stored as a string.
+fn cmd_alias(config_path: &Path, name: &str, template: &str) -> Result<()> {
+    if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == '.') {
+        bail!("Invalid alias name '{name}': use a single word without dots");
+    }
+    let mut json = read_or_new(config_path)?;
+    set_nested(&mut json, &format!("aliases.{name}"), Value::String(template.to_string()))?;
+    std::fs::write(config_path, serde_json::to_string_pretty(&json)?)?;
+    println!("Alias {name} = {template}");
+    Ok(())
+}
+
+/// Export format:
+///
+/// ```json
+/// {
+///   "myagent_export": { "exported_by": "0.5.0", "secrets": "masked", "keys": { ... } },
+///   "config": { ...settings.json... }
+/// }
+/// ```
+///
+/// `keys` describes every documented key so the file explains itself.
+fn cmd_export(config_path: &Path, output: Option<&Path>, include_secrets: bool) -> Result<()> {
+    if !config_path.exists() {
+        bail!(
+            "No config at {}\nRun 'myagent config init' to create one",
+            config_path.display()
+        );
+    }
+    let json: Value = serde_json::from_str(&std::fs::read_to_string(config_path)?)?;
+    let config = if include_secrets { json } else { mask_secrets(&json) };
+    let keys: serde_json::Map<String, Value> = CONFIG_KEYS
+        .iter()
+        .map(|k| {
+            let doc = format!("{}: {}", k.type_name, k.description);
+            (k.path.to_string(), Value::String(doc))
+        })
+        .collect();
+    let export = serde_json::json!({
+        EXPORT_KEY: {
+            "note": "Import with `myagent config import <file>` (add --merge to keep existing keys)",
+            "exported_by": CURRENT_VERSION,
+            "exported_at": chrono::Utc::now().to_rfc3339(),
+            "secrets": if include_secrets { "included" } else { "masked" },
+            "keys": keys,
+        },
+        "config": config,
+    });
+    let text = serde_json::to_string_pretty(&export)?;
+    match output {
+        Some(path) => {
+            std::fs::write(path, format!("{text}\n"))?;
+            println!("Exported {} to {}", config_path.display(), path.display());
+            if include_secrets {
+                eprintln!("Warning: {} contains secrets", path.display());
+            }
+        }
+        None => println!("{text}"),
+    }
+    Ok(())
+}
+
+fn cmd_import(config_path: &Path, input: &Path, merge: bool) -> Result<()> {
+    let content = std::fs::read_to_string(input)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", input.display()))?;
+    let json: Value = serde_json::from_str(&content)
+        .map_err(|e| anyhow::anyhow!("Failed to parse {}: {e}", input.display()))?;
+
+    let mut incoming = match json.get(EXPORT_KEY) {
+        Some(meta) => {
+            let mut incoming = json.get("config").cloned().unwrap_or(Value::Null);
+            if meta["secrets"].as_str() == Some("masked") {
+                // Masked values would overwrite the real ones
+                strip_secrets(&mut incoming);
+                eprintln!("Warning: {} has masked secrets; they were not imported", input.display());
+            }
+            incoming
         }
-        serde_json::json!({ "version": 1 })
+        None => json,
     };
-    set_nested(&mut json, key, value)?;
-    std::fs::write(config_path, serde_json::to_string_pretty(&json)?)?;
-    println!("Set {} = {}", key, mask_value(key, value));
+    if !incoming.is_object() {
+        bail!("{} does not contain a config object", input.display());
+    }
+    let version = incoming["version"].as_u64();
+    if let Some(version) = version.filter(|&v| v < u64::from(config::CONFIG_VERSION)) {
+        eprintln!(
+            "Warning: {} has config version {version}, older than the current version {}; \
+             review the result with 'myagent config list'",
+            input.display(),
+            config::CONFIG_VERSION
+        );
+    }
+
+    if merge && config_path.exists() {
+        let mut current: Value = serde_json::from_str(&std::fs::read_to_string(config_path)?)?;
+        deep_merge(&mut current, incoming);
+        incoming = current;
+    }
+
+    let parsed: config::AppConfig = serde_json::from_value(incoming.clone())
+        .map_err(|e| anyhow::anyhow!("Invalid config in {}: {e}", input.display()))?;
+    parsed
+        .validate()
+        .map_err(|e| anyhow::anyhow!("Invalid config in {}: {e}", input.display()))?;
+
+    if let Some(parent) = config_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(config_path, serde_json::to_string_pretty(&incoming)?)?;
+    let verb = if merge { "Merged" } else { "Imported" };
+    println!("{verb} {} into {}", input.display(), config_path.display());
+    Ok(())
+}
+
+/// Set the agent env of every known variable in a `.env` file, as if it had
+/// been exported when the daemon started.
+fn cmd_import_env(config_path: &PathBuf, path: &Path) -> Result<()> {
+    let vars = dotenvy::from_path_iter(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", path.display()))?;
+    // Edit the file as JSON so defaults and keys this version doesn't know stay as they are
+    let mut json = read_or_new(config_path)?;
+
+    let mut imported = Vec::new();
+    let mut skipped = Vec::new();
+    for var in vars {
+        let (key, value) =
+            var.map_err(|e| anyhow::anyhow!("Failed to parse {}: {e}", path.display()))?;
+        match config::ENV_MAPPINGS.iter().find(|(_, k)| *k == key) {
+            Some((agent, _)) => {
+                imported.push(format!("agents.{agent}.env.{key} = {}", mask_value(&key, &value)));
+                set_nested(&mut json, &format!("agents.{agent}.env.{key}"), Value::String(value))?;
+            }
+            None if key == "MYAGENT_UPDATE_CHANNEL" => {
+                imported.push(format!("update_channel = {value}"));
+                set_nested(&mut json, "update_channel", Value::String(value))?;
+            }
+            None => skipped.push(key),
+        }
+    }
+
+    if !imported.is_empty() {
+        let parsed: config::AppConfig = serde_json::from_value(json.clone())
+            .map_err(|e| anyhow::anyhow!("Invalid config after import: {e}"))?;
+        parsed.validate()?;
+        std::fs::write(config_path, serde_json::to_string_pretty(&json)?)?;
+    }
+    for line in &imported {
+        println!("Imported {line}");
+    }
+    if !skipped.is_empty() {
+        println!("Skipped unknown keys: {}", skipped.join(", "));
+    }
+    if imported.is_empty() {
+        println!("No known keys in {}; config unchanged", path.display());
+    } else {
+        println!("Wrote {}", config_path.display());
+    }
+    Ok(())
+}
+
+/// Merge `from` into `into`: objects merge key by key, anything else replaces.
+fn deep_merge(into: &mut Value, from: Value) {
+    match (into, from) {
+        (Value::Object(into), Value::Object(from)) => {
+            for (k, v) in from {
+                match into.get_mut(&k) {
+                    Some(existing) => deep_merge(existing, v),
+                    None => {
+                        into.insert(k, v);
+                    }
+                }
+            }
+        }
+        (into, from) => *into = from,
+    }
+}
+
+/// Remove string values under secret-looking keys.
+fn strip_secrets(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|k, v| !(is_secret_key(k) && v.is_string()));
+            map.values_mut().for_each(strip_secrets);
+        }
+        Value::Array(a) => a.iter_mut().for_each(strip_secrets),
+        _ => {}
+    }
+}
+
+/// Values starting with `{` or `[` are parsed as JSON; anything else becomes
+/// a bool, an integer or a string.
+fn parse_value(val: &str) -> serde_json::Result<Value> {
+    let trimmed = val.trim_start();
+    if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        return serde_json::from_str(val);
+    }
+    Ok(if val == "true" {
+        Value::Bool(true)
+    } else if val == "false" {
+        Value::Bool(false)
+    } else if let Ok(n) = val.parse::<u64>() {
+        Value::Number(n.into())
+    } else {
+        Value::String(val.to_string())
+    })
+}
+
+/// `config get` found no value at the key; the command exits with status 1.
+#[derive(Debug)]
+pub struct KeyNotFound;
+
+impl std::fmt::Display for KeyNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Key not found")
+    }
+}
+
+impl std::error::Error for KeyNotFound {}
+
+/// Print the value at `key` with no trailing newline: strings as-is, anything
+/// else as compact JSON. A missing key prints nothing and fails with
+/// [`KeyNotFound`].
+fn cmd_get(config_path: &Path, key: &str, raw: bool) -> Result<()> {
+    let json: Value = match std::fs::read_to_string(config_path) {
+        Ok(content) => serde_json::from_str(&content)?,
+        Err(_) => return Err(KeyNotFound.into()),
+    };
+    let Some(value) = get_nested(&json, key) else {
+        return Err(KeyNotFound.into());
+    };
+    let last = key.rsplit('.').next().unwrap_or(key);
+    let value = match value {
+        _ if raw => value.clone(),
+        Value::String(s) if is_secret_key(last) => Value::String(mask_str(s)),
+        other => mask_secrets(other),
+    };
+    let out = match &value {
+        Value::String(s) => s.clone(),
+        other => serde_json::to_string(other)?,
+    };
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(out.as_bytes())?;
+    stdout.flush()?;
     Ok(())
 }
 
-fn set_nested(json: &mut Value, key: &str, val: &str) -> Result<()> {
+/// Look up a dot-notation key; numeric parts index into arrays.
+fn get_nested<'a>(json: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.').try_fold(json, |cur, part| match cur {
+        Value::Object(map) => map.get(part),
+        Value::Array(items) => items.get(part.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+fn set_nested(json: &mut Value, key: &str, typed: Value) -> Result<()> {
     let parts: Vec<&str> = key.split('.').collect();
     if parts.is_empty() {
         bail!("Empty key");
@@ -95,20 +493,12 @@ fn set_nested(json: &mut Value, key: &str, val: &str) -> Result<()> {
     if !cur.is_object() {
         *cur = serde_json::json!({});
     }
-    let typed = if val == "true" {
-        Value::Bool(true)
-    } else if val == "false" {
-        Value::Bool(false)
-    } else if let Ok(n) = val.parse::<u64>() {
-        Value::Number(n.into())
-    } else {
-        Value::String(val.to_string())
-    };
     cur.as_object_mut().unwrap().insert(last.to_string(), typed);
     Ok(())
 }
 
-fn mask_secrets(value: &Value) -> Value {
+/// Recursively mask string values under secret-looking keys.
+pub fn mask_secrets(value: &Value) -> Value {
     match value {
         Value::Object(map) => {
             let mut m = serde_json::Map::new();
@@ -143,3 +533,32 @@ fn mask_str(s: &str) -> String {
 fn mask_value(key: &str, value: &str) -> String {
     if is_secret_key(key) { mask_str(value) } else { value.to_string() }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_json_object_is_saved_nested() {
+        let dir = std::env::temp_dir().join(format!("myagent-cmd-set-{}", std::process::id()));
+        let config_path = dir.join("settings.json");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        cmd_set(
+            &config_path,
+            "agents.myagent.env",
+            r#"{"ANTHROPIC_MODEL": "claude-x", "MAX_TOKENS": "100"}"#,
+        )
+        .unwrap();
+
+        let saved: Value =
+            serde_json::from_str(&std::fs::read_to_string(&config_path).unwrap()).unwrap();
+        let loaded = config::AppConfig::load(&config_path);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let env = &saved["agents"]["myagent"]["env"];
+        assert!(env.is_object(), "env saved as {env}");
+        assert_eq!(env["ANTHROPIC_MODEL"], "claude-x");
+        assert_eq!(env["MAX_TOKENS"], "100");
+        assert!(loaded.is_ok(), "saved config does not load: {:?}", loaded.err());
+    }
+}
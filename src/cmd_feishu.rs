@@ -2,6 +2,7 @@ use std::path::Path;
 
 use anyhow::Result;
 use clap::Subcommand;
+use serde::Serialize;
 use serde_json::Value;
 
 use crate::config::{self, AppConfig};
@@ -50,6 +51,20 @@ pub enum FeishuAction {
         #[arg(short, long)]
         message: String,
     },
+    /// Download every file shared in a Feishu chat
+    DownloadAll {
+        /// Chat ID to download files from
+        chat_id: String,
+        /// Directory to save files into
+        #[arg(short, long, default_value = ".")]
+        output_dir: String,
+        /// Only files sent at or after this time (YYYY-MM-DD, "YYYY-MM-DD HH:MM:SS" or Unix timestamp)
+        #[arg(long)]
+        since: Option<String>,
+        /// List the files that would be downloaded without fetching them
+        #[arg(long)]
+        dry_run: bool,
+    },
     /// List file messages in a Feishu chat
     Files {
         /// Chat ID to list files from
@@ -60,6 +75,9 @@ pub enum FeishuAction {
         /// Max number of files to show (default: 10)
         #[arg(short = 'n', long, default_value = "10")]
         count: usize,
+        /// Output format: text or json
+        #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+        format: String,
     },
 }
 
@@ -121,14 +139,168 @@ pub async fn run(action: &FeishuAction) -> Result<()> {
             println!("{reply_id}");
             Ok(())
         }
+        FeishuAction::DownloadAll {
+            chat_id,
+            output_dir,
+            since,
+            dry_run,
+        } => download_all(&api, chat_id, output_dir, since.as_deref(), *dry_run).await,
         FeishuAction::Files {
             chat_id,
             page,
             count,
+            format,
         } => {
-            list_files(&api, chat_id, page.as_deref(), *count).await
+            list_files(&api, chat_id, page.as_deref(), *count, format == "json").await
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct FileEntry {
+    file_name: String,
+    file_key: String,
+    message_id: String,
+    create_time: String,
+    file_size: Option<u64>,
+}
+
+/// Extract the file details from a `file` message; `None` for other messages.
+fn parse_file_entry(item: &Value) -> Option<FileEntry> {
+    if item["msg_type"].as_str() != Some("file") {
+        return None;
+    }
+    // Parse content JSON to get file_key and file_name
+    let content_str = item["body"]["content"].as_str().unwrap_or("{}");
+    let content: Value = serde_json::from_str(content_str).unwrap_or_default();
+    let file_key = content["file_key"].as_str().filter(|k| !k.is_empty())?;
+    // file_size is a number in some API versions and a string in others
+    let file_size = content["file_size"]
+        .as_u64()
+        .or_else(|| content["file_size"].as_str().and_then(|s| s.parse().ok()));
+
+    Some(FileEntry {
+        file_name: content["file_name"].as_str().unwrap_or("unknown").to_string(),
+        file_key: file_key.to_string(),
+        message_id: item["message_id"].as_str().unwrap_or("").to_string(),
+        create_time: item["create_time"].as_str().unwrap_or("").to_string(),
+        file_size,
+    })
+}
+
+/// Download every file message in a chat (newest first, back to `since`)
+/// into `output_dir` as `<timestamp>_<filename>`.
+async fn download_all(
+    api: &FeishuApi,
+    chat_id: &str,
+    output_dir: &str,
+    since: Option<&str>,
+    dry_run: bool,
+) -> Result<()> {
+    let since_ms = since.map(parse_since).transpose()?;
+    let mut files: Vec<FileEntry> = Vec::new();
+    let mut page_token: Option<String> = None;
+    const PAGE_SIZE: usize = 50;
+
+    // Messages come newest first, so stop at the first one older than `since`
+    'pages: loop {
+        let (items, has_more, next_token) =
+            api.list_messages(chat_id, PAGE_SIZE, page_token.as_deref()).await?;
+        for item in &items {
+            let created: i64 = item["create_time"]
+                .as_str()
+                .and_then(|t| t.parse().ok())
+                .unwrap_or(0);
+            if since_ms.is_some_and(|since| created < since) {
+                break 'pages;
+            }
+            if let Some(entry) = parse_file_entry(item) {
+                files.push(entry);
+            }
+        }
+        if !has_more || next_token.is_none() {
+            break;
+        }
+        page_token = next_token;
+    }
+
+    if files.is_empty() {
+        eprintln!("No file messages found.");
+        return Ok(());
+    }
+
+    if !dry_run {
+        tokio::fs::create_dir_all(output_dir).await?;
+    }
+    let mut used = std::collections::HashSet::new();
+    let total = files.len();
+    for (i, f) in files.iter().enumerate() {
+        let target = unique_path(Path::new(output_dir), &download_name(f), &mut used);
+        if dry_run {
+            let size = f.file_size.map(format_size).unwrap_or_else(|| "-".to_string());
+            println!("{}  ({size})", target.display());
+            continue;
         }
+        eprintln!("Downloading file {}/{total}: {}...", i + 1, f.file_name);
+        let bytes = api
+            .download_message_resource(&f.message_id, &f.file_key, "file")
+            .await?;
+        tokio::fs::write(&target, &bytes).await?;
+        println!("{}", target.display());
+    }
+
+    if dry_run {
+        eprintln!("\n{total} file(s) would be downloaded.");
+    } else {
+        eprintln!("\nDownloaded {total} file(s) to {output_dir}");
+    }
+    Ok(())
+}
+
+/// `<YYYYmmdd-HHMMSS>_<filename>`, with path separators removed from the name.
+fn download_name(f: &FileEntry) -> String {
+    let ms: i64 = f.create_time.parse().unwrap_or(0);
+    let stamp = chrono::DateTime::from_timestamp(ms / 1000, 0)
+        .map(|d| d.format("%Y%m%d-%H%M%S").to_string())
+        .unwrap_or_else(|| f.create_time.clone());
+    let name = f.file_name.replace(['/', '\\'], "_");
+    format!("{stamp}_{name}")
+}
+
+/// Append `_2`, `_3`, ... before the extension until the name is free.
+fn unique_path(
+    dir: &Path,
+    name: &str,
+    used: &mut std::collections::HashSet<std::path::PathBuf>,
+) -> std::path::PathBuf {
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{ext}")),
+        _ => (name, String::new()),
+    };
+    let mut candidate = dir.join(name);
+    let mut n = 2;
+    while used.contains(&candidate) || candidate.exists() {
+        candidate = dir.join(format!("{stem}_{n}{ext}"));
+        n += 1;
+    }
+    used.insert(candidate.clone());
+    candidate
+}
+
+/// Parse `--since` as a date (YYYY-MM-DD), date and time (YYYY-MM-DD HH:MM:SS),
+/// or a Unix timestamp in seconds or milliseconds. Returns milliseconds.
+fn parse_since(s: &str) -> Result<i64> {
+    if let Ok(n) = s.parse::<i64>() {
+        // Anything this large is already in milliseconds
+        return Ok(if n > 100_000_000_000 { n } else { n * 1000 });
+    }
+    if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S") {
+        return Ok(dt.and_utc().timestamp_millis());
+    }
+    if let Ok(d) = chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(d.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp_millis());
     }
+    anyhow::bail!("Invalid --since '{s}': use YYYY-MM-DD, 'YYYY-MM-DD HH:MM:SS' or a Unix timestamp")
 }
 
 /// List file messages from a Feishu chat, with client-side filtering.
@@ -138,8 +310,9 @@ async fn list_files(
     chat_id: &str,
     start_page: Option<&str>,
     max_files: usize,
+    json: bool,
 ) -> Result<()> {
-    let mut files: Vec<(String, String, String, String)> = Vec::new(); // (name, key, msg_id, time)
+    let mut files: Vec<FileEntry> = Vec::new();
     let mut page_token = start_page.map(|s| s.to_string());
     let mut pages_scanned = 0;
     let mut total_messages = 0;
@@ -153,20 +326,8 @@ async fn list_files(
         total_messages += items.len();
 
         for item in &items {
-            if item["msg_type"].as_str() != Some("file") {
-                continue;
-            }
-            let message_id = item["message_id"].as_str().unwrap_or("").to_string();
-            let create_time = item["create_time"].as_str().unwrap_or("").to_string();
-
-            // Parse content JSON to get file_key and file_name
-            let content_str = item["body"]["content"].as_str().unwrap_or("{}");
-            let content: Value = serde_json::from_str(content_str).unwrap_or_default();
-            let file_key = content["file_key"].as_str().unwrap_or("").to_string();
-            let file_name = content["file_name"].as_str().unwrap_or("unknown").to_string();
-
-            if !file_key.is_empty() {
-                files.push((file_name, file_key, message_id, create_time));
+            if let Some(entry) = parse_file_entry(item) {
+                files.push(entry);
             }
             if files.len() >= max_files {
                 break;
@@ -180,16 +341,32 @@ async fn list_files(
         page_token = next_token;
     }
 
+    // Most recent first
+    files.sort_by_key(|f| std::cmp::Reverse(f.create_time.parse::<i64>().unwrap_or(0)));
+
+    if json {
+        let out = serde_json::json!({ "files": files, "page_token": page_token });
+        println!("{}", serde_json::to_string_pretty(&out)?);
+        return Ok(());
+    }
+
     if files.is_empty() {
         eprintln!("No file messages found (scanned {total_messages} messages in {pages_scanned} pages).");
         return Ok(());
     }
 
     // Print results
-    println!("{:<4} {:<30} {:<40} {:<30} {}", "#", "FILE_NAME", "FILE_KEY", "MESSAGE_ID", "TIME");
-    for (i, (name, key, msg_id, time)) in files.iter().enumerate() {
-        let display_time = format_timestamp(time);
-        println!("{:<4} {:<30} {:<40} {:<30} {}", i + 1, name, key, msg_id, display_time);
+    for (i, f) in files.iter().enumerate() {
+        let size = f.file_size.map(format_size).unwrap_or_else(|| "-".to_string());
+        println!(
+            "{:>3}. {}  {:>9}  {}  (file_key: {}, msg_id: {})",
+            i + 1,
+            format_timestamp(&f.create_time),
+            size,
+            f.file_name,
+            f.file_key,
+            f.message_id
+        );
     }
 
     // Print scan stats and next page token
@@ -210,7 +387,22 @@ fn format_timestamp(ts: &str) -> String {
     let secs = ms / 1000;
     let dt = chrono::DateTime::from_timestamp(secs, 0);
     match dt {
-        Some(d) => d.format("%Y-%m-%d %H:%M").to_string(),
+        Some(d) => d.format("%Y-%m-%d %H:%M:%S").to_string(),
         None => ts.to_string(),
     }
 }
+
+/// Human-readable byte count, e.g. "1.2 MB".
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
@@ -2,7 +2,10 @@ use std::io::stdout;
 
 use anyhow::Result;
 use crossterm::{
-    event::{self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyEventKind},
+    event::{
+        self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyEventKind,
+        KeyModifiers,
+    },
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
@@ -14,7 +17,14 @@ use ratatui::{
     Terminal,
 };
 
-use crate::config;
+use crate::ai::client::AnthropicClient;
+use crate::ai::types::{ContentBlock, CreateMessageRequest, Message};
+use crate::config::{self, MyAgentEnv, ProxyConfig};
+
+const PROXY_SECTION: usize = 4;
+const ADVANCED_SECTION: usize = 5;
+const UPDATE_CHANNELS: [&str; 2] = ["stable", "beta"];
+const CONNECTION_TEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(20);
 
 // ── Data Model ──
 
@@ -41,20 +51,28 @@ struct Section {
     completed: bool,
 }
 
+/// Result of checking the MyAgent credentials before leaving that section.
+enum ConnectionTest {
+    Pending,
+    Passed,
+    Failed(String),
+}
+
 struct InitApp {
     sections: Vec<Section>,
     sec_idx: usize,
     field_idx: usize,
     finished: bool,
     cancelled: bool,
+    connection_test: Option<ConnectionTest>,
+    /// The workspace default came from the enclosing git repository.
+    workspace_is_git_root: bool,
 }
 
 impl InitApp {
     fn new() -> Self {
-        let workspace_default = config::config_dir()
-            .join("workspace")
-            .to_string_lossy()
-            .to_string();
+        let workspace_default = detect_workspace_default();
+        let workspace_is_git_root = config::find_git_root().is_some();
 
         let sections = vec![
             Section {
@@ -211,6 +229,75 @@ impl InitApp {
                     },
                 ],
             },
+            Section {
+                title: "Proxy (Optional)".into(),
+                skippable: true,
+                skipped: false,
+                active: false,
+                completed: false,
+                fields: vec![
+                    // Defaults to Skip: most users don't need a proxy
+                    Field {
+                        label: "".into(),
+                        kind: FieldKind::Select {
+                            options: vec!["Configure".into(), "Skip".into()],
+                            selected: 1,
+                        },
+                        done: false,
+                    },
+                    Field {
+                        label: "HTTP_PROXY".into(),
+                        kind: FieldKind::Text {
+                            value: String::new(),
+                            default: None,
+                        },
+                        done: false,
+                    },
+                    Field {
+                        label: "HTTPS_PROXY".into(),
+                        kind: FieldKind::Text {
+                            value: String::new(),
+                            default: None,
+                        },
+                        done: false,
+                    },
+                    Field {
+                        label: "NO_PROXY".into(),
+                        kind: FieldKind::Text {
+                            value: String::new(),
+                            default: None,
+                        },
+                        done: false,
+                    },
+                ],
+            },
+            Section {
+                title: "Advanced (Optional)".into(),
+                skippable: false,
+                skipped: false,
+                active: false,
+                completed: false,
+                fields: vec![
+                    // 0: Update channel
+                    Field {
+                        label: "Update channel".into(),
+                        kind: FieldKind::Select {
+                            options: UPDATE_CHANNELS.iter().map(|c| c.to_string()).collect(),
+                            selected: 0,
+                        },
+                        done: false,
+                    },
+                    // 1: Replaces the built-in base system prompt
+                    Field {
+                        label: "System prompt file (empty: built-in prompt)".into(),
+                        kind: FieldKind::Text {
+                            value: String::new(),
+                            default: None,
+                        },
+                        done: false,
+                    },
+                ],
+            },
         ];
 
         Self {
@@ -219,6 +306,8 @@ impl InitApp {
             field_idx: 0,
             finished: false,
             cancelled: false,
+            connection_test: None,
+            workspace_is_git_root,
         }
     }
 
@@ -299,6 +388,28 @@ impl InitApp {
             self.set_field_value(3, 1, &f.app_id);
             self.set_field_value(3, 2, &f.app_secret);
         }
+        // Proxy
+        if let Some(p) = &cfg.proxy {
+            if let Some(FieldKind::Select { selected, .. }) =
+                self.sections.get_mut(PROXY_SECTION).and_then(|s| s.fields.get_mut(0)).map(|f| &mut f.kind)
+            {
+                *selected = 0;
+            }
+            self.set_field_value(PROXY_SECTION, 1, p.http_url.as_deref().unwrap_or_default());
+            self.set_field_value(PROXY_SECTION, 2, p.https_url.as_deref().unwrap_or_default());
+            self.set_field_value(PROXY_SECTION, 3, p.no_proxy.as_deref().unwrap_or_default());
+        }
+        // Updates
+        let channel = UPDATE_CHANNELS.iter().position(|c| *c == cfg.update_channel);
+        if let (Some(idx), Some(FieldKind::Select { selected, .. })) = (
+            channel,
+            self.sections.get_mut(ADVANCED_SECTION).and_then(|s| s.fields.get_mut(0)).map(|f| &mut f.kind),
+        ) {
+            *selected = idx;
+        }
+        if let Some(path) = &me.system_prompt_file {
+            self.set_field_value(ADVANCED_SECTION, 1, &path.to_string_lossy());
+        }
     }
 
     fn set_field_value(&mut self, sec: usize, field: usize, val: &str) {
@@ -391,20 +502,16 @@ impl InitApp {
                     // After model select (OpenRouter): if preset model → done, if Custom → field 4
                     if let FieldKind::Select { selected, .. } = &sec.fields[3].kind {
                         if *selected < 3 {
-                            // Preset model selected, section done
-                            sec.completed = true;
-                            sec.active = false;
-                            self.next_section();
+                            // Preset model selected, test before leaving the section
+                            self.connection_test = Some(ConnectionTest::Pending);
                             return;
                         }
                     }
                     // Custom model: fall through to field 4
                 }
                 4 => {
-                    // After custom model name: section done
-                    sec.completed = true;
-                    sec.active = false;
-                    self.next_section();
+                    // After custom model name: test before leaving the section
+                    self.connection_test = Some(ConnectionTest::Pending);
                     return;
                 }
                 _ => {}
@@ -419,6 +526,53 @@ impl InitApp {
         }
     }
 
+    /// Apply the outcome of the MyAgent connection test. On failure the
+    /// cursor returns to the API key so only the credentials need fixing.
+    fn finish_connection_test(&mut self, result: Result<()>) {
+        match result {
+            Ok(()) => {
+                self.connection_test = Some(ConnectionTest::Passed);
+                let sec = &mut self.sections[self.sec_idx];
+                sec.completed = true;
+                sec.active = false;
+                self.next_section();
+            }
+            Err(e) => {
+                self.connection_test = Some(ConnectionTest::Failed(format!("{e:#}")));
+                self.field_idx = 1;
+            }
+        }
+    }
+
+    fn is_testing_connection(&self) -> bool {
+        matches!(self.connection_test, Some(ConnectionTest::Pending))
+    }
+
+    /// Ctrl+B: reopen the last confirmed field, stepping into the previous
+    /// section when nothing in this one has been confirmed yet.
+    fn back(&mut self) {
+        // Conditional flows skip fields, so step to the last one confirmed
+        let last_done = |sec: &Section| sec.fields.iter().rposition(|f| f.done);
+        let field_idx = match last_done(&self.sections[self.sec_idx]) {
+            Some(idx) => idx,
+            None => {
+                if self.sec_idx == 0 {
+                    return;
+                }
+                self.sections[self.sec_idx].active = false;
+                self.sec_idx -= 1;
+                let sec = &mut self.sections[self.sec_idx];
+                sec.active = true;
+                sec.completed = false;
+                sec.skipped = false;
+                last_done(sec).unwrap_or(0)
+            }
+        };
+        self.field_idx = field_idx;
+        self.sections[self.sec_idx].fields[field_idx].done = false;
+        self.connection_test = None;
+    }
+
     fn next_section(&mut self) {
         self.sec_idx += 1;
         self.field_idx = 0;
@@ -429,11 +583,14 @@ impl InitApp {
         }
     }
 
-    fn handle_key(&mut self, code: KeyCode) {
+    fn handle_key(&mut self, code: KeyCode, modifiers: KeyModifiers) {
         match code {
             KeyCode::Esc => {
                 self.cancelled = true;
             }
+            KeyCode::Char('b') if modifiers.contains(KeyModifiers::CONTROL) => {
+                self.back();
+            }
             KeyCode::Enter => {
                 self.advance();
             }
@@ -491,8 +648,8 @@ impl InitApp {
         }
     }
 
-    fn build_config(&self) -> serde_json::Value {
-        let workspace = self.get_text(0, 0);
+    /// Credentials entered in the MyAgent section.
+    fn myagent_env(&self) -> MyAgentEnv {
         let api_key = self.get_text(1, 1); // field 1: API key
 
         // Determine provider, base_url, model
@@ -513,11 +670,44 @@ impl InitApp {
             (self.get_text(1, 2), self.get_text(1, 4))
         };
 
+        MyAgentEnv {
+            api_keys: vec![api_key.clone()],
+            api_key,
+            base_url,
+            model,
+            use_prompt_caching: false,
+            api_timeout_ms: config::DEFAULT_API_TIMEOUT_MS,
+            max_turns: config::DEFAULT_MAX_TURNS,
+            system_prompt_file: None,
+            http_referer: None,
+            x_title: None,
+            shell_timeout_ms: config::DEFAULT_SHELL_TIMEOUT_MS,
+        }
+    }
+
+    /// Proxy settings, unless the section is skipped. Prefilled values are
+    /// used even before the section is reached (e.g. for the connection test).
+    fn proxy_config(&self) -> Option<ProxyConfig> {
+        if self.sections[PROXY_SECTION].skipped || self.get_select(PROXY_SECTION, 0) == 1 {
+            return None;
+        }
+        let text = |field| Some(self.get_text(PROXY_SECTION, field)).filter(|v| !v.is_empty());
+        Some(ProxyConfig {
+            http_url: text(1),
+            https_url: text(2),
+            no_proxy: text(3),
+        })
+    }
+
+    fn build_config(&self) -> serde_json::Value {
+        let workspace = self.get_text(0, 0);
+        let me = self.myagent_env();
+
         let mut agents = serde_json::json!({
             "myagent": { "env": {
-                "MYAGENT_API_KEY": api_key,
-                "MYAGENT_BASE_URL": base_url,
-                "MYAGENT_MODEL": model,
+                "MYAGENT_API_KEY": me.api_key,
+                "MYAGENT_BASE_URL": me.base_url,
+                "MYAGENT_MODEL": me.model,
             }}
         });
 
@@ -558,6 +748,24 @@ impl InitApp {
             });
         }
 
+        if let Some(proxy) = self.proxy_config() {
+            config["proxy"] = serde_json::json!({
+                "http_url": proxy.http_url,
+                "https_url": proxy.https_url,
+                "no_proxy": proxy.no_proxy,
+            });
+        }
+
+        let channel = UPDATE_CHANNELS[self.get_select(ADVANCED_SECTION, 0)];
+        if channel != "stable" {
+            config["update_channel"] = serde_json::json!(channel);
+        }
+        let prompt_file = self.get_text(ADVANCED_SECTION, 1);
+        if !prompt_file.trim().is_empty() {
+            config["agents"]["myagent"]["env"]["MYAGENT_SYSTEM_PROMPT_FILE"] =
+                serde_json::json!(prompt_file.trim());
+        }
+
         config
     }
 
@@ -589,6 +797,15 @@ impl InitApp {
     }
 }
 
+/// Suggest the enclosing git repository as the workspace, falling back to
+/// ~/.myagent/workspace when the current directory isn't inside one.
+fn detect_workspace_default() -> String {
+    config::find_git_root()
+        .unwrap_or_else(|| config::config_dir().join("workspace"))
+        .to_string_lossy()
+        .to_string()
+}
+
 // ── Rendering ──
 
 // "my" = first 18 columns, "agent" = rest
@@ -675,7 +892,31 @@ fn render(frame: &mut Frame, app: &InitApp) {
                 continue;
             }
 
+            // Never echo proxy credentials back once a field is confirmed
+            if si == PROXY_SECTION && !is_active && (fi == 1 || fi == 2) {
+                let mut shown = field.clone();
+                if let FieldKind::Text { value, .. } = &mut shown.kind {
+                    *value = mask_proxy_url(value);
+                }
+                render_field(&mut lines, &shown, false);
+                continue;
+            }
+
             render_field(&mut lines, field, is_active);
+
+            let show_git_hint = si == 0 && is_active && app.workspace_is_git_root;
+            match &field.kind {
+                FieldKind::Text { value, default: Some(d) } if show_git_hint && value.is_empty() => {
+                    lines.push(Line::from(Span::styled(
+                        format!("    Default: {d} (detected git root)"),
+                        Style::default().fg(Color::DarkGray),
+                    )));
+                }
+                _ => {}
+            }
+        }
+        if si == 1 {
+            render_connection_test(&mut lines, app.connection_test.as_ref());
         }
         lines.push(Line::from(""));
     }
@@ -697,8 +938,8 @@ fn render(frame: &mut Frame, app: &InitApp) {
             Some(Field {
                 kind: FieldKind::Select { .. },
                 ..
-            }) => "↑↓ select  Enter confirm  Esc quit",
-            _ => "Enter confirm  Esc quit",
+            }) => "↑↓ select  Enter confirm  Ctrl+B back  Esc quit",
+            _ => "Enter confirm  Ctrl+B back  Esc quit",
         };
         lines.push(Line::from(Span::styled(
             hint,
@@ -712,6 +953,38 @@ fn render(frame: &mut Frame, app: &InitApp) {
     frame.render_widget(paragraph, area);
 }
 
+/// Reduce a proxy URL to `scheme://host[:port]`, dropping any credentials.
+fn mask_proxy_url(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(u) => match (u.host_str(), u.port()) {
+            (Some(host), Some(port)) => format!("{}://{host}:{port}", u.scheme()),
+            (Some(host), None) => format!("{}://{host}", u.scheme()),
+            (None, _) => String::new(),
+        },
+        Err(_) if url.is_empty() => String::new(),
+        Err(_) => "(invalid URL)".to_string(),
+    }
+}
+
+fn render_connection_test(lines: &mut Vec<Line>, test: Option<&ConnectionTest>) {
+    let span = match test {
+        None => return,
+        Some(ConnectionTest::Pending) => Span::styled(
+            "    Testing API connection...",
+            Style::default().fg(Color::Yellow),
+        ),
+        Some(ConnectionTest::Passed) => Span::styled(
+            "    Testing API connection... ✓",
+            Style::default().fg(Color::Green),
+        ),
+        Some(ConnectionTest::Failed(err)) => Span::styled(
+            format!("    Testing API connection... ✗ {err}"),
+            Style::default().fg(Color::Red),
+        ),
+    };
+    lines.push(Line::from(span));
+}
+
 fn render_field(lines: &mut Vec<Line>, field: &Field, is_active: bool) {
     match &field.kind {
         FieldKind::Text { value, default } => {
@@ -844,9 +1117,16 @@ fn render_field(lines: &mut Vec<Line>, field: &Field, is_active: bool) {
 
 // ── Entry Point ──
 
-pub fn run() -> Result<()> {
+pub fn run(non_interactive: bool) -> Result<()> {
     let config_path = config::default_config_path();
 
+    if non_interactive {
+        let config_json = config_from_env()?;
+        write_config(&config_path, &config_json)?;
+        println!("Config created at {}", config_path.display());
+        return Ok(());
+    }
+
     // Load existing config to pre-populate fields
     let existing = if config_path.exists() {
         config::AppConfig::load(&config_path).ok()
@@ -872,9 +1152,22 @@ pub fn run() -> Result<()> {
             break;
         }
 
+        if app.is_testing_connection() {
+            // Leave raw mode while the request runs so Ctrl-C still works
+            disable_raw_mode()?;
+            let env = app.myagent_env();
+            let proxy = app.proxy_config();
+            let result = tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(test_connection(&env, proxy.as_ref()))
+            });
+            enable_raw_mode()?;
+            app.finish_connection_test(result);
+            continue;
+        }
+
         match event::read()? {
             Event::Key(key) if key.kind == KeyEventKind::Press => {
-                app.handle_key(key.code);
+                app.handle_key(key.code, key.modifiers);
             }
             Event::Paste(text) => {
                 app.handle_paste(text);
@@ -894,14 +1187,102 @@ pub fn run() -> Result<()> {
 
     // Write config
     let config_json = app.build_config();
-    if let Some(parent) = config_path.parent() {
-        std::fs::create_dir_all(parent)?;
-    }
-    std::fs::write(
-        &config_path,
-        serde_json::to_string_pretty(&config_json)?,
-    )?;
+    write_config(&config_path, &config_json)?;
     println!("✓ Config saved to {}", config_path.display());
     println!("  Try: myagent -p \"hello\"");
     Ok(())
 }
+
+/// Send a single-token request to check that the MyAgent credentials work.
+pub async fn test_connection(env: &MyAgentEnv, proxy: Option<&ProxyConfig>) -> Result<()> {
+    let mut http = reqwest::Client::builder();
+    if let Some(proxy) = proxy {
+        http = proxy.apply(http)?;
+    }
+    let client = AnthropicClient::with_http_client(http.build()?, &env.api_key, &env.base_url)
+        .with_headers(env.attribution_headers());
+    let request = CreateMessageRequest {
+        model: env.model.clone(),
+        max_tokens: 1,
+        messages: vec![Message {
+            role: "user".into(),
+            content: vec![ContentBlock::Text { text: "ping".into() }],
+        }],
+        tools: Vec::new(),
+        stream: false,
+        system: None,
+    };
+    tokio::time::timeout(CONNECTION_TEST_TIMEOUT, client.send_message(request))
+        .await
+        .map_err(|_| anyhow::anyhow!("timed out"))??;
+    Ok(())
+}
+
+fn write_config(path: &std::path::Path, config_json: &serde_json::Value) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(config_json)?)?;
+    Ok(())
+}
+
+/// Build a config from environment variables, using the wizard's defaults
+/// for anything optional. Used by `myagent init --yes`.
+fn config_from_env() -> Result<serde_json::Value> {
+    let env = |key: &str| std::env::var(key).ok().filter(|v| !v.trim().is_empty());
+
+    let api_key = env("MYAGENT_API_KEY").ok_or_else(|| {
+        anyhow::anyhow!("MYAGENT_API_KEY is required for non-interactive init. Set it and re-run `myagent init --yes`.")
+    })?;
+    let workspace = config::config_dir()
+        .join("workspace")
+        .to_string_lossy()
+        .to_string();
+
+    let mut agents = serde_json::json!({
+        "myagent": { "env": {
+            "MYAGENT_API_KEY": api_key,
+            "MYAGENT_BASE_URL": env("MYAGENT_BASE_URL").unwrap_or_else(|| "https://openrouter.ai/api".into()),
+            "MYAGENT_MODEL": env("MYAGENT_MODEL").unwrap_or_else(|| "moonshotai/kimi-k2.5".into()),
+        }}
+    });
+    if let Some(path) = env("MYAGENT_SYSTEM_PROMPT_FILE") {
+        agents["myagent"]["env"]["MYAGENT_SYSTEM_PROMPT_FILE"] = serde_json::json!(path);
+    }
+
+    // Claude: only when a credential is provided
+    if let Some(key) = env("ANTHROPIC_API_KEY") {
+        let mut claude_env = serde_json::json!({ "ANTHROPIC_API_KEY": key });
+        if let Some(base) = env("ANTHROPIC_BASE_URL") {
+            claude_env["ANTHROPIC_BASE_URL"] = serde_json::json!(base);
+        }
+        agents["claude"] = serde_json::json!({ "env": claude_env });
+    }
+
+    let mut config = serde_json::json!({
+        "version": 1,
+        "workspace": workspace,
+        "default_agent": "myagent",
+        "agents": agents,
+    });
+
+    if let Some(channel) = env("MYAGENT_UPDATE_CHANNEL") {
+        config["update_channel"] = serde_json::json!(channel);
+    }
+
+    // Feishu: both credentials are needed for the bot to connect
+    match (env("FEISHU_APP_ID"), env("FEISHU_APP_SECRET")) {
+        (Some(app_id), Some(app_secret)) => {
+            config["channels"] = serde_json::json!({
+                "feishu": {
+                    "app_id": app_id,
+                    "app_secret": app_secret,
+                }
+            });
+        }
+        (None, None) => {}
+        _ => anyhow::bail!("FEISHU_APP_ID and FEISHU_APP_SECRET must be set together."),
+    }
+
+    Ok(config)
+}
@@ -0,0 +1,109 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use anyhow::Result;
+use notify::{RecursiveMode, Watcher};
+
+/// Bytes read at a time while scanning backwards for line breaks.
+const SCAN_CHUNK: u64 = 8 * 1024;
+
+/// Re-check the file this often even without a change notification, in case
+/// the platform's watcher misses an event.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Print the last `lines` lines of the log at `path`, then with `follow`
+/// keep printing what is appended to it until interrupted.
+pub fn run(path: &Path, lines: usize, follow: bool) -> Result<()> {
+    let mut file = File::open(path)
+        .map_err(|e| anyhow::anyhow!("Failed to open {}: {e}", path.display()))?;
+    let start = tail_start(&mut file, lines)?;
+    let mut pos = copy_from(&mut file, start)?;
+    if follow {
+        watch(path, &mut pos)?;
+    }
+    Ok(())
+}
+
+/// Offset of the first of the last `lines` lines, found by scanning
+/// backwards from the end so large logs aren't read in full.
+fn tail_start(file: &mut File, lines: usize) -> Result<u64> {
+    let len = file.metadata()?.len();
+    if lines == 0 {
+        return Ok(len);
+    }
+    let mut end = len;
+    let mut newlines = 0;
+    let mut buf = vec![0u8; SCAN_CHUNK as usize];
+    // A newline ending the file terminates the last line rather than
+    // starting a new one
+    let mut skip_trailing = true;
+    while end > 0 {
+        let start = end.saturating_sub(SCAN_CHUNK);
+        let chunk = &mut buf[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(chunk)?;
+        for (i, &byte) in chunk.iter().enumerate().rev() {
+            if byte != b'\n' {
+                skip_trailing = false;
+                continue;
+            }
+            if skip_trailing {
+                skip_trailing = false;
+                continue;
+            }
+            newlines += 1;
+            if newlines == lines {
+                return Ok(start + i as u64 + 1);
+            }
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+/// Write everything from `pos` to the end of the file to stdout and return
+/// the new end offset.
+fn copy_from(file: &mut File, pos: u64) -> Result<u64> {
+    file.seek(SeekFrom::Start(pos))?;
+    let mut stdout = std::io::stdout().lock();
+    let copied = std::io::copy(file, &mut stdout)?;
+    stdout.flush()?;
+    Ok(pos + copied)
+}
+
+/// Print bytes appended to the log as they arrive. The file is reopened on
+/// each change, so following survives the daemon rotating it on restart.
+fn watch(path: &Path, pos: &mut u64) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)
+        .map_err(|e| anyhow::anyhow!("Failed to watch {}: {e}", path.display()))?;
+    // Watch the directory: the log itself is replaced when it is rotated
+    let dir = path.parent().unwrap_or(Path::new("."));
+    watcher
+        .watch(dir, RecursiveMode::NonRecursive)
+        .map_err(|e| anyhow::anyhow!("Failed to watch {}: {e}", dir.display()))?;
+
+    loop {
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) if !event.paths.iter().any(|p| p == path) => continue,
+            Ok(Ok(_)) | Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Ok(Err(e)) => anyhow::bail!("Failed to watch {}: {e}", path.display()),
+            Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+        // Missing between a rotation's rename and the new file's creation
+        let Ok(mut file) = File::open(path) else {
+            continue;
+        };
+        let len = file.metadata()?.len();
+        if len < *pos {
+            // Truncated or replaced by a new log: start from its beginning
+            *pos = 0;
+        }
+        if len > *pos {
+            *pos = copy_from(&mut file, *pos)?;
+        }
+    }
+}
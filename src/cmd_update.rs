@@ -1,31 +1,172 @@
-use std::io::Cursor;
+use std::io::{Cursor, Write};
+use std::path::Path;
 
 use anyhow::Result;
 use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use unicode_width::UnicodeWidthStr;
 
-use crate::update_check::{self, CURRENT_VERSION};
+use crate::audit::{AuditLogger, UpdateRecord};
+use crate::config;
+use crate::update_check::{self, GithubRelease, CURRENT_VERSION};
 
-pub async fn run() -> Result<()> {
+const MAX_CHANGELOG_CHARS: usize = 2000;
+const CHANGELOG_WIDTH: usize = 80;
+const CHECKSUMS_ASSET: &str = "myagent-checksums.txt";
+
+/// Persisted at ~/.myagent/update_meta.json so `rollback` can report what it restores.
+#[derive(Serialize, Deserialize, Default)]
+struct UpdateMeta {
+    backup_version: Option<String>,
+    /// Last version installed by auto-update, so a release that keeps
+    /// reporting an older version can't trigger an update loop.
+    #[serde(default)]
+    auto_installed_version: Option<String>,
+}
+
+/// A `--dry-run` found a newer release; the command exits with status 2.
+#[derive(Debug)]
+pub struct UpdateAvailable;
+
+impl std::fmt::Display for UpdateAvailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Update available")
+    }
+}
+
+impl std::error::Error for UpdateAvailable {}
+
+/// Check `channel` for a newer release and install it. With `dry_run`, only
+/// report what would be installed and fail with [`UpdateAvailable`] if there
+/// is one.
+pub async fn run(yes: bool, skip_verify: bool, dry_run: bool, channel: &str) -> Result<()> {
     println!("Checking for updates...");
 
-    let (tag, assets) = update_check::fetch_release_info()
+    let release = update_check::fetch_release_info(channel)
         .await
         .map_err(|_| anyhow::anyhow!("Update failed. Please check your network and try again."))?;
-    let latest = tag.as_str();
+    let latest = release.tag_name.as_str();
 
-    let current_ver = parse_ver(CURRENT_VERSION);
-    let latest_ver = parse_ver(latest);
+    if update_check::compare_versions(latest, CURRENT_VERSION)
+        .is_some_and(|o| o != std::cmp::Ordering::Greater)
+    {
+        println!("Already up to date (v{CURRENT_VERSION}).");
+        return Ok(());
+    }
 
-    match (current_ver, latest_ver) {
-        (Some(c), Some(l)) if l <= c => {
-            println!("Already up to date (v{CURRENT_VERSION}).");
-            return Ok(());
+    if dry_run {
+        println!(
+            "Would update from v{CURRENT_VERSION} to v{}",
+            latest.trim_start_matches('v')
+        );
+        let target_asset = update_check::asset_name()?;
+        match release.assets.iter().find(|a| a.name == target_asset) {
+            Some(asset) => {
+                println!("  Asset: {}", asset.name);
+                println!("  URL:   {}", asset.browser_download_url);
+            }
+            None => println!("  No {target_asset} asset in this release"),
         }
-        _ => {}
+        return Err(UpdateAvailable.into());
+    }
+
+    if let Some(body) = release.body.as_deref().filter(|b| !b.trim().is_empty()) {
+        println!("\nWhat's new in {latest}:\n");
+        println!("{}\n", format_changelog(body));
+    }
+
+    if !yes && !confirm(&format!("Update to v{}?", latest.trim_start_matches('v')))? {
+        println!("Update cancelled.");
+        return Ok(());
     }
 
     println!("Updating {CURRENT_VERSION} → {latest}...");
 
+    install(&release, skip_verify).await?;
+
+    if crate::daemon::is_daemon_running() {
+        println!("Updated to {latest}. Run `myagent restart` to apply to the daemon.");
+    } else {
+        println!("Updated to {latest}.");
+    }
+
+    Ok(())
+}
+
+/// Serve-mode auto-update: check the release channel every
+/// `CHECK_INTERVAL_HOURS` and install the first newer release found.
+/// Returns the installed version; the caller restarts the daemon.
+/// With `audit_log`, each step is also written to the audit log.
+pub async fn run_auto_update(channel: String, audit_log: bool) -> String {
+    let audit = audit_log.then(|| AuditLogger::new("daemon"));
+    let interval = std::time::Duration::from_secs(update_check::CHECK_INTERVAL_HOURS as u64 * 3600);
+    loop {
+        let installed = match update_check::do_check(channel.clone()).await {
+            Some(release) => auto_install(&release, audit.as_ref()).await,
+            None => None,
+        };
+        if let Some(version) = installed {
+            return version;
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Install `release` unless it is already on disk or was auto-installed
+/// before. Returns the version the daemon should restart into.
+async fn auto_install(release: &GithubRelease, audit: Option<&AuditLogger>) -> Option<String> {
+    let latest = release.tag_name.as_str();
+    let record = |status, error| UpdateRecord {
+        from_version: CURRENT_VERSION,
+        to_version: latest,
+        status,
+        error,
+    };
+    tracing::info!("Auto-update: {latest} available (running v{CURRENT_VERSION})");
+    audit_update(audit, &record("available", None)).await;
+
+    // The binary on disk may already be newer, e.g. after a manual `myagent update`
+    let installed = std::env::current_exe()
+        .ok()
+        .and_then(|exe| binary_version(&exe));
+    if let Some(installed) = installed.filter(|v| !update_check::is_newer(latest, v)) {
+        tracing::info!("Auto-update: v{installed} is already installed, restarting");
+        audit_update(audit, &record("skipped", Some("already installed"))).await;
+        return Some(installed);
+    }
+    if read_update_meta().auto_installed_version.as_deref() == Some(latest) {
+        tracing::warn!("Auto-update: {latest} was already installed once, not retrying");
+        audit_update(audit, &record("skipped", Some("installed before"))).await;
+        return None;
+    }
+
+    if let Err(e) = install(release, false).await {
+        tracing::warn!("Auto-update to {latest} failed: {e}");
+        audit_update(audit, &record("failed", Some(&e.to_string()))).await;
+        return None;
+    }
+    let mut meta = read_update_meta();
+    meta.auto_installed_version = Some(latest.to_string());
+    if let Err(e) = write_update_meta(&meta) {
+        tracing::warn!("Failed to write update metadata: {e}");
+    }
+    tracing::info!("Auto-update: installed {latest}");
+    audit_update(audit, &record("installed", None)).await;
+    Some(latest.trim_start_matches('v').to_string())
+}
+
+/// Write `update` to the audit log, if there is one.
+async fn audit_update(audit: Option<&AuditLogger>, update: &UpdateRecord<'_>) {
+    if let Some(audit) = audit {
+        audit.record_update(update).await;
+    }
+}
+
+/// Download, verify and atomically install `release` over the running
+/// executable, keeping a backup for `myagent rollback`.
+async fn install(release: &GithubRelease, skip_verify: bool) -> Result<()> {
+    let assets = &release.assets;
     let target_asset = update_check::asset_name()?;
     let asset = assets
         .iter()
@@ -49,6 +190,39 @@ pub async fn run() -> Result<()> {
         .await
         .map_err(|_| anyhow::anyhow!("Download interrupted. Please try again."))?;
 
+    // Verify the archive against the published checksums
+    if skip_verify {
+        println!("Skipping checksum verification.");
+    } else {
+        let checksums = assets
+            .iter()
+            .find(|a| a.name == CHECKSUMS_ASSET)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Release has no {CHECKSUMS_ASSET}. Re-run with --skip-verify to install anyway."
+                )
+            })?;
+        let listing = client
+            .get(&checksums.browser_download_url)
+            .header("User-Agent", format!("myagent/{CURRENT_VERSION}"))
+            .send()
+            .await
+            .map_err(|_| anyhow::anyhow!("Update failed. Please check your network and try again."))?
+            .error_for_status()
+            .map_err(|_| anyhow::anyhow!("Update failed. Please try again later."))?
+            .text()
+            .await
+            .map_err(|_| anyhow::anyhow!("Download interrupted. Please try again."))?;
+        let expected = find_checksum(&listing, &asset.name).ok_or_else(|| {
+            anyhow::anyhow!("No checksum listed for {}.", asset.name)
+        })?;
+        let actual = hex::encode(Sha256::digest(&bytes));
+        if !actual.eq_ignore_ascii_case(&expected) {
+            drop(bytes);
+            anyhow::bail!("Checksum mismatch: download may be corrupted");
+        }
+    }
+
     // Extract
     let binary = extract_binary(&bytes, &asset.name)
         .map_err(|_| anyhow::anyhow!("Update failed. Please try again later."))?;
@@ -83,6 +257,12 @@ pub async fn run() -> Result<()> {
         anyhow::bail!("Update failed. Please try again later.");
     }
 
+    // Keep the current binary around so `myagent rollback` can restore it
+    if let Err(e) = backup_current_exe() {
+        cleanup();
+        anyhow::bail!("Update failed: could not back up the current binary: {e}");
+    }
+
     // Verified — safe to replace. self_replace uses atomic rename,
     // so even if this fails the original binary remains intact.
     if self_replace::self_replace(&tmp_bin).is_err() {
@@ -91,16 +271,138 @@ pub async fn run() -> Result<()> {
     }
 
     cleanup();
+    Ok(())
+}
+
+/// Restore ~/.myagent/myagent.bak over the running executable.
+pub fn rollback() -> Result<()> {
+    let backup = config::backup_binary_path();
+    if !backup.exists() {
+        anyhow::bail!(
+            "No backup found at {}. A backup is created each time `myagent update` installs a new version.",
+            backup.display()
+        );
+    }
+
+    match binary_version(&backup) {
+        Some(v) => println!("Backup binary reports v{v} (current: v{CURRENT_VERSION})"),
+        None => println!("Backup binary did not report a version (current: v{CURRENT_VERSION})"),
+    }
+
+    self_replace::self_replace(&backup)
+        .map_err(|e| anyhow::anyhow!("Rollback failed: {e}"))?;
+
+    let restored = read_update_meta().backup_version.or_else(|| {
+        std::env::current_exe()
+            .ok()
+            .and_then(|exe| binary_version(&exe))
+    });
+    match restored {
+        Some(v) => println!("Restored v{v}"),
+        None => println!("Restored previous version."),
+    }
 
     if crate::daemon::is_daemon_running() {
-        println!("Updated to {latest}. Run `myagent restart` to apply to the daemon.");
-    } else {
-        println!("Updated to {latest}.");
+        println!("Run `myagent restart` to apply to the daemon.");
     }
 
     Ok(())
 }
 
+/// Copy the running executable to the backup path and record its version.
+fn backup_current_exe() -> Result<()> {
+    let exe = std::env::current_exe()?;
+    std::fs::create_dir_all(config::config_dir())?;
+    std::fs::copy(&exe, config::backup_binary_path())?;
+
+    let mut meta = read_update_meta();
+    meta.backup_version = Some(CURRENT_VERSION.to_string());
+    write_update_meta(&meta)
+}
+
+fn write_update_meta(meta: &UpdateMeta) -> Result<()> {
+    std::fs::write(config::update_meta_path(), serde_json::to_string_pretty(meta)?)?;
+    Ok(())
+}
+
+fn read_update_meta() -> UpdateMeta {
+    std::fs::read_to_string(config::update_meta_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// Run `<binary> --version` and return the version number it reports.
+fn binary_version(binary: &Path) -> Option<String> {
+    let output = std::process::Command::new(binary)
+        .arg("--version")
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    // clap prints "myagent <version>"
+    text.split_whitespace()
+        .last()
+        .map(|v| v.trim_start_matches('v').to_string())
+}
+
+/// Ask a yes/no question on stdin. Defaults to no.
+fn confirm(question: &str) -> Result<bool> {
+    print!("{question} [y/N] ");
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Truncate release notes and wrap them at 80 columns.
+fn format_changelog(body: &str) -> String {
+    let mut text: String = body.chars().take(MAX_CHANGELOG_CHARS).collect();
+    if body.chars().count() > MAX_CHANGELOG_CHARS {
+        text.push_str("\n...");
+    }
+    text.lines()
+        .map(|line| wrap_line(line.trim_end(), CHANGELOG_WIDTH))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Word-wrap a single line, keeping its leading indentation on continuation lines.
+fn wrap_line(line: &str, width: usize) -> String {
+    if line.width() <= width {
+        return line.to_string();
+    }
+    let indent: String = line.chars().take_while(|c| c.is_whitespace()).collect();
+    let indent_width = indent.width();
+    let mut out = String::new();
+    let mut current = indent.clone();
+    for word in line.split_whitespace() {
+        let len = current.width();
+        if len > indent_width && len + 1 + word.width() > width {
+            out.push_str(&current);
+            out.push('\n');
+            current = indent.clone();
+        }
+        if current.len() > indent.len() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    out.push_str(&current);
+    out
+}
+
+/// Look up a file's hash in a `SHA256SUMS`-style listing (`<hex> <filename>`).
+fn find_checksum(listing: &str, file_name: &str) -> Option<String> {
+    listing.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        let hash = parts.next()?;
+        // sha256sum marks binary-mode entries with a leading '*'
+        let name = parts.next()?.trim_start_matches('*');
+        (name == file_name).then(|| hash.to_string())
+    })
+}
+
 fn extract_binary(data: &[u8], asset_name: &str) -> Result<Vec<u8>> {
     if asset_name.ends_with(".tar.gz") {
         extract_from_tar_gz(data)
@@ -141,11 +443,3 @@ fn extract_from_zip(data: &[u8]) -> Result<Vec<u8>> {
     }
     anyhow::bail!("Binary not found in archive")
 }
-
-fn parse_ver(v: &str) -> Option<(u64, u64, u64)> {
-    let mut iter = v.trim().split('.');
-    let maj = iter.next()?.parse::<u64>().ok()?;
-    let min = iter.next()?.parse::<u64>().ok()?;
-    let pat = iter.next()?.parse::<u64>().ok()?;
-    Some((maj, min, pat))
-}
\ No newline at end of file
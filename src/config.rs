@@ -1,8 +1,27 @@
 use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
 use std::path::PathBuf;
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use tracing::info;
+
+/// How many parent directories to search when looking for a git root.
+const GIT_ROOT_MAX_DEPTH: usize = 5;
+
+/// Walk up from the current directory looking for a `.git` entry.
+pub fn find_git_root() -> Option<PathBuf> {
+    let mut dir = std::env::current_dir().ok()?;
+    for _ in 0..=GIT_ROOT_MAX_DEPTH {
+        if dir.join(".git").exists() {
+            return Some(dir);
+        }
+        if !dir.pop() {
+            break;
+        }
+    }
+    None
+}
 
 /// Default config directory: ~/.myagent/
 pub fn config_dir() -> PathBuf {
@@ -21,12 +40,61 @@ pub fn pid_file_path() -> PathBuf {
     config_dir().join("myagent.pid")
 }
 
+/// CLI history file path: ~/.myagent/history
+pub fn history_file_path() -> PathBuf {
+    config_dir().join("history")
+}
+
+/// Previous binary kept by `myagent update`: ~/.myagent/myagent.bak
+pub fn backup_binary_path() -> PathBuf {
+    config_dir().join("myagent.bak")
+}
+
+/// Feishu card routing saved across restarts: ~/.myagent/feishu_state.json
+pub fn feishu_state_path() -> PathBuf {
+    config_dir().join("feishu_state.json")
+}
+
+/// Attachments of Feishu messages: ~/.myagent/downloads/<message_id>/
+pub fn feishu_downloads_dir() -> PathBuf {
+    config_dir().join("downloads")
+}
+
+/// Update metadata file path: ~/.myagent/update_meta.json
+pub fn update_meta_path() -> PathBuf {
+    config_dir().join("update_meta.json")
+}
+
 /// Log directory: ~/.myagent/logs/
 pub fn log_dir() -> PathBuf {
     config_dir().join("logs")
 }
 
+/// Config schema version written by this build.
+pub const CONFIG_VERSION: u32 = 1;
 pub const DEFAULT_PORT: u16 = 17890;
+pub const DEFAULT_API_TIMEOUT_MS: u64 = 120_000;
+pub const DEFAULT_SHELL_TIMEOUT_MS: u64 = 120_000;
+pub const DEFAULT_MAX_TURNS: u32 = 50;
+pub const DEFAULT_HEALTH_BIND: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+/// Environment variables copied into an agent's env, as (agent, variable).
+pub const ENV_MAPPINGS: [(&str, &str); 14] = [
+    ("myagent", "MYAGENT_API_KEY"),
+    ("myagent", "MYAGENT_API_KEYS"),
+    ("myagent", "MYAGENT_BASE_URL"),
+    ("myagent", "MYAGENT_MODEL"),
+    ("myagent", "MYAGENT_PROMPT_CACHING"),
+    ("myagent", "MYAGENT_API_TIMEOUT_MS"),
+    ("myagent", "MYAGENT_MAX_TURNS"),
+    ("myagent", "MYAGENT_SYSTEM_PROMPT_FILE"),
+    ("myagent", "MYAGENT_HTTP_REFERER"),
+    ("myagent", "MYAGENT_X_TITLE"),
+    ("claude", "ANTHROPIC_BASE_URL"),
+    ("claude", "ANTHROPIC_API_KEY"),
+    ("claude", "ANTHROPIC_AUTH_TOKEN"),
+    ("claude", "CLAUDE_MAX_TURNS"),
+];
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct AppConfig {
@@ -42,42 +110,227 @@ pub struct AppConfig {
     pub agents: HashMap<String, AgentConfig>,
     #[serde(default)]
     pub channels: ChannelsConfig,
+    /// Seconds to wait for in-flight threads to finish on shutdown.
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<ProxyConfig>,
+    /// In serve mode without `workspace`, use the enclosing git repository
+    /// of the launch directory as the workspace.
+    #[serde(default = "default_true")]
+    pub workspace_auto_detect: bool,
+    /// Log output format: "text" or "json" (overridden by `--log-format`).
+    #[serde(default = "default_log_format")]
+    pub log_format: String,
+    /// Log filter directives used when `RUST_LOG` is unset,
+    /// e.g. "info,myagent::transport::feishu::event=debug".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_filters: Option<String>,
+    /// Record every tool call in ~/.myagent/logs/audit.jsonl.
+    #[serde(default)]
+    pub audit_log: bool,
+    /// Directories searched before PATH for the shell and the commands it
+    /// runs. Defaults to ~/.cargo/bin, ~/.npm/bin and ~/.local/bin.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra_path: Option<Vec<String>>,
+    /// Release channel for update checks: "stable" or "beta".
+    #[serde(default = "default_update_channel")]
+    pub update_channel: String,
+    /// In serve mode, install new releases as they appear and restart.
+    #[serde(default)]
+    pub auto_update: bool,
+    /// Interface the health server binds to (default 127.0.0.1).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_bind: Option<String>,
+    /// Hosts the `remote_shell` tool may ssh into (`host` or `user@host`).
+    /// The tool is disabled while this is unset or empty.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_hosts: Option<Vec<String>>,
+    /// Offer the `clipboard` tool, which reads and writes the system clipboard.
+    #[serde(default)]
+    pub allow_clipboard_tool: bool,
+    /// Prompt shorthands for the CLI: a message starting with an alias name
+    /// is replaced by its template, with `{args}` standing for the rest.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub aliases: HashMap<String, String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct AgentConfig {
     #[serde(default)]
     pub env: HashMap<String, String>,
+    /// Shell tool timeout used when a call doesn't give its own `timeout_ms`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct ChannelsConfig {
     #[serde(default)]
     pub feishu: Option<FeishuConfig>,
+    #[serde(default)]
+    pub slack: Option<SlackConfig>,
+    #[serde(default)]
+    pub webhook: Option<WebhookConfig>,
+    #[serde(default)]
+    pub discord: Option<DiscordConfig>,
+    #[serde(default)]
+    pub telegram: Option<TelegramConfig>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct FeishuConfig {
     pub app_id: String,
     pub app_secret: String,
+    /// Give each chat its own `<workspace>/<chat_id>/` directory so agents
+    /// in different chats don't modify the same files.
+    #[serde(default)]
+    pub per_chat_workspace: bool,
+    /// Add a token usage, cost and duration footer to finished cards.
+    #[serde(default = "default_true")]
+    pub show_usage_in_card: bool,
+    /// Save card → thread routing to ~/.myagent/feishu_state.json on
+    /// shutdown so replies to older cards still work after a restart.
+    #[serde(default = "default_true")]
+    pub persist_threads: bool,
+    /// How events are received: "websocket", falling back to polling while
+    /// the WebSocket can't connect, or "polling" (the message list API
+    /// every few seconds, without reactions).
+    #[serde(default = "default_transport_mode")]
+    pub transport_mode: String,
+    /// Characters a card holds before the response continues on a new card.
+    #[serde(default = "default_max_card_chars")]
+    pub max_card_chars: usize,
+}
+
+/// Outbound HTTP proxy for API calls.
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct ProxyConfig {
+    #[serde(default)]
+    pub http_url: Option<String>,
+    #[serde(default)]
+    pub https_url: Option<String>,
+    /// Comma-separated hosts that bypass the proxy (same format as NO_PROXY).
+    #[serde(default)]
+    pub no_proxy: Option<String>,
+}
+
+impl ProxyConfig {
+    /// Route a reqwest client builder through the configured proxies.
+    pub fn apply(&self, mut builder: reqwest::ClientBuilder) -> Result<reqwest::ClientBuilder> {
+        let no_proxy = self
+            .no_proxy
+            .as_deref()
+            .and_then(reqwest::NoProxy::from_string);
+        if let Some(url) = self.http_url.as_deref().filter(|u| !u.is_empty()) {
+            let proxy = reqwest::Proxy::http(url)
+                .map_err(|e| anyhow::anyhow!("Invalid HTTP proxy URL: {e}"))?;
+            builder = builder.proxy(proxy.no_proxy(no_proxy.clone()));
+        }
+        if let Some(url) = self.https_url.as_deref().filter(|u| !u.is_empty()) {
+            let proxy = reqwest::Proxy::https(url)
+                .map_err(|e| anyhow::anyhow!("Invalid HTTPS proxy URL: {e}"))?;
+            builder = builder.proxy(proxy.no_proxy(no_proxy));
+        }
+        Ok(builder)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SlackConfig {
+    pub bot_token: String,
+    pub signing_secret: String,
+    /// Port for the Events API endpoint (`POST /slack/events`).
+    #[serde(default = "default_slack_port")]
+    pub port: u16,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WebhookConfig {
+    /// Shared secret expected as `Authorization: Bearer <token>`. Must not
+    /// be empty: the webhook refuses to start without one.
+    pub token: String,
+    #[serde(default = "default_webhook_port")]
+    pub port: u16,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DiscordConfig {
+    pub token: String,
+    /// Guilds the bot answers everyone in.
+    #[serde(default)]
+    pub guild_ids: Vec<u64>,
+    /// Users the bot answers anywhere, including DMs. Nobody else is
+    /// answered; the bot refuses to start when both lists are empty.
+    #[serde(default)]
+    pub allowed_user_ids: Vec<u64>,
+    #[serde(default = "default_command_prefix")]
+    pub command_prefix: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TelegramConfig {
+    pub bot_token: String,
+    /// Chats the bot answers in. Required: the bot refuses to start
+    /// without any.
+    #[serde(default)]
+    pub allowed_chat_ids: Vec<i64>,
 }
 
 // --- Typed agent configs extracted from env maps ---
 
 pub struct MyAgentEnv {
     pub api_key: String,
+    /// Keys to rotate between (MYAGENT_API_KEYS, comma-separated).
+    /// Falls back to `api_key` alone when unset.
+    pub api_keys: Vec<String>,
     pub base_url: String,
     pub model: String,
+    /// Mark the system prompt cacheable (MYAGENT_PROMPT_CACHING).
+    pub use_prompt_caching: bool,
+    /// Timeout for sending an API request (MYAGENT_API_TIMEOUT_MS).
+    pub api_timeout_ms: u64,
+    /// API round trips allowed per message before giving up (MYAGENT_MAX_TURNS).
+    pub max_turns: u32,
+    /// Replaces the built-in base system prompt (MYAGENT_SYSTEM_PROMPT_FILE).
+    pub system_prompt_file: Option<PathBuf>,
+    /// Sent as `HTTP-Referer` for OpenRouter attribution (MYAGENT_HTTP_REFERER).
+    pub http_referer: Option<String>,
+    /// Sent as `X-Title` (MYAGENT_X_TITLE); "myagent" for OpenRouter when unset.
+    pub x_title: Option<String>,
+    /// Default shell and remote shell timeout (agents.myagent.timeout_ms).
+    pub shell_timeout_ms: u64,
+}
+
+impl MyAgentEnv {
+    /// App attribution headers added to every API request.
+    pub fn attribution_headers(&self) -> HashMap<String, String> {
+        let mut headers = HashMap::new();
+        if let Some(referer) = &self.http_referer {
+            headers.insert("HTTP-Referer".to_string(), referer.clone());
+        }
+        let title = self.x_title.clone().or_else(|| {
+            self.base_url
+                .contains("openrouter.ai")
+                .then(|| "myagent".to_string())
+        });
+        if let Some(title) = title {
+            headers.insert("X-Title".to_string(), title);
+        }
+        headers
+    }
 }
 
 pub struct ClaudeEnv {
     pub base_url: Option<String>,
     pub api_key: Option<String>,
     pub auth_token: Option<String>,
+    /// Passed to `claude --max-turns` (CLAUDE_MAX_TURNS).
+    pub max_turns: Option<u32>,
 }
 
 fn default_version() -> u32 {
-    1
+    CONFIG_VERSION
 }
 fn default_port() -> u16 {
     DEFAULT_PORT
@@ -85,6 +338,33 @@ fn default_port() -> u16 {
 fn default_agent() -> String {
     "myagent".to_string()
 }
+fn default_command_prefix() -> String {
+    "!agent".to_string()
+}
+fn default_webhook_port() -> u16 {
+    17891
+}
+fn default_slack_port() -> u16 {
+    17892
+}
+fn default_true() -> bool {
+    true
+}
+fn default_shutdown_timeout_secs() -> u64 {
+    30
+}
+fn default_log_format() -> String {
+    "text".to_string()
+}
+fn default_max_card_chars() -> usize {
+    3500
+}
+fn default_transport_mode() -> String {
+    "websocket".to_string()
+}
+fn default_update_channel() -> String {
+    "stable".to_string()
+}
 
 impl Default for AppConfig {
     fn default() -> Self {
@@ -95,6 +375,19 @@ impl Default for AppConfig {
             default_agent: default_agent(),
             agents: HashMap::new(),
             channels: ChannelsConfig::default(),
+            shutdown_timeout_secs: default_shutdown_timeout_secs(),
+            proxy: None,
+            workspace_auto_detect: true,
+            log_format: default_log_format(),
+            log_filters: None,
+            audit_log: false,
+            extra_path: None,
+            update_channel: default_update_channel(),
+            auto_update: false,
+            health_bind: None,
+            allowed_hosts: None,
+            allow_clipboard_tool: false,
+            aliases: HashMap::new(),
         }
     }
 }
@@ -105,18 +398,78 @@ impl AppConfig {
             .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", path.display()))?;
         let config: AppConfig = serde_json::from_str(&content)
             .map_err(|e| anyhow::anyhow!("Failed to parse {}: {e}", path.display()))?;
+        config
+            .validate()
+            .map_err(|e| anyhow::anyhow!("Invalid config {}: {e}", path.display()))?;
         Ok(config)
     }
 
+    /// Check the values serde can't, such as the health bind address.
+    pub fn validate(&self) -> Result<()> {
+        self.health_bind_ip()?;
+        self.api_http_client()?;
+        Ok(())
+    }
+
+    /// HTTP client for AI API calls, routed through `proxy` when set.
+    pub fn api_http_client(&self) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(proxy) = &self.proxy {
+            builder = proxy.apply(builder)?;
+        }
+        Ok(builder.build()?)
+    }
+
+    /// The health server's bind address, `DEFAULT_HEALTH_BIND` when unset.
+    pub fn health_bind_ip(&self) -> Result<IpAddr> {
+        match &self.health_bind {
+            Some(bind) => bind.trim().parse().map_err(|_| {
+                anyhow::anyhow!("health_bind '{bind}' is not an IP address (e.g. 127.0.0.1 or 0.0.0.0)")
+            }),
+            None => Ok(DEFAULT_HEALTH_BIND),
+        }
+    }
+
     /// Extract typed MyAgent config from agents.myagent.env
     pub fn myagent_env(&self) -> MyAgentEnv {
         let env = self.agents.get("myagent").map(|a| &a.env);
+        let api_key = get_env(env, "MYAGENT_API_KEY").unwrap_or_default();
+        let mut api_keys: Vec<String> = get_env(env, "MYAGENT_API_KEYS")
+            .map(|v| {
+                v.split(',')
+                    .map(|k| k.trim().to_string())
+                    .filter(|k| !k.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        if api_keys.is_empty() && !api_key.is_empty() {
+            api_keys.push(api_key.clone());
+        }
         MyAgentEnv {
-            api_key: get_env(env, "MYAGENT_API_KEY").unwrap_or_default(),
+            api_key,
+            api_keys,
             base_url: get_env(env, "MYAGENT_BASE_URL")
                 .unwrap_or_else(|| "https://api.anthropic.com/v1/messages".to_string()),
             model: get_env(env, "MYAGENT_MODEL")
                 .unwrap_or_else(|| "claude-sonnet-4-20250514".to_string()),
+            use_prompt_caching: get_env(env, "MYAGENT_PROMPT_CACHING")
+                .is_some_and(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes")),
+            api_timeout_ms: get_env(env, "MYAGENT_API_TIMEOUT_MS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_API_TIMEOUT_MS),
+            max_turns: get_env(env, "MYAGENT_MAX_TURNS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_MAX_TURNS),
+            system_prompt_file: get_env(env, "MYAGENT_SYSTEM_PROMPT_FILE")
+                .filter(|v| !v.trim().is_empty())
+                .map(PathBuf::from),
+            http_referer: get_env(env, "MYAGENT_HTTP_REFERER").filter(|v| !v.trim().is_empty()),
+            x_title: get_env(env, "MYAGENT_X_TITLE").filter(|v| !v.trim().is_empty()),
+            shell_timeout_ms: self
+                .agents
+                .get("myagent")
+                .and_then(|a| a.timeout_ms)
+                .unwrap_or(DEFAULT_SHELL_TIMEOUT_MS),
         }
     }
 
@@ -127,22 +480,58 @@ impl AppConfig {
             base_url: get_env(env, "ANTHROPIC_BASE_URL"),
             api_key: get_env(env, "ANTHROPIC_API_KEY"),
             auth_token: get_env(env, "ANTHROPIC_AUTH_TOKEN"),
+            max_turns: get_env(env, "CLAUDE_MAX_TURNS").and_then(|v| v.parse().ok()),
         }
     }
 
+    /// Directories to prepend to PATH for shell commands.
+    pub fn extra_path(&self) -> Vec<String> {
+        self.extra_path.clone().unwrap_or_else(|| {
+            ["~/.cargo/bin", "~/.npm/bin", "~/.local/bin"]
+                .map(String::from)
+                .to_vec()
+        })
+    }
+
     /// Get Feishu channel config
     pub fn feishu_config(&self) -> Option<&FeishuConfig> {
         self.channels.feishu.as_ref()
     }
 
+    /// Get Slack channel config
+    pub fn slack_config(&self) -> Option<&SlackConfig> {
+        self.channels.slack.as_ref()
+    }
+
+    /// Get webhook channel config
+    pub fn webhook_config(&self) -> Option<&WebhookConfig> {
+        self.channels.webhook.as_ref()
+    }
+
+    /// Get Discord channel config
+    pub fn discord_config(&self) -> Option<&DiscordConfig> {
+        self.channels.discord.as_ref()
+    }
+
+    /// Get Telegram channel config
+    pub fn telegram_config(&self) -> Option<&TelegramConfig> {
+        self.channels.telegram.as_ref()
+    }
+
     /// Resolve workspace path (for serve mode; CLI mode uses pwd)
     pub fn resolve_workspace(&self) -> String {
-        self.workspace.clone().unwrap_or_else(|| {
-            config_dir()
-                .join("workspace")
-                .to_string_lossy()
-                .to_string()
-        })
+        if let Some(ws) = &self.workspace {
+            info!("Workspace: {ws} (configured)");
+            return ws.clone();
+        }
+        if let Some(root) = self.workspace_auto_detect.then(find_git_root).flatten() {
+            let ws = root.to_string_lossy().to_string();
+            info!("Workspace: {ws} (detected git root)");
+            return ws;
+        }
+        let ws = config_dir().join("workspace").to_string_lossy().to_string();
+        info!("Workspace: {ws} (default)");
+        ws
     }
 
     /// Set an agent env value.
@@ -151,6 +540,7 @@ impl AppConfig {
             .entry(agent.to_string())
             .or_insert_with(|| AgentConfig {
                 env: HashMap::new(),
+                timeout_ms: None,
             })
             .env
             .insert(key.to_string(), value.to_string());
@@ -159,25 +549,20 @@ impl AppConfig {
     /// Override config values with environment variables.
     /// Priority: env var > config file.
     pub fn with_env_overrides(mut self) -> Self {
-        let env_mappings = [
-            ("myagent", "MYAGENT_API_KEY"),
-            ("myagent", "MYAGENT_BASE_URL"),
-            ("myagent", "MYAGENT_MODEL"),
-            ("claude", "ANTHROPIC_BASE_URL"),
-            ("claude", "ANTHROPIC_API_KEY"),
-            ("claude", "ANTHROPIC_AUTH_TOKEN"),
-        ];
-        for (agent, key) in env_mappings {
+        for (agent, key) in ENV_MAPPINGS {
             if let Ok(v) = std::env::var(key) {
                 self.set_agent_env(agent, key, &v);
             }
         }
+        if let Ok(v) = std::env::var("MYAGENT_UPDATE_CHANNEL") {
+            self.update_channel = v;
+        }
         self
     }
 
     /// Check if required env vars are set for at least one agent.
     pub fn has_required_env_vars() -> bool {
-        std::env::var("MYAGENT_API_KEY").is_ok()
+        std::env::var("MYAGENT_API_KEY").is_ok() || std::env::var("MYAGENT_API_KEYS").is_ok()
     }
 }
 
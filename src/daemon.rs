@@ -1,6 +1,7 @@
 use std::fs;
 use std::io::{Read, Write};
-use std::net::TcpStream;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream};
+use std::path::Path;
 
 use anyhow::{bail, Result};
 
@@ -23,20 +24,46 @@ pub fn remove_pid_file() {
 }
 
 /// Read PID from file.
-fn read_pid() -> Option<u32> {
+pub fn read_pid() -> Option<u32> {
     let path = config::pid_file_path();
     let content = fs::read_to_string(&path).ok()?;
     content.trim().parse().ok()
 }
 
+/// `myagent pid` found no running daemon; the command exits with status 1.
+#[derive(Debug)]
+pub struct NotRunning;
+
+impl std::fmt::Display for NotRunning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "myagent is not running")
+    }
+}
+
+impl std::error::Error for NotRunning {}
+
+/// Print the daemon's PID, or fail with [`NotRunning`].
+pub fn print_pid() -> Result<()> {
+    match read_pid().filter(|&pid| is_running(pid)) {
+        Some(pid) => {
+            println!("{pid}");
+            Ok(())
+        }
+        None => {
+            eprintln!("{NotRunning}");
+            Err(NotRunning.into())
+        }
+    }
+}
+
 /// Check if a process is alive.
 #[cfg(unix)]
-fn is_running(pid: u32) -> bool {
+pub fn is_running(pid: u32) -> bool {
     unsafe { libc::kill(pid as i32, 0) == 0 }
 }
 
 #[cfg(windows)]
-fn is_running(pid: u32) -> bool {
+pub fn is_running(pid: u32) -> bool {
     std::process::Command::new("tasklist")
         .args(["/FI", &format!("PID eq {}", pid), "/NH"])
         .output()
@@ -46,11 +73,13 @@ fn is_running(pid: u32) -> bool {
 
 /// Stop the running daemon via HTTP RPC, with PID+SIGTERM fallback.
 pub fn stop_daemon() -> Result<()> {
-    let port = load_port();
+    let addr = load_health_addr();
 
     // Try HTTP shutdown first
-    if let Some(_) = http_post_rpc(port, "shutdown") {
-        std::thread::sleep(std::time::Duration::from_millis(500));
+    if let Some(_) = http_post_rpc(addr, "shutdown") {
+        // The daemon drains in-flight threads before exiting
+        println!("Waiting for in-flight tasks to finish...");
+        wait_for_exit(addr, load_drain_timeout());
         remove_pid_file();
         println!("Stopped myagent");
         return Ok(());
@@ -77,18 +106,44 @@ pub fn stop_daemon() -> Result<()> {
     Ok(())
 }
 
+/// Ask the running daemon to re-read its config file.
+pub fn reload_daemon() -> Result<()> {
+    let addr = load_health_addr();
+    let body = http_post_rpc(addr, "reload")
+        .ok_or_else(|| anyhow::anyhow!("myagent is not running"))?;
+    let resp: serde_json::Value = serde_json::from_str(&body)
+        .map_err(|e| anyhow::anyhow!("Invalid reload response: {e}"))?;
+    if let Some(err) = resp["result"]["error"].as_str() {
+        bail!("Reload failed: {err}");
+    }
+    println!("Config reloaded");
+    Ok(())
+}
+
 /// Show daemon status via HTTP health check, with PID fallback.
 pub fn show_status() -> Result<()> {
-    let port = load_port();
+    let addr = load_health_addr();
 
     // Try HTTP health check
-    if let Some(body) = http_get(port, "/health") {
+    if let Some(body) = http_get(addr, "/health") {
         if let Ok(health) = serde_json::from_str::<serde_json::Value>(&body) {
             println!("myagent is running");
             println!("  Version: {}", health["version"].as_str().unwrap_or("?"));
             println!("  PID:     {}", health["pid"]);
             println!("  Uptime:  {}s", health["uptime"]);
             println!("  Port:    {}", health["port"]);
+            if let Some(threads) = http_get(addr, "/threads")
+                .and_then(|b| serde_json::from_str::<serde_json::Value>(&b).ok())
+            {
+                println!("  Active threads: {}", threads["count"]);
+            }
+            match health["last_activity_at"].as_i64() {
+                Some(at) => {
+                    let secs = (chrono::Utc::now().timestamp() - at).max(0);
+                    println!("  Last activity: {secs}s ago");
+                }
+                None => println!("  Last activity: never"),
+            }
             return Ok(());
         }
     }
@@ -108,7 +163,8 @@ pub fn show_status() -> Result<()> {
 }
 
 /// Daemonize: re-launch self with `serve` subcommand, redirect stdio to log file.
-pub fn daemonize() -> Result<()> {
+/// Returns the PID of the new daemon.
+pub fn daemonize() -> Result<u32> {
     let exe = std::env::current_exe()?;
 
     // Collect global args (config path) if present
@@ -158,7 +214,106 @@ pub fn daemonize() -> Result<()> {
 
     println!("myagent started (PID {})", child.id());
     println!("Log: {}", log_file.display());
-    Ok(())
+    Ok(child.id())
+}
+
+/// Replace the current process with a fresh `exe` started with the same
+/// arguments, e.g. to pick up a newly installed binary. Without `exec`
+/// (non-unix) this returns so the process exits; a supervisor restarts it.
+pub fn restart_in_place(exe: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        let err = std::process::Command::new(exe)
+            .args(std::env::args_os().skip(1))
+            .exec();
+        bail!("Failed to restart {}: {err}", exe.display());
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = exe;
+        tracing::warn!("Restart myagent to finish the update");
+        Ok(())
+    }
+}
+
+/// Render a systemd unit that runs `myagent serve` and print it to stdout,
+/// or write it to `output`. Root gets a system unit, other users a user unit.
+pub fn generate_systemd(config_path: &Path, output: Option<&Path>) -> Result<()> {
+    #[cfg(not(unix))]
+    {
+        let _ = (config_path, output);
+        bail!("systemd units are only supported on Linux");
+    }
+
+    #[cfg(unix)]
+    {
+        let exe = std::env::current_exe()?;
+        let is_root = unsafe { libc::geteuid() } == 0;
+        let user = std::env::var("USER").unwrap_or_else(|_| {
+            if is_root { "root".to_string() } else { "nobody".to_string() }
+        });
+
+        let (install_path, user_line, wanted_by) = if is_root {
+            (
+                std::path::PathBuf::from("/etc/systemd/system/myagent.service"),
+                format!("User={user}\n"),
+                "multi-user.target",
+            )
+        } else {
+            (
+                dirs::home_dir()
+                    .unwrap_or_else(|| std::path::PathBuf::from("."))
+                    .join(".config/systemd/user/myagent.service"),
+                String::new(),
+                "default.target",
+            )
+        };
+
+        let unit = format!(
+            "[Unit]\n\
+            Description=myagent AI coding agent\n\
+            After=network-online.target\n\
+            Wants=network-online.target\n\
+            \n\
+            [Service]\n\
+            Type=simple\n\
+            {user_line}\
+            ExecStart={} --config {} serve\n\
+            Restart=on-failure\n\
+            RestartSec=5\n\
+            StandardOutput=journal\n\
+            StandardError=journal\n\
+            \n\
+            [Install]\n\
+            WantedBy={wanted_by}\n",
+            exe.display(),
+            config_path.display(),
+        );
+
+        let enable_cmd = if is_root {
+            "systemctl daemon-reload && systemctl enable --now myagent"
+        } else {
+            "systemctl --user daemon-reload && systemctl --user enable --now myagent"
+        };
+
+        match output {
+            Some(path) => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, &unit)?;
+                println!("Wrote {}", path.display());
+                println!("Enable with: {enable_cmd}");
+            }
+            None => {
+                print!("{unit}");
+                eprintln!("\n# Save to {} and run: {enable_cmd}", install_path.display());
+            }
+        }
+        Ok(())
+    }
 }
 
 /// Check if the daemon is currently running.
@@ -170,18 +325,89 @@ pub fn is_daemon_running() -> bool {
     }
 }
 
-/// Load port from config file, or use default.
-fn load_port() -> u16 {
+/// Address to reach the health server at, from the config file or defaults.
+/// A wildcard bind (0.0.0.0, ::) is reached through loopback.
+fn load_health_addr() -> SocketAddr {
+    let path = config::default_config_path();
+    let config = config::AppConfig::load(&path).unwrap_or_default();
+    let ip = match config.health_bind_ip().unwrap_or(config::DEFAULT_HEALTH_BIND) {
+        IpAddr::V4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(ip) if ip.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ip => ip,
+    };
+    SocketAddr::new(ip, config.port)
+}
+
+/// Load the shutdown drain timeout from config, plus a grace period.
+fn load_drain_timeout() -> std::time::Duration {
     let path = config::default_config_path();
-    config::AppConfig::load(&path)
-        .map(|c| c.port)
-        .unwrap_or(config::DEFAULT_PORT)
+    let secs = config::AppConfig::load(&path)
+        .map(|c| c.shutdown_timeout_secs)
+        .unwrap_or_else(|_| config::AppConfig::default().shutdown_timeout_secs);
+    std::time::Duration::from_secs(secs + 5)
+}
+
+/// Poll the health endpoint until the daemon stops responding or `timeout` elapses.
+fn wait_for_exit(addr: SocketAddr, timeout: std::time::Duration) {
+    let deadline = std::time::Instant::now() + timeout;
+    while std::time::Instant::now() < deadline {
+        if http_get(addr, "/health").is_none() {
+            return;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    }
+}
+
+/// `restart --wait` gave up on the new daemon; the command exits with status 1.
+#[derive(Debug)]
+pub struct NotReady;
+
+impl std::fmt::Display for NotReady {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Timeout waiting for daemon")
+    }
+}
+
+impl std::error::Error for NotReady {}
+
+/// Poll the health endpoint every 500ms until the daemon with `pid` answers,
+/// then print its PID. An old daemon still draining doesn't count. Fails
+/// with [`NotReady`] after `timeout_secs` without an answer.
+pub fn wait_for_healthy(pid: u32, timeout_secs: u64) -> Result<()> {
+    let addr = load_health_addr();
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(timeout_secs);
+    while std::time::Instant::now() < deadline {
+        let health = http_get_ok(addr, "/health")
+            .and_then(|b| serde_json::from_str::<serde_json::Value>(&b).ok());
+        if health.is_some_and(|h| h["pid"].as_u64() == Some(u64::from(pid))) {
+            println!("myagent ready (PID {pid})");
+            return Ok(());
+        }
+        std::thread::sleep(std::time::Duration::from_millis(500));
+    }
+    eprintln!("{NotReady}");
+    Err(NotReady.into())
+}
+
+/// Like `http_get`, but `None` unless the daemon answers `200`.
+fn http_get_ok(addr: SocketAddr, path: &str) -> Option<String> {
+    let response = http_get_response(addr, path)?;
+    if !is_status_ok(&response) {
+        return None;
+    }
+    response.split("\r\n\r\n").nth(1).map(|s| s.to_string())
 }
 
 /// Simple HTTP GET using raw TCP (no external deps needed for sync context).
-fn http_get(port: u16, path: &str) -> Option<String> {
-    let addr = format!("127.0.0.1:{}", port);
-    let mut stream = TcpStream::connect(&addr).ok()?;
+fn http_get(addr: SocketAddr, path: &str) -> Option<String> {
+    let response = http_get_response(addr, path)?;
+    // Extract body after \r\n\r\n
+    response.split("\r\n\r\n").nth(1).map(|s| s.to_string())
+}
+
+/// The raw response, status line and headers included, to a GET of `path`.
+fn http_get_response(addr: SocketAddr, path: &str) -> Option<String> {
+    let mut stream = TcpStream::connect(addr).ok()?;
     stream
         .set_read_timeout(Some(std::time::Duration::from_secs(2)))
         .ok()?;
@@ -189,14 +415,12 @@ fn http_get(port: u16, path: &str) -> Option<String> {
     stream.write_all(request.as_bytes()).ok()?;
     let mut response = String::new();
     stream.read_to_string(&mut response).ok()?;
-    // Extract body after \r\n\r\n
-    response.split("\r\n\r\n").nth(1).map(|s| s.to_string())
+    Some(response)
 }
 
-/// Simple HTTP POST JSON-RPC using raw TCP.
-fn http_post_rpc(port: u16, method: &str) -> Option<String> {
-    let addr = format!("127.0.0.1:{}", port);
-    let mut stream = TcpStream::connect(&addr).ok()?;
+/// Simple HTTP POST JSON-RPC using raw TCP. `None` unless the daemon answers `200`.
+fn http_post_rpc(addr: SocketAddr, method: &str) -> Option<String> {
+    let mut stream = TcpStream::connect(addr).ok()?;
     stream
         .set_read_timeout(Some(std::time::Duration::from_secs(3)))
         .ok()?;
@@ -212,27 +436,67 @@ fn http_post_rpc(port: u16, method: &str) -> Option<String> {
     stream.write_all(request.as_bytes()).ok()?;
     let mut response = String::new();
     stream.read_to_string(&mut response).ok()?;
+    if !is_status_ok(&response) {
+        return None;
+    }
     response.split("\r\n\r\n").nth(1).map(|s| s.to_string())
 }
 
+/// Whether a raw HTTP response starts with a `200` status line.
+fn is_status_ok(response: &str) -> bool {
+    response
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        == Some("200")
+}
+
 // ── Log rotation ──
 
 const MAX_LOG_SIZE: u64 = 10 * 1024 * 1024; // 10 MB
+/// Rotated logs kept as gzip files: myagent.log.1.gz (newest) to myagent.log.5.gz.
 const MAX_LOG_FILES: usize = 5;
 
-/// Rotate log file if it exceeds max_size.
+/// Rotate log file if it exceeds max_size, compressing the rotated copy.
 fn rotate_log(log_path: &std::path::Path, max_size: u64, max_files: usize) {
     let size = fs::metadata(log_path).map(|m| m.len()).unwrap_or(0);
     if size < max_size {
         return;
     }
-    for i in (1..max_files).rev() {
-        let from = log_path.with_extension(format!("log.{i}"));
-        let to = log_path.with_extension(format!("log.{}", i + 1));
-        let _ = fs::rename(&from, &to);
+    // Plain log.N files are copies whose compression failed; shift them too
+    for suffix in [".gz", ""] {
+        let name = |i: usize| log_path.with_extension(format!("log.{i}{suffix}"));
+        for i in (1..max_files).rev() {
+            let _ = fs::rename(name(i), name(i + 1));
+        }
+        let _ = fs::remove_file(name(max_files + 1));
+    }
+
+    let rotated = log_path.with_extension("log.1");
+    if fs::rename(log_path, &rotated).is_err() {
+        return;
+    }
+    let compressed = log_path.with_extension("log.1.gz");
+    match compress_file(&rotated, &compressed) {
+        Ok(()) => {
+            let _ = fs::remove_file(&rotated);
+        }
+        // Keep the plain copy rather than lose it; drop the partial archive
+        Err(e) => {
+            eprintln!("Failed to compress {}: {e}", rotated.display());
+            let _ = fs::remove_file(&compressed);
+        }
     }
-    let _ = fs::rename(log_path, log_path.with_extension("log.1"));
-    let _ = fs::remove_file(log_path.with_extension(format!("log.{}", max_files + 1)));
+}
+
+/// Gzip `from` into `to`.
+fn compress_file(from: &Path, to: &Path) -> Result<()> {
+    let mut input = fs::File::open(from)?;
+    let mut encoder =
+        flate2::write::GzEncoder::new(fs::File::create(to)?, flate2::Compression::default());
+    std::io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+    Ok(())
 }
 
 /// Clear all log files.
@@ -245,7 +509,9 @@ pub fn clear_logs() -> Result<()> {
     let mut count = 0;
     for entry in fs::read_dir(&log_dir)? {
         let entry = entry?;
-        if entry.file_name().to_string_lossy().starts_with("myagent.log") {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        // myagent.log plus rotated myagent.log.N and myagent.log.N.gz
+        if name == "myagent.log" || name.starts_with("myagent.log.") {
             fs::remove_file(entry.path())?;
             count += 1;
         }
@@ -1,94 +1,604 @@
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use indicatif::{ProgressBar, ProgressStyle};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{
+    Cmd, ConditionalEventHandler, Context, Editor, Event, EventContext, EventHandler, Helper,
+    KeyCode, KeyEvent, Modifiers, RepeatCount,
+};
+use tracing::{info, warn};
 
+use crate::config;
 use crate::protocol::{AgentEvent, AgentStatus, ContentBlock, Submission};
-use crate::thread_manager::ThreadManager;
+use crate::thread::AgentThread;
+use crate::thread_manager::{ThreadManager, ThreadOverrides};
 
 use crate::update_check::CURRENT_VERSION;
 
 use super::Frontend;
+use super::cost::{self, CostMeter};
+use super::highlight::CodeHighlighter;
+
+/// Maximum number of entries kept in the history file.
+const MAX_HISTORY_ENTRIES: usize = 1000;
+
+/// Longest tool input shown in the spinner message.
+const MAX_SPINNER_DETAIL: usize = 60;
 
 pub struct CliFrontend {
-    /// If Some, run in one-shot mode with this prompt.
+    /// If Some, run in one-shot mode with this prompt (from `-p`, or all of
+    /// stdin when it is piped).
     pub prompt: Option<String>,
-    /// Which agent type to use.
+    /// Which agent type to use; a comma-separated list is tried in order.
     pub agent_type: String,
     /// If Some, a newer version is available.
     pub update_hint: Option<String>,
+    /// Persist interactive input history to ~/.myagent/history.
+    pub history: bool,
+    /// Syntax-highlight fenced code blocks in agent output.
+    pub highlight: bool,
+    /// Run the `myagent chat` REPL: multi-line input and meta-commands.
+    pub chat: bool,
+    /// Prompt aliases from the config, expanded before a message is sent.
+    pub aliases: HashMap<String, String>,
+    /// Print a preview of each tool's output.
+    pub verbose: bool,
+    /// Cancel a one-shot run that takes longer than this.
+    pub timeout: Option<Duration>,
+    /// Show a running token count and cost estimate while output streams.
+    pub show_cost: bool,
+}
+
+/// How agent output is rendered.
+#[derive(Clone, Copy)]
+struct OutputOptions {
+    highlight: bool,
+    verbose: bool,
+    show_cost: bool,
+}
+
+/// A one-shot run was cancelled by `--timeout`.
+#[derive(Debug)]
+pub struct TimedOut(pub Duration);
+
+impl std::fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Timeout after {}s", self.0.as_secs())
+    }
+}
+
+impl std::error::Error for TimedOut {}
+
+/// Prints streamed text, optionally buffering each text block so its
+/// code fences can be highlighted once the block is complete.
+struct TextPrinter {
+    highlighter: Option<CodeHighlighter>,
+    buffer: String,
+    /// The last text printed didn't end with a newline.
+    mid_line: bool,
+}
+
+impl TextPrinter {
+    fn new(highlight: bool) -> Self {
+        Self {
+            highlighter: highlight.then(CodeHighlighter::new),
+            buffer: String::new(),
+            mid_line: false,
+        }
+    }
+
+    fn push(&mut self, text: &str) {
+        if self.highlighter.is_some() {
+            self.buffer.push_str(text);
+        } else if !text.is_empty() {
+            print!("{text}");
+            self.mid_line = !text.ends_with('\n');
+        }
+    }
+
+    /// Called at the end of a text block.
+    fn flush(&mut self) {
+        let Some(h) = &self.highlighter else {
+            return;
+        };
+        if !self.buffer.is_empty() {
+            let rendered = h.render(&self.buffer);
+            print!("{rendered}");
+            self.mid_line = !rendered.ends_with('\n');
+            self.buffer.clear();
+        }
+    }
+
+    /// Text is held back until the end of its block.
+    fn is_buffering(&self) -> bool {
+        self.highlighter.is_some()
+    }
+
+    fn at_line_start(&self) -> bool {
+        !self.mid_line
+    }
+}
+
+/// Stderr spinner shown while waiting for the model or a tool. Does nothing
+/// when stderr is not a terminal.
+struct Spinner {
+    enabled: bool,
+    bar: Option<ProgressBar>,
+    /// Name and streamed input of the tool call being received.
+    tool: Option<(String, String)>,
+    /// A tool result was just shown; the model is working on the next reply.
+    after_result: bool,
+}
+
+impl Spinner {
+    fn new() -> Self {
+        Self {
+            enabled: std::io::stderr().is_terminal(),
+            bar: None,
+            tool: None,
+            after_result: false,
+        }
+    }
+
+    fn start(&mut self, message: String) {
+        if !self.enabled {
+            return;
+        }
+        if let Some(bar) = &self.bar {
+            bar.set_message(message);
+            return;
+        }
+        let bar = ProgressBar::new_spinner();
+        if let Ok(style) = ProgressStyle::with_template("{spinner} {msg}") {
+            bar.set_style(style);
+        }
+        bar.set_message(message);
+        bar.enable_steady_tick(Duration::from_millis(100));
+        self.bar = Some(bar);
+    }
+
+    fn stop(&mut self) {
+        if let Some(bar) = self.bar.take() {
+            bar.finish_and_clear();
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.bar.is_some()
+    }
+
+    /// Called before an event is printed: clears the spinner line if the
+    /// event produces output.
+    fn before_event(&mut self, event: &AgentEvent) {
+        match event {
+            AgentEvent::TextDelta { .. }
+            | AgentEvent::ToolStart { .. }
+            | AgentEvent::ToolEnd { .. }
+            | AgentEvent::ToolResult { .. }
+            | AgentEvent::RateLimited { .. }
+            | AgentEvent::Error(_) => self.stop(),
+            AgentEvent::ContentBlockStart { content_block, .. } => {
+                self.stop();
+                self.tool = match content_block {
+                    ContentBlock::ToolUse { name, input, .. } => {
+                        let input = input
+                            .as_object()
+                            .filter(|o| !o.is_empty())
+                            .map(|_| input.to_string())
+                            .unwrap_or_default();
+                        Some((name.clone(), input))
+                    }
+                    _ => None,
+                };
+                self.after_result = matches!(content_block, ContentBlock::ToolResult { .. });
+            }
+            AgentEvent::InputJsonDelta { partial_json, .. } => {
+                if let Some((_, input)) = &mut self.tool {
+                    input.push_str(partial_json);
+                }
+            }
+            AgentEvent::StatusChange(status) if status.is_terminal() => self.stop(),
+            _ => {}
+        }
+    }
+
+    /// Called after an event is printed: spins while the agent works without
+    /// producing output.
+    fn after_event(&mut self, event: &AgentEvent) {
+        match event {
+            AgentEvent::StatusChange(AgentStatus::Working) | AgentEvent::RateLimited { .. } => {
+                self.start("Thinking...".to_string());
+            }
+            AgentEvent::ToolStart { name, input_summary } => {
+                self.start(format!("Executing {name}: {input_summary}"));
+            }
+            AgentEvent::ContentBlockStop { .. } => {
+                if let Some((name, input)) = self.tool.take() {
+                    let message = match tool_detail(&input) {
+                        Some(detail) => format!("Executing {name}: {detail}"),
+                        None => format!("Executing {name}"),
+                    };
+                    self.start(message);
+                } else if std::mem::take(&mut self.after_result) {
+                    self.start("Thinking...".to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The most telling argument of a tool call, e.g. the command for `shell`.
+fn tool_detail(input: &str) -> Option<String> {
+    let input: serde_json::Value = serde_json::from_str(input).ok()?;
+    let value = ["command", "path", "pattern"]
+        .iter()
+        .find_map(|key| input[key].as_str())?;
+    let line = value.lines().next().unwrap_or("");
+    if line.chars().count() > MAX_SPINNER_DETAIL {
+        let cut: String = line.chars().take(MAX_SPINNER_DETAIL).collect();
+        Some(format!("{cut}..."))
+    } else {
+        Some(line.to_string())
+    }
 }
 
 #[async_trait::async_trait]
 impl Frontend for CliFrontend {
     async fn run(self: Box<Self>, manager: Arc<ThreadManager>) -> Result<()> {
-        if let Some(prompt) = &self.prompt {
-            run_oneshot(&manager, &self.agent_type, prompt).await
+        let output = OutputOptions {
+            highlight: self.highlight,
+            verbose: self.verbose,
+            show_cost: self.show_cost,
+        };
+        if self.chat {
+            run_chat(
+                &manager,
+                &self.agent_type,
+                self.update_hint.as_deref(),
+                self.history,
+                output,
+                &self.aliases,
+            )
+            .await
+        } else if let Some(prompt) = &self.prompt {
+            let prompt = expand_alias(&self.aliases, prompt);
+            run_oneshot(&manager, &self.agent_type, &prompt, output, self.timeout).await
         } else {
-            run_interactive(&manager, &self.agent_type, self.update_hint.as_deref()).await
+            run_interactive(
+                &manager,
+                &self.agent_type,
+                self.update_hint.as_deref(),
+                self.history,
+                output,
+                &self.aliases,
+            )
+            .await
         }
     }
 }
 
+/// Expand `line` if its first word names an alias: `{args}` in the template
+/// becomes the rest of the line, which is appended when there is no
+/// placeholder. Other lines are returned unchanged.
+fn expand_alias(aliases: &HashMap<String, String>, line: &str) -> String {
+    let trimmed = line.trim();
+    let (name, args) = trimmed.split_once(char::is_whitespace).unwrap_or((trimmed, ""));
+    let Some(template) = aliases.get(name) else {
+        return line.to_string();
+    };
+    let args = args.trim();
+    if template.contains("{args}") {
+        template.replace("{args}", args)
+    } else if args.is_empty() {
+        template.clone()
+    } else {
+        format!("{template} {args}")
+    }
+}
+
 async fn run_oneshot(
-    manager: &ThreadManager,
+    manager: &Arc<ThreadManager>,
     agent_type: &str,
     prompt: &str,
+    output: OutputOptions,
+    timeout: Option<Duration>,
 ) -> Result<()> {
-    let (_thread_id, thread) = manager.create_thread(agent_type).await?;
+    let mut printer = TextPrinter::new(output.highlight);
+    let mut spinner = Spinner::new();
+    let (agent, thread) = create_thread_with_fallback(manager, agent_type).await?;
+    let mut meter = cost_meter(manager, &agent, output.show_cost).await;
     thread
         .submit(Submission::UserMessage(prompt.to_string()))
         .await?;
 
-    while let Some(event) = thread.next_event().await {
-        match &event {
-            AgentEvent::TextDelta { text, .. } => {
-                print!("{text}");
-            }
-            AgentEvent::ContentBlockStart {
-                content_block: ContentBlock::ToolUse { name, .. },
-                ..
-            } => {
-                eprintln!("\n--- Tool: {name} ---");
-            }
-            AgentEvent::ContentBlockStart {
-                content_block: ContentBlock::ToolResult { .. },
-                ..
-            } => {
-                eprintln!("--- Tool done ---");
-            }
-            AgentEvent::StatusChange(status) => {
-                if status.is_terminal() {
-                    match status {
-                        AgentStatus::Completed => {}
-                        AgentStatus::Failed(msg) => eprintln!("\nFailed: {msg}"),
-                        AgentStatus::Cancelled => eprintln!("\nCancelled"),
-                        _ => {}
-                    }
-                    break;
+    let mut ticker = tokio::time::interval(cost::REFRESH_INTERVAL);
+    let events = async {
+        loop {
+            let event = tokio::select! {
+                event = thread.next_event() => event,
+                _ = ticker.tick(), if meter.is_enabled() => {
+                    meter.draw(printer.at_line_start() && !spinner.is_active());
+                    continue;
                 }
-            }
-            AgentEvent::Error(msg) => {
-                eprintln!("\nError: {msg}");
+            };
+            let Some(event) = event else {
+                break;
+            };
+            meter.before_event(&event, printer.is_buffering());
+            spinner.before_event(&event);
+            if print_event(&mut printer, &event, output.verbose) {
                 break;
             }
-            _ => {}
+            spinner.after_event(&event);
         }
-    }
+    };
+    let timed_out = match timeout {
+        Some(limit) => tokio::time::timeout(limit, events).await.is_err(),
+        None => {
+            events.await;
+            false
+        }
+    };
+    meter.clear();
+    spinner.stop();
+    printer.flush();
     println!();
+    if let Some(limit) = timeout.filter(|_| timed_out) {
+        let _ = thread.submit(Submission::Cancel).await;
+        let err = TimedOut(limit);
+        eprintln!("{err}");
+        return Err(err.into());
+    }
+    Ok(())
+}
+
+/// Start a thread with the first agent of a comma-separated fallback chain
+/// (e.g. `myagent,claude`) that starts; returns the agent used. Fails with
+/// the last error when none does.
+async fn create_thread_with_fallback(
+    manager: &Arc<ThreadManager>,
+    agent_types: &str,
+) -> Result<(String, Arc<AgentThread>)> {
+    let mut last_err = None;
+    for agent in agent_types.split(',').map(str::trim).filter(|a| !a.is_empty()) {
+        if last_err.is_some() {
+            info!("Falling back to agent {agent}");
+        }
+        match manager.create_thread(agent, ThreadOverrides::default()).await {
+            Ok((_thread_id, thread)) => return Ok((agent.to_string(), thread)),
+            Err(e) => {
+                info!("Agent {agent} failed to start: {e}");
+                last_err = Some(e);
+            }
+        }
+    }
+    Err(last_err.unwrap_or_else(|| anyhow::anyhow!("No agent given")))
+}
+
+/// A cost meter priced for the model `agent` runs; only the built-in agent's
+/// model is known.
+async fn cost_meter(manager: &ThreadManager, agent: &str, enabled: bool) -> CostMeter {
+    if !enabled {
+        return CostMeter::new(false, None);
+    }
+    let model = if agent == "myagent" {
+        Some(manager.config().await.myagent_env().model)
+    } else {
+        None
+    };
+    CostMeter::new(true, model.as_deref())
+}
+
+/// Render recorded events as if they came from a live agent.
+pub fn replay(events: &[AgentEvent], highlight: bool) -> Result<()> {
+    let mut printer = TextPrinter::new(highlight);
+    for event in events {
+        if print_event(&mut printer, event, false) {
+            printer.flush();
+            println!();
+        }
+    }
+    printer.flush();
+    Ok(())
+}
+
+/// Print one event; returns true when it ends the turn. With `verbose`, tool
+/// output previews are printed too.
+fn print_event(printer: &mut TextPrinter, event: &AgentEvent, verbose: bool) -> bool {
+    match event {
+        AgentEvent::TextDelta { text, .. } => {
+            printer.push(text);
+        }
+        AgentEvent::ContentBlockStop { .. } => {
+            printer.flush();
+        }
+        AgentEvent::ContentBlockStart {
+            content_block: ContentBlock::ToolUse { name, .. },
+            ..
+        } => {
+            eprintln!("\n--- Tool: {name} ---");
+        }
+        AgentEvent::ContentBlockStart {
+            content_block: ContentBlock::ToolResult { .. },
+            ..
+        } => {
+            eprintln!("--- Tool done ---");
+        }
+        AgentEvent::StatusChange(status) => {
+            if status.is_terminal() {
+                match status {
+                    AgentStatus::Completed => {}
+                    AgentStatus::Failed(msg) => eprintln!("\nFailed: {msg}"),
+                    AgentStatus::Cancelled => eprintln!("\nCancelled"),
+                    _ => {}
+                }
+                return true;
+            }
+        }
+        AgentEvent::ToolStart { name, .. } => {
+            eprintln!("[tool: {name} (executing...)]");
+        }
+        AgentEvent::ToolEnd { name, duration_ms, is_error } => {
+            let outcome = if *is_error { "failed" } else { "done" };
+            eprintln!("[tool: {name} {outcome} in {:.1}s]", *duration_ms as f64 / 1000.0);
+        }
+        AgentEvent::ToolResult { output_preview, .. } if verbose => {
+            eprintln!("[result: {}]", output_preview.trim_end());
+        }
+        AgentEvent::RateLimited { retry_after_ms } => {
+            eprintln!("\nRate limited, retrying in {}s...", retry_after_ms.div_ceil(1000));
+        }
+        AgentEvent::Error(msg) => {
+            eprintln!("\nError: {msg}");
+            return true;
+        }
+        _ => {}
+    }
+    false
+}
+
+/// Completes the current input from previously submitted commands.
+struct HistoryHelper {
+    entries: Vec<String>,
+}
+
+impl Completer for HistoryHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let prefix = &line[..pos];
+        if prefix.is_empty() {
+            return Ok((0, Vec::new()));
+        }
+        // Most recent first, without duplicates
+        let mut candidates: Vec<String> = Vec::new();
+        for entry in self.entries.iter().rev() {
+            if entry.starts_with(prefix) && !candidates.contains(entry) {
+                candidates.push(entry.clone());
+            }
+        }
+        Ok((0, candidates))
+    }
+}
+
+impl Hinter for HistoryHelper {
+    type Hint = String;
+}
+
+impl Highlighter for HistoryHelper {}
+
+impl Validator for HistoryHelper {}
+
+impl Helper for HistoryHelper {}
+
+/// Chat-mode Enter: submits an empty buffer, a meta-command or input ending
+/// in a blank line; otherwise inserts a newline.
+struct ChatEnter;
+
+impl ConditionalEventHandler for ChatEnter {
+    fn handle(&self, _: &Event, _: RepeatCount, _: bool, ctx: &EventContext) -> Option<Cmd> {
+        let line = ctx.line();
+        let blank_line = ctx.pos() == line.len() && line.ends_with('\n');
+        if line.is_empty() || blank_line || is_meta_command(line) {
+            Some(Cmd::AcceptLine)
+        } else {
+            Some(Cmd::Newline)
+        }
+    }
+}
+
+/// Chat-mode Ctrl+D: submits the buffer, or quits when it is empty.
+struct ChatSubmit;
+
+impl ConditionalEventHandler for ChatSubmit {
+    fn handle(&self, _: &Event, _: RepeatCount, _: bool, ctx: &EventContext) -> Option<Cmd> {
+        if ctx.line().is_empty() {
+            Some(Cmd::EndOfFile)
+        } else {
+            Some(Cmd::AcceptLine)
+        }
+    }
+}
+
+type LineEditor = Editor<HistoryHelper, DefaultHistory>;
+
+fn build_editor(history_path: Option<&Path>, multiline: bool) -> Result<LineEditor> {
+    let rl_config = rustyline::Config::builder()
+        .max_history_size(MAX_HISTORY_ENTRIES)?
+        .auto_add_history(false)
+        .build();
+    let mut editor = LineEditor::with_config(rl_config)?;
+    let mut entries = Vec::new();
+    if let Some(path) = history_path.filter(|p| p.exists()) {
+        if let Err(e) = editor.load_history(path) {
+            warn!("Failed to load history from {}: {e}", path.display());
+        }
+        entries = editor.history().iter().cloned().collect();
+    }
+    editor.set_helper(Some(HistoryHelper { entries }));
+    if multiline {
+        editor.bind_sequence(
+            KeyEvent(KeyCode::Enter, Modifiers::NONE),
+            EventHandler::Conditional(Box::new(ChatEnter)),
+        );
+        editor.bind_sequence(
+            KeyEvent::ctrl('D'),
+            EventHandler::Conditional(Box::new(ChatSubmit)),
+        );
+    }
+    Ok(editor)
+}
+
+fn save_history(editor: &mut LineEditor, path: &Path) {
+    if let Err(e) = write_history(editor, path) {
+        warn!("Failed to save history to {}: {e}", path.display());
+    }
+}
+
+/// Write history atomically: save to `.history.tmp`, then rename over the target.
+fn write_history(editor: &mut LineEditor, path: &Path) -> Result<()> {
+    let tmp = path.with_file_name(".history.tmp");
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    editor.save_history(&tmp)?;
+    std::fs::rename(&tmp, path)?;
     Ok(())
 }
 
 async fn run_interactive(
-    manager: &ThreadManager,
+    manager: &Arc<ThreadManager>,
     agent_type: &str,
     update_hint: Option<&str>,
+    history: bool,
+    output: OutputOptions,
+    aliases: &HashMap<String, String>,
 ) -> Result<()> {
-    let stdin = BufReader::new(tokio::io::stdin());
-    let mut lines = stdin.lines();
+    let mut printer = TextPrinter::new(output.highlight);
+    let mut spinner = Spinner::new();
+    let history_path = history.then(config::history_file_path);
+    let mut editor = build_editor(history_path.as_deref(), false)?;
 
     eprintln!("myagent v{CURRENT_VERSION} (type 'exit' to quit)");
-    eprintln!("Agent: {agent_type}");
+    let (agent, thread) = create_thread_with_fallback(manager, agent_type).await?;
+    let mut meter = cost_meter(manager, &agent, output.show_cost).await;
+    eprintln!("Agent: {agent}");
     if let Some(latest) = update_hint {
         eprintln!(
             "\n  Update available: {CURRENT_VERSION} -> {latest}. Run `myagent update` to upgrade."
@@ -96,13 +606,14 @@ async fn run_interactive(
     }
     eprintln!();
 
-    let (_thread_id, thread) = manager.create_thread(agent_type).await?;
     let mut first_message = true;
 
     loop {
-        eprint!("> ");
-        let Some(line) = lines.next_line().await? else {
-            break;
+        // rustyline blocks on the terminal; keep the runtime's other tasks moving
+        let line = match tokio::task::block_in_place(|| editor.readline("> ")) {
+            Ok(line) => line,
+            Err(ReadlineError::Eof | ReadlineError::Interrupted) => break,
+            Err(e) => return Err(e.into()),
         };
         let line = line.trim().to_string();
         if line.is_empty() {
@@ -112,6 +623,9 @@ async fn run_interactive(
             break;
         }
 
+        remember_line(&mut editor, history_path.as_deref(), &line)?;
+
+        let line = expand_alias(aliases, &line);
         let sub = if first_message {
             first_message = false;
             Submission::UserMessage(line)
@@ -120,40 +634,175 @@ async fn run_interactive(
         };
         thread.submit(sub).await?;
 
-        // Drain events until status is terminal
-        while let Some(event) = thread.next_event().await {
-            match &event {
-                AgentEvent::TextDelta { text, .. } => {
-                    print!("{text}");
-                }
-                AgentEvent::ContentBlockStart {
-                    content_block: ContentBlock::ToolUse { name, .. },
-                    ..
-                } => {
-                    eprintln!("\n--- Tool: {name} ---");
-                }
-                AgentEvent::ContentBlockStart {
-                    content_block: ContentBlock::ToolResult { .. },
-                    ..
-                } => {
-                    eprintln!("--- Tool done ---");
-                }
-                AgentEvent::StatusChange(status) => {
-                    if status.is_terminal() {
-                        if let AgentStatus::Failed(msg) = status {
-                            eprintln!("\nFailed: {msg}");
-                        }
-                        break;
-                    }
-                }
-                AgentEvent::Error(msg) => {
-                    eprintln!("\nError: {msg}");
-                    break;
+        drain_turn(&thread, &mut printer, &mut spinner, &mut meter, false, output.verbose).await;
+    }
+
+    Ok(())
+}
+
+/// Print a turn's events until its status is terminal. With `interruptible`,
+/// Ctrl+C stops waiting and returns `true`.
+async fn drain_turn(
+    thread: &AgentThread,
+    printer: &mut TextPrinter,
+    spinner: &mut Spinner,
+    meter: &mut CostMeter,
+    interruptible: bool,
+    verbose: bool,
+) -> bool {
+    let mut interrupted = false;
+    let mut ticker = tokio::time::interval(cost::REFRESH_INTERVAL);
+    meter.reset();
+    loop {
+        let event = tokio::select! {
+            event = thread.next_event() => event,
+            _ = tokio::signal::ctrl_c(), if interruptible => {
+                interrupted = true;
+                break;
+            }
+            _ = ticker.tick(), if meter.is_enabled() => {
+                meter.draw(printer.at_line_start() && !spinner.is_active());
+                continue;
+            }
+        };
+        let Some(event) = event else {
+            break;
+        };
+        meter.before_event(&event, printer.is_buffering());
+        spinner.before_event(&event);
+        if print_event(printer, &event, verbose) {
+            break;
+        }
+        spinner.after_event(&event);
+    }
+    meter.clear();
+    spinner.stop();
+    printer.flush();
+    println!();
+    interrupted
+}
+
+/// Whether chat input is handled by the REPL instead of the agent.
+fn is_meta_command(line: &str) -> bool {
+    let line = line.trim();
+    matches!(line, "exit" | "quit" | "/clear") || line == "/thread" || line.starts_with("/thread ")
+}
+
+/// Record a submitted line in the editor history and the history file.
+fn remember_line(editor: &mut LineEditor, history_path: Option<&Path>, line: &str) -> Result<()> {
+    editor.add_history_entry(line)?;
+    if let Some(helper) = editor.helper_mut() {
+        helper.entries.push(line.to_string());
+    }
+    if let Some(path) = history_path {
+        save_history(editor, path);
+    }
+    Ok(())
+}
+
+/// `myagent chat`: like interactive mode, but input can span several lines
+/// (Enter adds a line; a blank line or Ctrl+D sends), Ctrl+C cancels the
+/// running turn, and `/clear`, `/thread new` and `/thread list` are handled
+/// by the REPL.
+async fn run_chat(
+    manager: &Arc<ThreadManager>,
+    agent_type: &str,
+    update_hint: Option<&str>,
+    history: bool,
+    output: OutputOptions,
+    aliases: &HashMap<String, String>,
+) -> Result<()> {
+    let mut printer = TextPrinter::new(output.highlight);
+    let mut spinner = Spinner::new();
+    let history_path = history.then(config::history_file_path);
+    let mut editor = build_editor(history_path.as_deref(), true)?;
+
+    eprintln!("myagent v{CURRENT_VERSION} chat");
+    eprintln!("Enter adds a line; a blank line or Ctrl+D sends. Ctrl+C cancels a turn.");
+    eprintln!("Commands: /clear, /thread new, /thread list, exit");
+    let (agent, thread) = create_thread_with_fallback(manager, agent_type).await?;
+    let mut meter = cost_meter(manager, &agent, output.show_cost).await;
+    eprintln!("Agent: {agent}");
+    if let Some(latest) = update_hint {
+        eprintln!(
+            "\n  Update available: {CURRENT_VERSION} -> {latest}. Run `myagent update` to upgrade."
+        );
+    }
+    eprintln!();
+
+    // Every thread started this session, oldest first; the last is current
+    let mut threads = vec![(agent, thread)];
+    let mut first_message = true;
+
+    loop {
+        let (agent, thread) = threads.last().unwrap().clone();
+        let id = thread.thread_id.to_string();
+        let prompt = format!("myagent[{agent}#{}] > ", id.get(..6).unwrap_or(&id));
+        let line = match tokio::task::block_in_place(|| editor.readline(&prompt)) {
+            Ok(line) => line,
+            Err(ReadlineError::Eof) => break,
+            // Ctrl+C at the prompt discards the input
+            Err(ReadlineError::Interrupted) => continue,
+            Err(e) => return Err(e.into()),
+        };
+        let line = line.trim().to_string();
+        if line.is_empty() {
+            continue;
+        }
+        remember_line(&mut editor, history_path.as_deref(), &line)?;
+
+        match line.as_str() {
+            "exit" | "quit" => break,
+            "/clear" => {
+                editor.clear_screen()?;
+                continue;
+            }
+            "/thread new" => {
+                let (agent, thread) = create_thread_with_fallback(manager, agent_type).await?;
+                eprintln!("New thread {} ({agent})", thread.thread_id);
+                threads.push((agent, thread));
+                first_message = true;
+                continue;
+            }
+            "/thread list" => {
+                let current = threads.len() - 1;
+                for (i, (agent, thread)) in threads.iter().enumerate() {
+                    let marker = if i == current { "*" } else { " " };
+                    let state = if thread.is_finished() { "finished" } else { "active" };
+                    eprintln!("{marker} {} {agent} ({state})", thread.thread_id);
                 }
-                _ => {}
+                continue;
+            }
+            _ if is_meta_command(&line) => {
+                eprintln!("Usage: /thread new | /thread list");
+                continue;
+            }
+            _ => {}
+        }
+
+        let line = expand_alias(aliases, &line);
+        let sub = if first_message {
+            first_message = false;
+            Submission::UserMessage(line)
+        } else {
+            Submission::FollowUp(line)
+        };
+        thread.submit(sub).await?;
+
+        if drain_turn(&thread, &mut printer, &mut spinner, &mut meter, true, output.verbose).await {
+            // The agent stops the turn and keeps the conversation; wait for
+            // its Cancelled status before prompting again
+            let _ = thread.submit(Submission::Cancel).await;
+            drain_turn(&thread, &mut printer, &mut spinner, &mut meter, false, output.verbose).await;
+            if thread.is_finished() {
+                // The turn had already ended, so the Cancel stopped the agent
+                manager.remove_thread(&thread.thread_id).await;
+                let (agent, thread) = create_thread_with_fallback(manager, agent_type).await?;
+                eprintln!("Continuing in new thread {} ({agent})", thread.thread_id);
+                threads.push((agent, thread));
+                first_message = true;
             }
         }
-        println!();
     }
 
     Ok(())
@@ -0,0 +1,109 @@
+use std::io::{IsTerminal, Write};
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::protocol::AgentEvent;
+
+/// How often the running estimate is redrawn.
+pub const REFRESH_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Rough characters per token for English text and code.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Per-model prices in USD per million tokens.
+const MODELS_JSON: &str = include_str!("../../assets/models.json");
+
+/// Running token count and cost estimate for `--show-cost`, drawn on stderr
+/// with `\r` overwrites. It is only drawn at the start of a line, so streamed
+/// text is never overwritten. Does nothing when stderr is not a terminal.
+pub struct CostMeter {
+    enabled: bool,
+    /// USD per output token, when the model's price is known.
+    price: Option<f64>,
+    chars: usize,
+    drawn: bool,
+}
+
+impl CostMeter {
+    pub fn new(enabled: bool, model: Option<&str>) -> Self {
+        Self {
+            enabled: enabled && std::io::stderr().is_terminal(),
+            price: model.and_then(output_price),
+            chars: 0,
+            drawn: false,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Start counting a new turn.
+    pub fn reset(&mut self) {
+        self.chars = 0;
+    }
+
+    /// Called before an event is printed: counts generated text and clears
+    /// the estimate if the event may print. With `buffering`, text deltas
+    /// are held back by the printer and leave the line alone.
+    pub fn before_event(&mut self, event: &AgentEvent, buffering: bool) {
+        let quiet = match event {
+            AgentEvent::TextDelta { text, .. } => {
+                self.chars += text.chars().count();
+                buffering
+            }
+            AgentEvent::InputJsonDelta { partial_json, .. } => {
+                self.chars += partial_json.chars().count();
+                true
+            }
+            AgentEvent::Usage { .. } => true,
+            _ => false,
+        };
+        if !quiet {
+            self.clear();
+        }
+    }
+
+    /// Redraw the estimate, e.g. `[~1.2k tokens | ~$0.0018]`, if the cursor
+    /// is `at_line_start`.
+    pub fn draw(&mut self, at_line_start: bool) {
+        if !self.enabled || !at_line_start {
+            return;
+        }
+        let tokens = self.chars.div_ceil(CHARS_PER_TOKEN);
+        let count = if tokens < 1000 {
+            tokens.to_string()
+        } else {
+            format!("{:.1}k", tokens as f64 / 1000.0)
+        };
+        let label = match self.price {
+            Some(price) => format!("[~{count} tokens | ~${:.4}]", tokens as f64 * price),
+            None => format!("[~{count} tokens]"),
+        };
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(stderr, "\r\x1b[K{label}");
+        let _ = stderr.flush();
+        self.drawn = true;
+    }
+
+    /// Erase the estimate so output can take its place.
+    pub fn clear(&mut self) {
+        if std::mem::take(&mut self.drawn) {
+            let mut stderr = std::io::stderr().lock();
+            let _ = write!(stderr, "\r\x1b[K");
+            let _ = stderr.flush();
+        }
+    }
+}
+
+/// USD per output token for `model`, looked up by its full name and then
+/// without a provider prefix (e.g. OpenRouter's `anthropic/...`).
+fn output_price(model: &str) -> Option<f64> {
+    let models: Value = serde_json::from_str(MODELS_JSON).ok()?;
+    let unprefixed = model.rsplit('/').next().unwrap_or(model);
+    [model, unprefixed]
+        .iter()
+        .find_map(|name| models[*name]["output_per_mtok"].as_f64())
+        .map(|per_mtok| per_mtok / 1_000_000.0)
+}
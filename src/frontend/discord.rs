@@ -0,0 +1,524 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use serenity::all::{
+    ChannelId, Client, Context, CreateEmbed, CreateMessage, EditMessage, EventHandler,
+    GatewayIntents, Http, Message, MessageId, Ready,
+};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use crate::config::DiscordConfig;
+use crate::protocol::{AgentEvent, AgentStatus, ContentBlock, Submission, ThreadId};
+use crate::thread::AgentThread;
+use crate::thread_manager::{ThreadManager, ThreadOverrides};
+
+use super::{
+    chat_default_agent, command_usage, parse_command, spawn_drain_watcher, split_message, Frontend,
+};
+
+/// Discord rejects message content longer than this.
+const MAX_MESSAGE_CHARS: usize = 2000;
+/// Discord allows at most 10 embeds per message.
+const MAX_EMBEDS: usize = 10;
+/// Discord allows 5 edits per 5 seconds on a single message.
+const EDITS_PER_WINDOW: usize = 5;
+const EDIT_WINDOW: Duration = Duration::from_secs(5);
+
+/// Sliding-window limiter for edits to one message.
+struct EditLimiter {
+    recent: VecDeque<Instant>,
+}
+
+impl EditLimiter {
+    fn new() -> Self {
+        Self {
+            recent: VecDeque::with_capacity(EDITS_PER_WINDOW),
+        }
+    }
+
+    /// Record an edit if one is allowed right now.
+    fn try_acquire(&mut self) -> bool {
+        let now = Instant::now();
+        while self
+            .recent
+            .front()
+            .is_some_and(|t| now.duration_since(*t) >= EDIT_WINDOW)
+        {
+            self.recent.pop_front();
+        }
+        if self.recent.len() >= EDITS_PER_WINDOW {
+            return false;
+        }
+        self.recent.push_back(now);
+        true
+    }
+
+    /// How long until the next edit is allowed.
+    fn wait_time(&self) -> Duration {
+        if self.recent.len() < EDITS_PER_WINDOW {
+            return Duration::ZERO;
+        }
+        self.recent
+            .front()
+            .map(|t| EDIT_WINDOW.saturating_sub(t.elapsed()))
+            .unwrap_or_default()
+    }
+
+    /// Claim the next edit slot even if it is in the future; returns how
+    /// long to wait before making the edit.
+    fn reserve(&mut self) -> Duration {
+        let wait = self.wait_time();
+        if self.recent.len() >= EDITS_PER_WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(Instant::now() + wait);
+        wait
+    }
+}
+
+/// Per-thread rendering state for the bot's reply message.
+struct ThreadRenderState {
+    channel_id: ChannelId,
+    /// The user message the current reply answers.
+    request_msg_id: MessageId,
+    reply_msg_id: Option<MessageId>,
+    text_buffer: String,
+    tools: Vec<String>,
+    limiter: EditLimiter,
+    /// Text changed since the last successful edit.
+    dirty: bool,
+    /// The current turn's reply has received its final edit.
+    turn_finished: bool,
+}
+
+/// Internal events for the Discord frontend's main loop.
+enum DiscordInternalEvent {
+    NewMessage {
+        channel_id: ChannelId,
+        msg_id: MessageId,
+        /// Set when the user replied to one of the bot's messages.
+        reply_to: Option<MessageId>,
+        author: String,
+        text: String,
+    },
+    ReplyReady {
+        thread_id: ThreadId,
+        msg_id: MessageId,
+    },
+    AgentOutput {
+        thread_id: ThreadId,
+        event: AgentEvent,
+    },
+    /// Shutdown drain finished; `remaining` threads did not exit in time.
+    Drained {
+        remaining: Vec<ThreadId>,
+    },
+}
+
+/// Discord bot frontend: answers `<prefix> <prompt>` in allowed guilds, and
+/// allowed users anywhere including DMs.
+pub struct DiscordFrontend {
+    config: DiscordConfig,
+}
+
+impl DiscordFrontend {
+    pub fn new(config: DiscordConfig) -> Self {
+        Self { config }
+    }
+}
+
+struct Handler {
+    config: DiscordConfig,
+    fe_tx: mpsc::Sender<DiscordInternalEvent>,
+}
+
+#[serenity::async_trait]
+impl EventHandler for Handler {
+    async fn ready(&self, _ctx: Context, ready: Ready) {
+        info!("Discord bot connected as {}", ready.user.name);
+    }
+
+    async fn message(&self, ctx: Context, msg: Message) {
+        if msg.author.bot {
+            return;
+        }
+        // The agent can run commands on this host: deny unless allowed
+        let allowed_user = self.config.allowed_user_ids.contains(&msg.author.id.get());
+        let text = match msg.guild_id {
+            // Direct message: the whole message is the prompt
+            None if allowed_user => msg.content.trim().to_string(),
+            None => {
+                info!("Ignoring Discord DM from {}: not an allowed user", msg.author.name);
+                return;
+            }
+            Some(guild_id) => {
+                if !allowed_user && !self.config.guild_ids.contains(&guild_id.get()) {
+                    return;
+                }
+                // A reply to one of our messages is a follow-up, no prefix needed
+                let replying_to_bot = msg
+                    .referenced_message
+                    .as_ref()
+                    .is_some_and(|m| m.author.id == ctx.cache.current_user().id);
+                match msg.content.strip_prefix(&self.config.command_prefix) {
+                    Some(rest) => rest.trim().to_string(),
+                    None if replying_to_bot => msg.content.trim().to_string(),
+                    None => return,
+                }
+            }
+        };
+        if text.is_empty() {
+            return;
+        }
+        let _ = self
+            .fe_tx
+            .send(DiscordInternalEvent::NewMessage {
+                channel_id: msg.channel_id,
+                msg_id: msg.id,
+                reply_to: msg.referenced_message.as_ref().map(|m| m.id),
+                author: msg.author.name.clone(),
+                text,
+            })
+            .await;
+    }
+}
+
+#[async_trait::async_trait]
+impl Frontend for DiscordFrontend {
+    async fn run(self: Box<Self>, manager: Arc<ThreadManager>) -> Result<()> {
+        if self.config.guild_ids.is_empty() && self.config.allowed_user_ids.is_empty() {
+            anyhow::bail!(
+                "Discord: set channels.discord.guild_ids or allowed_user_ids; \
+                 the bot answers nobody else"
+            );
+        }
+        let (fe_tx, mut fe_rx) = mpsc::channel::<DiscordInternalEvent>(512);
+
+        let intents = GatewayIntents::GUILD_MESSAGES
+            | GatewayIntents::DIRECT_MESSAGES
+            | GatewayIntents::MESSAGE_CONTENT;
+        let handler = Handler {
+            config: self.config.clone(),
+            fe_tx: fe_tx.clone(),
+        };
+        let mut client = Client::builder(&self.config.token, intents)
+            .event_handler(handler)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to create Discord client: {e}"))?;
+        let http = client.http.clone();
+        let shard_manager = client.shard_manager.clone();
+        tokio::spawn(async move {
+            if let Err(e) = client.start().await {
+                error!("Discord gateway error: {e}");
+            }
+        });
+
+        spawn_drain_watcher(manager.clone(), fe_tx.clone(), |remaining| {
+            DiscordInternalEvent::Drained { remaining }
+        });
+        info!("Discord frontend started");
+
+        let mut render_states: HashMap<ThreadId, ThreadRenderState> = HashMap::new();
+        let mut reply_to_thread: HashMap<MessageId, ThreadId> = HashMap::new();
+
+        while let Some(event) = fe_rx.recv().await {
+            match event {
+                DiscordInternalEvent::NewMessage {
+                    channel_id,
+                    msg_id,
+                    reply_to,
+                    author,
+                    text,
+                } => {
+                    manager.record_activity();
+                    if manager.is_draining() {
+                        info!("Ignoring Discord message from {author}: shutting down");
+                        continue;
+                    }
+
+                    // Reply to one of our messages → follow-up on that thread
+                    let follow_up = match reply_to.and_then(|id| reply_to_thread.get(&id)).cloned() {
+                        Some(tid) => manager.get_thread(&tid).await,
+                        None => None,
+                    };
+                    if let Some(thread) = follow_up {
+                        let tid = &thread.thread_id;
+                        info!("[{tid}] Routing Discord reply");
+                        if let Some(state) = render_states.get_mut(tid) {
+                            state.request_msg_id = msg_id;
+                        }
+                        let _ = thread.submit(Submission::FollowUp(text)).await;
+                        continue;
+                    }
+
+                    let default_agent = chat_default_agent(&manager).await;
+                    let Some((agent_type, prompt)) = parse_command(&text, &default_agent) else {
+                        let http = http.clone();
+                        tokio::spawn(async move {
+                            let usage = CreateMessage::new()
+                                .content(command_usage())
+                                .reference_message((channel_id, msg_id));
+                            if let Err(e) = channel_id.send_message(&http, usage).await {
+                                error!("Failed to send command usage: {e}");
+                            }
+                        });
+                        continue;
+                    };
+                    let agent_type = agent_type.as_str();
+
+                    let (thread_id, thread) = match manager.create_thread(agent_type, ThreadOverrides::default()).await {
+                        Ok(v) => v,
+                        Err(e) => {
+                            error!("Failed to create thread: {e}");
+                            continue;
+                        }
+                    };
+                    info!("[{thread_id}] New Discord task: user={author}, agent={agent_type}");
+
+                    if let Err(e) = thread.submit(Submission::UserMessage(prompt)).await {
+                        error!("[{thread_id}] Failed to submit: {e}");
+                        continue;
+                    }
+
+                    render_states.insert(
+                        thread_id.clone(),
+                        ThreadRenderState {
+                            channel_id,
+                            request_msg_id: msg_id,
+                            reply_msg_id: None,
+                            text_buffer: String::new(),
+                            tools: Vec::new(),
+                            limiter: EditLimiter::new(),
+                            dirty: false,
+                            turn_finished: false,
+                        },
+                    );
+                    spawn_reply(&http, &fe_tx, thread_id, channel_id, msg_id);
+                    spawn_event_poller(thread, fe_tx.clone());
+                }
+
+                DiscordInternalEvent::ReplyReady { thread_id, msg_id } => {
+                    if let Some(state) = render_states.get_mut(&thread_id) {
+                        state.reply_msg_id = Some(msg_id);
+                        reply_to_thread.insert(msg_id, thread_id);
+                        // Show anything that arrived before the reply existed
+                        if state.turn_finished {
+                            finish_reply(state, &http);
+                        } else if state.dirty {
+                            update_reply(state, &http).await;
+                        }
+                    }
+                }
+
+                DiscordInternalEvent::AgentOutput { thread_id, event } => {
+                    if let Some(state) = render_states.get_mut(&thread_id) {
+                        handle_agent_event(state, &http, &fe_tx, &thread_id, event).await;
+                    }
+                }
+
+                DiscordInternalEvent::Drained { remaining } => {
+                    let mut edits = Vec::new();
+                    for tid in &remaining {
+                        if let Some(state) = render_states.get_mut(tid) {
+                            if state.turn_finished {
+                                continue;
+                            }
+                            warn!("[{tid}] Still running at shutdown");
+                            state.text_buffer.push_str("\n\n⚠️ Agent shutting down.");
+                            state.turn_finished = true;
+                            edits.extend(finish_reply(state, &http));
+                        }
+                    }
+                    futures_util::future::join_all(edits).await;
+                    info!("Discord frontend drained");
+                    break;
+                }
+            }
+        }
+
+        shard_manager.shutdown_all().await;
+        Ok(())
+    }
+}
+
+/// Spawn a task that forwards AgentEvents from a thread's EQ.
+/// Stays alive across turns so follow-ups reuse the same thread.
+fn spawn_event_poller(thread: Arc<AgentThread>, fe_tx: mpsc::Sender<DiscordInternalEvent>) {
+    let thread_id = thread.thread_id.clone();
+    tokio::spawn(async move {
+        while let Some(event) = thread.next_event().await {
+            let _ = fe_tx
+                .send(DiscordInternalEvent::AgentOutput {
+                    thread_id: thread_id.clone(),
+                    event,
+                })
+                .await;
+        }
+    });
+}
+
+/// Post the placeholder reply that gets edited as output streams in.
+fn spawn_reply(
+    http: &Arc<Http>,
+    fe_tx: &mpsc::Sender<DiscordInternalEvent>,
+    thread_id: ThreadId,
+    channel_id: ChannelId,
+    request_msg_id: MessageId,
+) {
+    let http = http.clone();
+    let fe_tx = fe_tx.clone();
+    tokio::spawn(async move {
+        let builder = CreateMessage::new()
+            .content("⏳ Working...")
+            .reference_message((channel_id, request_msg_id));
+        match channel_id.send_message(&http, builder).await {
+            Ok(msg) => {
+                let _ = fe_tx
+                    .send(DiscordInternalEvent::ReplyReady {
+                        thread_id,
+                        msg_id: msg.id,
+                    })
+                    .await;
+            }
+            Err(e) => error!("[{thread_id}] Failed to send Discord reply: {e}"),
+        }
+    });
+}
+
+async fn handle_agent_event(
+    state: &mut ThreadRenderState,
+    http: &Arc<Http>,
+    fe_tx: &mpsc::Sender<DiscordInternalEvent>,
+    thread_id: &ThreadId,
+    event: AgentEvent,
+) {
+    match event {
+        AgentEvent::TextDelta { text, .. } => {
+            state.text_buffer.push_str(&text);
+            state.dirty = true;
+            if state.limiter.try_acquire() {
+                update_reply(state, http).await;
+            }
+        }
+
+        AgentEvent::ContentBlockStart {
+            content_block: ContentBlock::ToolUse { name, .. },
+            ..
+        } => {
+            info!("[{thread_id}] Tool start: {name}");
+            state.tools.push(name);
+            state.dirty = true;
+            if state.limiter.try_acquire() {
+                update_reply(state, http).await;
+            }
+        }
+
+        AgentEvent::StatusChange(ref status) => {
+            info!("[{thread_id}] Status: {status:?}");
+            if *status == AgentStatus::Working && state.turn_finished {
+                // Follow-up turn: start a fresh reply to the new request
+                state.text_buffer.clear();
+                state.tools.clear();
+                state.reply_msg_id = None;
+                state.limiter = EditLimiter::new();
+                state.turn_finished = false;
+                spawn_reply(http, fe_tx, thread_id.clone(), state.channel_id, state.request_msg_id);
+            }
+            if let AgentStatus::Failed(msg) = status {
+                state.text_buffer.push_str(&format!("\n\n**Failed:** {msg}"));
+            }
+            if status.is_terminal() {
+                state.turn_finished = true;
+                finish_reply(state, http);
+            }
+        }
+
+        AgentEvent::Error(msg) => {
+            state.text_buffer.push_str(&format!("\n\n**Error:** {msg}"));
+            state.turn_finished = true;
+            finish_reply(state, http);
+        }
+
+        _ => {}
+    }
+}
+
+/// Tool calls shown as embeds on the reply (most recent ones if there are many).
+fn tool_embeds(tools: &[String]) -> Vec<CreateEmbed> {
+    let skip = tools.len().saturating_sub(MAX_EMBEDS);
+    tools[skip..]
+        .iter()
+        .map(|name| {
+            CreateEmbed::new()
+                .title(format!("🔧 Tool: {name}"))
+                .color(0x5865F2)
+        })
+        .collect()
+}
+
+/// While streaming, show the tail of the output so the latest text is visible.
+fn streaming_preview(text: &str) -> String {
+    let count = text.chars().count();
+    if count == 0 {
+        return "⏳ Working...".to_string();
+    }
+    if count <= MAX_MESSAGE_CHARS {
+        return text.to_string();
+    }
+    let tail: String = text.chars().skip(count - (MAX_MESSAGE_CHARS - 1)).collect();
+    format!("…{tail}")
+}
+
+/// Edit the reply with the current output. Skips silently if the reply
+/// hasn't been posted yet; `ReplyReady` flushes it later.
+async fn update_reply(state: &mut ThreadRenderState, http: &Arc<Http>) {
+    let Some(reply_id) = state.reply_msg_id else {
+        return;
+    };
+    let builder = EditMessage::new()
+        .content(streaming_preview(&state.text_buffer))
+        .embeds(tool_embeds(&state.tools));
+    match state.channel_id.edit_message(http, reply_id, builder).await {
+        Ok(_) => state.dirty = false,
+        Err(e) => warn!("Failed to edit Discord message: {e}"),
+    }
+}
+
+/// Write the final output: the first chunk goes into the reply, any
+/// overflow is posted as additional messages.
+/// Returns the task making the edits, if there is a reply to edit.
+fn finish_reply(state: &mut ThreadRenderState, http: &Arc<Http>) -> Option<JoinHandle<()>> {
+    let reply_id = state.reply_msg_id?;
+    let text = state.text_buffer.trim();
+    let text = if text.is_empty() { "(no output)" } else { text };
+    let mut chunks = split_message(text, MAX_MESSAGE_CHARS).into_iter();
+    let first = chunks.next().unwrap_or_default();
+    let rest: Vec<String> = chunks.collect();
+    let builder = EditMessage::new()
+        .content(first)
+        .embeds(tool_embeds(&state.tools));
+    state.dirty = false;
+
+    // The final edit must land, so wait out the rate limit instead of
+    // skipping it, in its own task so other conversations keep going
+    let delay = state.limiter.reserve();
+    let channel_id = state.channel_id;
+    let http = http.clone();
+    Some(tokio::spawn(async move {
+        tokio::time::sleep(delay).await;
+        if let Err(e) = channel_id.edit_message(&http, reply_id, builder).await {
+            warn!("Failed to finish Discord message: {e}");
+            return;
+        }
+        for chunk in rest {
+            if let Err(e) = channel_id.say(&http, chunk).await {
+                warn!("Failed to post Discord message: {e}");
+                return;
+            }
+        }
+    }))
+}
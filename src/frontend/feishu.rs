@@ -1,33 +1,162 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::Result;
-use tokio::sync::mpsc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, Semaphore};
 use tracing::{error, info, warn};
 
-use crate::config::FeishuConfig;
+use crate::config::{self, FeishuConfig};
 use crate::protocol::{AgentEvent, AgentStatus, ContentBlock, Submission, ThreadId};
 use crate::thread::AgentThread;
-use crate::thread_manager::ThreadManager;
-use crate::transport::feishu::FeishuTransport;
+use crate::thread_manager::{ThreadManager, ThreadOverrides};
+use crate::transport::feishu::{Attachment, FeishuTransport};
 
-use super::Frontend;
+use super::{chat_default_agent, command_usage, parse_command, spawn_drain_watcher, Frontend};
+
+/// Reaction added to a user's message when the agent starts on it.
+const ACK_EMOJI: &str = "THUMBSUP";
+
+/// Most attachment downloads of one message in flight at once.
+const MAX_CONCURRENT_DOWNLOADS: usize = 4;
+
+/// Reaction on an agent's card that cancels its thread.
+const CANCEL_EMOJI: &str = "THUMBSDOWN";
+
+/// Separates a message from the per-turn system prompt text that follows it.
+const SYSTEM_MARKER: &str = "---system---";
+
+/// Saved card routes unused for this long are dropped on the next save.
+const SAVED_ROUTE_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Most card routes kept in the state file; the least recently active go first.
+const MAX_SAVED_ROUTES: usize = 1000;
 
 /// Per-thread rendering state for Feishu cards.
 struct ThreadRenderState {
     thread_id: ThreadId,
+    agent_type: String,
     agent_name: String,
     conv_id: String,
+    /// Shown in card titles once looked up.
+    chat_name: Option<String>,
+    user_id: String,
+    /// The user's message, acknowledged with a reaction once work starts.
+    original_msg_id: Option<String>,
     card_msg_id: Option<String>,
     card_id: Option<String>,
     text_buffer: String,
+    /// "Executing..." elements of tools still running, as (tool name, element id).
+    tool_elements: Vec<(String, String)>,
+    /// Result lines of finished tools, shown in their elements on this card.
+    tool_results: Vec<String>,
+    next_tool_element: usize,
     streaming_closed: bool,
+    show_usage: bool,
+    /// Longer responses continue on a new card.
+    max_card_chars: usize,
+    /// 1 for a turn's first card, counting up for its continuation cards.
+    card_sequence: usize,
+    input_tokens: u64,
+    output_tokens: u64,
+    cost_usd: Option<f64>,
+    start_time: Instant,
+    /// Unix seconds of the last turn, saved so old routes expire.
+    last_active: i64,
 }
 
 impl ThreadRenderState {
+    fn new(
+        thread_id: ThreadId,
+        agent_type: &str,
+        agent_name: String,
+        conv_id: String,
+        user_id: String,
+        show_usage: bool,
+        max_card_chars: usize,
+    ) -> Self {
+        Self {
+            thread_id,
+            agent_type: agent_type.to_string(),
+            agent_name,
+            conv_id,
+            chat_name: None,
+            user_id,
+            original_msg_id: None,
+            card_msg_id: None,
+            card_id: None,
+            text_buffer: String::new(),
+            tool_elements: Vec::new(),
+            tool_results: Vec::new(),
+            next_tool_element: 0,
+            streaming_closed: false,
+            show_usage,
+            max_card_chars,
+            card_sequence: 1,
+            input_tokens: 0,
+            output_tokens: 0,
+            cost_usd: None,
+            start_time: Instant::now(),
+            last_active: chrono::Utc::now().timestamp(),
+        }
+    }
+
     fn title(&self) -> String {
-        format!("Task #{} · {}", self.thread_id.0, self.agent_name)
+        card_title(
+            &self.thread_id,
+            &self.agent_name,
+            self.chat_name.as_deref(),
+            self.card_sequence,
+        )
+    }
+
+    /// `text` followed by the finished tools' results, for a full card
+    /// update, which replaces the tool elements.
+    fn with_tool_results(&self, text: &str) -> String {
+        if self.tool_results.is_empty() {
+            return text.to_string();
+        }
+        format!("{text}\n\n{}", self.tool_results.join("\n"))
+    }
+
+    /// Footer for the finished card, e.g.
+    /// `Tokens: 1243 in / 847 out | Cost: $0.0042 | Duration: 12.3s`.
+    fn usage_footer(&self) -> Option<String> {
+        if !self.show_usage {
+            return None;
+        }
+        let mut parts = vec![format!(
+            "Tokens: {} in / {} out",
+            self.input_tokens, self.output_tokens
+        )];
+        if let Some(cost) = self.cost_usd {
+            parts.push(format!("Cost: ${cost:.4}"));
+        }
+        parts.push(format!(
+            "Duration: {:.1}s",
+            self.start_time.elapsed().as_secs_f64()
+        ));
+        Some(parts.join(" | "))
+    }
+}
+
+/// Card title, e.g. `ProjectX · Task #a1b2c3 · myagent (cont. 2)`.
+fn card_title(
+    thread_id: &ThreadId,
+    agent_name: &str,
+    chat_name: Option<&str>,
+    card_sequence: usize,
+) -> String {
+    let mut title = format!("Task #{} · {agent_name}", thread_id.0);
+    if let Some(chat_name) = chat_name {
+        title = format!("{chat_name} · {title}");
+    }
+    if card_sequence > 1 {
+        title.push_str(&format!(" (cont. {card_sequence})"));
     }
+    title
 }
 
 /// Internal events for the Feishu frontend's main loop.
@@ -35,12 +164,18 @@ enum FeishuInternalEvent {
     NewMessage {
         conv_id: String,
         user_id: String,
+        message_id: String,
         text: String,
     },
     ReplyMessage {
         card_msg_id: String,
         text: String,
     },
+    Reaction {
+        msg_id: String,
+        user_id: String,
+        emoji_type: String,
+    },
     CardReady {
         thread_id: ThreadId,
         msg_id: String,
@@ -50,6 +185,10 @@ enum FeishuInternalEvent {
         thread_id: ThreadId,
         event: AgentEvent,
     },
+    /// Shutdown drain finished; `remaining` threads did not exit in time.
+    Drained {
+        remaining: Vec<ThreadId>,
+    },
 }
 
 pub struct FeishuFrontend {
@@ -69,28 +208,59 @@ impl Frontend for FeishuFrontend {
         let (fe_tx, mut fe_rx) = mpsc::channel::<FeishuInternalEvent>(512);
 
         start_feishu_listener(transport.clone(), fe_tx.clone()).await?;
+        spawn_drain_watcher(manager.clone(), fe_tx.clone(), |remaining| {
+            FeishuInternalEvent::Drained { remaining }
+        });
         info!("Feishu frontend started");
 
         let mut render_states: HashMap<ThreadId, ThreadRenderState> = HashMap::new();
         let mut card_to_thread: HashMap<String, ThreadId> = HashMap::new();
+        // Cards from before a restart; their threads are resumed on the first reply
+        let mut saved_routes = if self.config.persist_threads {
+            load_state()
+        } else {
+            HashMap::new()
+        };
 
         while let Some(event) = fe_rx.recv().await {
             match event {
                 FeishuInternalEvent::NewMessage {
                     conv_id,
                     user_id,
+                    message_id,
                     text,
                 } => {
-                    let (agent_type, prompt) = if text.starts_with("/claude ") {
-                        ("claude", text.strip_prefix("/claude ").unwrap().to_string())
-                    } else {
-                        ("myagent", text)
+                    manager.record_activity();
+                    if manager.is_draining() {
+                        info!("Ignoring new message from {user_id}: shutting down");
+                        continue;
+                    }
+                    let default_agent = chat_default_agent(&manager).await;
+                    let Some((agent_type, prompt)) = parse_command(&text, &default_agent) else {
+                        let t = transport.clone();
+                        tokio::spawn(async move {
+                            let usage = command_usage();
+                            let sent = if message_id.is_empty() {
+                                t.send_text(&conv_id, &usage).await
+                            } else {
+                                t.reply_text(&message_id, &usage).await
+                            };
+                            if let Err(e) = sent {
+                                error!("Failed to send command usage: {e}");
+                            }
+                        });
+                        continue;
                     };
+                    let agent_type = agent_type.as_str();
+                    let (overrides, prompt) = parse_overrides(&prompt);
+                    let overrides = ThreadOverrides {
+                        conv_id: Some(conv_id.clone()),
+                        user_id: Some(user_id.clone()),
+                        ..overrides
+                    };
+                    let (prompt, system_suffix) = split_system_suffix(&prompt);
 
-                    // Prepend chat context so the agent knows the chat_id for file operations
-                    let prompt_with_ctx = format!("<feishu_context chat_id=\"{conv_id}\" />\n{prompt}");
-
-                    let (thread_id, thread) = match manager.create_thread(agent_type).await {
+                    let (thread_id, thread) = match manager.create_thread(agent_type, overrides).await {
                         Ok(v) => v,
                         Err(e) => {
                             error!("Failed to create thread: {e}");
@@ -100,31 +270,36 @@ impl Frontend for FeishuFrontend {
 
                     info!("[{thread_id}] New task: user={user_id}, agent={agent_type}");
 
-                    if let Err(e) = thread.submit(Submission::UserMessage(prompt_with_ctx)).await {
+                    if let Some(system_suffix) = system_suffix {
+                        let _ = thread.submit(Submission::Configure { system_suffix }).await;
+                    }
+                    if let Err(e) = thread.submit(Submission::UserMessage(prompt)).await {
                         error!("[{thread_id}] Failed to submit: {e}");
                         continue;
                     }
 
-                    let agent_name = thread.agent_name.clone();
-                    let title = format!("Task #{} · {agent_name}", thread_id.0);
-                    render_states.insert(
+                    let mut state = ThreadRenderState::new(
                         thread_id.clone(),
-                        ThreadRenderState {
-                            thread_id: thread_id.clone(),
-                            agent_name,
-                            conv_id: conv_id.clone(),
-                            card_msg_id: None,
-                            card_id: None,
-                            text_buffer: String::new(),
-                            streaming_closed: false,
-                        },
+                        agent_type,
+                        thread.agent_name.clone(),
+                        conv_id.clone(),
+                        user_id,
+                        self.config.show_usage_in_card,
+                        self.config.max_card_chars,
                     );
+                    state.original_msg_id = Some(message_id).filter(|id| !id.is_empty());
+                    state.chat_name = transport.cached_chat_name(&conv_id);
+                    let agent_name = state.agent_name.clone();
+                    render_states.insert(thread_id.clone(), state);
 
                     // Spawn card creation
                     let t = transport.clone();
                     let ftx = fe_tx.clone();
                     let tid = thread_id.clone();
                     tokio::spawn(async move {
+                        // Looked up once per chat; CardReady copies it into the state
+                        let chat_name = t.chat_name(&conv_id).await;
+                        let title = card_title(&tid, &agent_name, chat_name.as_deref(), 1);
                         match t.send_streaming_card(&conv_id, &title).await {
                             Ok((msg_id, card_id)) => {
                                 let _ = ftx
@@ -144,10 +319,40 @@ impl Frontend for FeishuFrontend {
                 }
 
                 FeishuInternalEvent::ReplyMessage { card_msg_id, text } => {
-                    if let Some(tid) = card_to_thread.get(&card_msg_id).cloned() {
+                    manager.record_activity();
+                    if manager.is_draining() {
+                        info!("Ignoring reply to {card_msg_id}: shutting down");
+                        continue;
+                    }
+                    let tid = match card_to_thread.get(&card_msg_id).cloned() {
+                        Some(tid) => Some(tid),
+                        None => {
+                            let resumed = resume_saved_thread(
+                                &manager,
+                                &fe_tx,
+                                &mut saved_routes,
+                                &card_msg_id,
+                                &self.config,
+                            )
+                            .await;
+                            resumed.map(|(state, cards)| {
+                                let tid = state.thread_id.clone();
+                                for card in cards {
+                                    card_to_thread.insert(card, tid.clone());
+                                }
+                                render_states.insert(tid.clone(), state);
+                                tid
+                            })
+                        }
+                    };
+                    if let Some(tid) = tid {
                         // Reply to an existing agent card → follow-up
                         if let Some(thread) = manager.get_thread(&tid).await {
                             info!("[{tid}] Routing reply");
+                            let (text, system_suffix) = split_system_suffix(&text);
+                            if let Some(system_suffix) = system_suffix {
+                                let _ = thread.submit(Submission::Configure { system_suffix }).await;
+                            }
                             let _ = thread.submit(Submission::FollowUp(text)).await;
                         }
                     } else {
@@ -165,6 +370,23 @@ impl Frontend for FeishuFrontend {
                     }
                 }
 
+                FeishuInternalEvent::Reaction {
+                    msg_id,
+                    user_id,
+                    emoji_type,
+                } => {
+                    if emoji_type != CANCEL_EMOJI {
+                        continue;
+                    }
+                    let Some(tid) = card_to_thread.get(&msg_id) else {
+                        continue;
+                    };
+                    if let Some(thread) = manager.get_thread(tid).await.filter(|t| !t.is_finished()) {
+                        info!("[{tid}] Cancelled by {user_id} via {emoji_type} reaction");
+                        let _ = thread.submit(Submission::Cancel).await;
+                    }
+                }
+
                 FeishuInternalEvent::CardReady {
                     thread_id,
                     msg_id,
@@ -173,12 +395,14 @@ impl Frontend for FeishuFrontend {
                     if let Some(state) = render_states.get_mut(&thread_id) {
                         state.card_msg_id = Some(msg_id.clone());
                         state.card_id = Some(card_id.clone());
+                        if state.chat_name.is_none() {
+                            state.chat_name = transport.cached_chat_name(&state.conv_id);
+                        }
                         card_to_thread.insert(msg_id, thread_id.clone());
                         // Flush any text buffered before the card was ready
                         if !state.text_buffer.is_empty() {
-                            let title = state.title();
                             if let Err(e) = transport
-                                .update_card_content(&card_id, &title, &state.text_buffer)
+                                .stream_card_content(&card_id, &state.text_buffer)
                                 .await
                             {
                                 warn!("Failed to flush buffered text to card: {e}");
@@ -196,6 +420,42 @@ impl Frontend for FeishuFrontend {
                         event,
                     )
                     .await;
+                    // A long response may have continued on a new card
+                    if let Some(msg_id) = render_states
+                        .get(&thread_id)
+                        .and_then(|state| state.card_msg_id.clone())
+                    {
+                        card_to_thread.entry(msg_id).or_insert(thread_id);
+                    }
+                }
+
+                FeishuInternalEvent::Drained { remaining } => {
+                    for tid in &remaining {
+                        if let Some(state) = render_states.get_mut(tid) {
+                            if state.streaming_closed {
+                                continue;
+                            }
+                            warn!("[{tid}] Still running at shutdown");
+                            state.text_buffer.push_str("\n\n⚠️ Agent shutting down.");
+                            finish_card(state, &transport, "cancelled").await;
+                        }
+                    }
+                    if let Some(notice) = manager.shutdown_notice() {
+                        let mut chats: Vec<&str> =
+                            render_states.values().map(|s| s.conv_id.as_str()).collect();
+                        chats.sort_unstable();
+                        chats.dedup();
+                        for chat in chats {
+                            if let Err(e) = transport.send_text(chat, &notice).await {
+                                warn!("Failed to send shutdown notice to {chat}: {e}");
+                            }
+                        }
+                    }
+                    if self.config.persist_threads {
+                        save_state(&render_states, &card_to_thread, saved_routes);
+                    }
+                    info!("Feishu frontend drained");
+                    break;
                 }
             }
         }
@@ -204,6 +464,137 @@ impl Frontend for FeishuFrontend {
     }
 }
 
+/// Routing state saved across restarts. Conversation history is not kept:
+/// a thread is resumed with an empty history on the first reply to one of
+/// its cards, and replies reach it as follow-ups.
+#[derive(Serialize, Deserialize, Default)]
+struct PersistedState {
+    /// Card message_id → the thread that sent it.
+    cards: HashMap<String, PersistedThread>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct PersistedThread {
+    thread_id: String,
+    agent_type: String,
+    conv_id: String,
+    user_id: String,
+    /// Unix seconds of the thread's last turn.
+    last_active: i64,
+}
+
+/// Write the route of every card to the state file, keeping saved routes
+/// that were not used this run. Routes older than `SAVED_ROUTE_TTL_SECS`
+/// are dropped and at most `MAX_SAVED_ROUTES` are kept.
+fn save_state(
+    render_states: &HashMap<ThreadId, ThreadRenderState>,
+    card_to_thread: &HashMap<String, ThreadId>,
+    mut cards: HashMap<String, PersistedThread>,
+) {
+    for (card, tid) in card_to_thread {
+        if let Some(s) = render_states.get(tid) {
+            let route = PersistedThread {
+                thread_id: s.thread_id.0.clone(),
+                agent_type: s.agent_type.clone(),
+                conv_id: s.conv_id.clone(),
+                user_id: s.user_id.clone(),
+                last_active: s.last_active,
+            };
+            cards.insert(card.clone(), route);
+        }
+    }
+    let cutoff = chrono::Utc::now().timestamp() - SAVED_ROUTE_TTL_SECS;
+    cards.retain(|_, t| t.last_active >= cutoff);
+    if cards.len() > MAX_SAVED_ROUTES {
+        let mut routes: Vec<_> = cards.into_iter().collect();
+        routes.sort_by_key(|(_, t)| std::cmp::Reverse(t.last_active));
+        routes.truncate(MAX_SAVED_ROUTES);
+        cards = routes.into_iter().collect();
+    }
+
+    let state = PersistedState { cards };
+    let path = config::feishu_state_path();
+    let result = serde_json::to_string_pretty(&state)
+        .map_err(anyhow::Error::from)
+        .and_then(|json| std::fs::write(&path, json).map_err(Into::into));
+    match result {
+        Ok(()) => info!("Saved {} Feishu card route(s) to {}", state.cards.len(), path.display()),
+        Err(e) => warn!("Failed to save Feishu state to {}: {e}", path.display()),
+    }
+}
+
+/// The card routes saved by `save_state`. No threads are started here.
+fn load_state() -> HashMap<String, PersistedThread> {
+    let path = config::feishu_state_path();
+    let Ok(content) = std::fs::read_to_string(&path) else {
+        return HashMap::new();
+    };
+    match serde_json::from_str::<PersistedState>(&content) {
+        Ok(saved) => {
+            info!("Loaded {} Feishu card route(s) from {}", saved.cards.len(), path.display());
+            saved.cards
+        }
+        Err(e) => {
+            warn!("Ignoring unreadable Feishu state {}: {e}", path.display());
+            HashMap::new()
+        }
+    }
+}
+
+/// Re-create the thread behind a saved card so a reply to it is routed as
+/// a follow-up instead of "session expired". Takes every saved card of that
+/// thread out of `saved_routes` and returns them with the thread's state.
+async fn resume_saved_thread(
+    manager: &Arc<ThreadManager>,
+    fe_tx: &mpsc::Sender<FeishuInternalEvent>,
+    saved_routes: &mut HashMap<String, PersistedThread>,
+    card_msg_id: &str,
+    config: &FeishuConfig,
+) -> Option<(ThreadRenderState, Vec<String>)> {
+    let saved = saved_routes.get(card_msg_id)?.clone();
+    let cards: Vec<String> = saved_routes
+        .iter()
+        .filter(|(_, t)| t.thread_id == saved.thread_id)
+        .map(|(card, _)| card.clone())
+        .collect();
+    for card in &cards {
+        saved_routes.remove(card);
+    }
+
+    let thread_id = ThreadId(saved.thread_id);
+    let thread = match manager
+        .resume_thread(
+            thread_id.clone(),
+            &saved.agent_type,
+            ThreadOverrides {
+                conv_id: Some(saved.conv_id.clone()),
+                user_id: Some(saved.user_id.clone()),
+                ..Default::default()
+            },
+        )
+        .await
+    {
+        Ok(thread) => thread,
+        Err(e) => {
+            warn!("[{thread_id}] Failed to resume thread: {e}");
+            return None;
+        }
+    };
+    let mut state = ThreadRenderState::new(
+        thread_id,
+        &saved.agent_type,
+        thread.agent_name.clone(),
+        saved.conv_id,
+        saved.user_id,
+        config.show_usage_in_card,
+        config.max_card_chars,
+    );
+    // Like a finished turn: the next Working status opens a new card
+    state.streaming_closed = true;
+    spawn_event_poller(thread, fe_tx.clone());
+    Some((state, cards))
+}
+
 /// Bridge Feishu transport events into FeishuInternalEvents.
 async fn start_feishu_listener(
     transport: Arc<FeishuTransport>,
@@ -219,33 +610,55 @@ async fn start_feishu_listener(
                 crate::transport::feishu::TransportEvent::NewMessage {
                     conv_id,
                     user_id,
+                    message_id,
                     text,
                 } => FeishuInternalEvent::NewMessage {
                     conv_id,
                     user_id,
+                    message_id,
                     text,
                 },
                 crate::transport::feishu::TransportEvent::ReplyMessage {
                     card_msg_id,
                     text,
+                    ..
                 } => FeishuInternalEvent::ReplyMessage { card_msg_id, text },
                 crate::transport::feishu::TransportEvent::FileMessage {
-                    conv_id: _,
-                    user_id: _,
-                    message_id: _,
-                    file_key: _,
-                    file_name,
+                    conv_id,
+                    user_id,
+                    message_id,
+                    files,
+                    text,
                     parent_id,
                 } => {
-                    if let Some(card_msg_id) = parent_id {
-                        // File reply to an existing card → route as follow-up
-                        let text = format!("User sent a file: {file_name}");
-                        FeishuInternalEvent::ReplyMessage { card_msg_id, text }
-                    } else {
-                        // Direct file message → ignore (agent can find via `feishu files`)
-                        info!("Ignoring direct file message: {file_name}");
-                        continue;
-                    }
+                    // Download off the bridge loop so other messages aren't held up
+                    let t = transport.clone();
+                    let ftx = fe_tx.clone();
+                    tokio::spawn(async move {
+                        let (saved, failed) = download_attachments(&t, &message_id, files).await;
+                        let text = attachment_message(&saved, &failed, &text);
+                        let fe_event = match parent_id {
+                            // File reply to an existing card → route as follow-up
+                            Some(card_msg_id) => FeishuInternalEvent::ReplyMessage { card_msg_id, text },
+                            None => FeishuInternalEvent::NewMessage {
+                                conv_id,
+                                user_id,
+                                message_id,
+                                text,
+                            },
+                        };
+                        let _ = ftx.send(fe_event).await;
+                    });
+                    continue;
+                },
+                crate::transport::feishu::TransportEvent::Reaction {
+                    msg_id,
+                    user_id,
+                    emoji_type,
+                } => FeishuInternalEvent::Reaction {
+                    msg_id,
+                    user_id,
+                    emoji_type,
                 },
             };
             let _ = fe_tx.send(fe_event).await;
@@ -255,6 +668,108 @@ async fn start_feishu_listener(
     Ok(())
 }
 
+/// Download a message's attachments into `~/.myagent/downloads/<message_id>/`,
+/// a few at a time. Returns the saved paths and the names that failed; a
+/// failed download doesn't stop the others.
+async fn download_attachments(
+    transport: &Arc<FeishuTransport>,
+    message_id: &str,
+    files: Vec<Attachment>,
+) -> (Vec<PathBuf>, Vec<String>) {
+    let dir = config::feishu_downloads_dir().join(sanitize_file_name(message_id));
+    if let Err(e) = tokio::fs::create_dir_all(&dir).await {
+        warn!("Failed to create {}: {e}", dir.display());
+        return (Vec::new(), files.into_iter().map(|f| f.file_name).collect());
+    }
+
+    let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_DOWNLOADS));
+    let mut used = HashSet::new();
+    let mut handles = Vec::new();
+    for (i, file) in files.into_iter().enumerate() {
+        let mut name = sanitize_file_name(&file.file_name);
+        if !used.insert(name.clone()) {
+            name = format!("{i}-{name}");
+        }
+        let path = dir.join(name);
+        let transport = transport.clone();
+        let permits = permits.clone();
+        let message_id = message_id.to_string();
+        handles.push(tokio::spawn(async move {
+            let result = async {
+                let _permit = permits.acquire_owned().await?;
+                let bytes = transport.download_attachment(&message_id, &file).await?;
+                let path = match image_extension(&bytes).filter(|_| file.resource_type == "image") {
+                    Some(ext) => path.with_extension(ext),
+                    None => path,
+                };
+                tokio::fs::write(&path, &bytes).await?;
+                anyhow::Ok(path)
+            }
+            .await;
+            (file.file_name, result)
+        }));
+    }
+
+    let mut saved = Vec::new();
+    let mut failed = Vec::new();
+    for joined in futures_util::future::join_all(handles).await {
+        match joined {
+            Ok((_, Ok(path))) => {
+                info!("Downloaded attachment to {}", path.display());
+                saved.push(path);
+            }
+            Ok((name, Err(e))) => {
+                warn!("Failed to download {name} from message {message_id}: {e}");
+                failed.push(name);
+            }
+            Err(e) => warn!("Download task failed: {e}"),
+        }
+    }
+    (saved, failed)
+}
+
+/// The user's text with the downloaded attachments listed before it.
+fn attachment_message(saved: &[PathBuf], failed: &[String], text: &str) -> String {
+    let mut message = String::new();
+    if !saved.is_empty() {
+        message.push_str("User sent files, saved to:\n");
+        for path in saved {
+            message.push_str(&format!("- {}\n", path.display()));
+        }
+    }
+    if !failed.is_empty() {
+        message.push_str(&format!("Could not download: {}\n", failed.join(", ")));
+    }
+    if !text.is_empty() {
+        message.push('\n');
+        message.push_str(text);
+    }
+    message.trim_end().to_string()
+}
+
+/// Keep only the final component of a user-supplied name.
+fn sanitize_file_name(name: &str) -> String {
+    Path::new(name)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "attachment".to_string())
+}
+
+/// Extension for common image formats, from their magic bytes.
+fn image_extension(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG") {
+        Some("png")
+    } else if bytes.starts_with(b"\xFF\xD8") {
+        Some("jpg")
+    } else if bytes.starts_with(b"GIF8") {
+        Some("gif")
+    } else if bytes.len() > 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("webp")
+    } else {
+        None
+    }
+}
+
 /// Spawn a task that polls AgentEvents from a thread's EQ.
 /// Does NOT exit on terminal status — the poller stays alive so follow-up
 /// replies can reuse the same thread. It only exits when the EQ channel
@@ -303,6 +818,73 @@ async fn handle_agent_event(
             update_card(state, transport).await;
         }
 
+        // Tool began executing — add a placeholder element for it
+        AgentEvent::ToolStart { name, .. } => {
+            let Some(card_id) = state.card_id.as_ref().filter(|_| !state.streaming_closed) else {
+                return;
+            };
+            let element_id = format!("tool_{}", state.next_tool_element);
+            state.next_tool_element += 1;
+            let placeholder = format!("⏳ Executing {name}...");
+            match transport
+                .append_card_markdown(card_id, &element_id, &placeholder)
+                .await
+            {
+                Ok(()) => state.tool_elements.push((name, element_id)),
+                Err(e) => warn!("Failed to add tool element: {e}"),
+            }
+        }
+
+        // Tool finished executing — replace its placeholder with the result
+        AgentEvent::ToolEnd {
+            name,
+            duration_ms,
+            is_error,
+        } => {
+            let secs = duration_ms as f64 / 1000.0;
+            let line = if is_error {
+                format!("❌ {name} failed after {secs:.1}s")
+            } else {
+                format!("✅ {name} done in {secs:.1}s")
+            };
+            let pending = state.tool_elements.iter().position(|(tool, _)| *tool == name);
+            let element_id = pending.map(|i| state.tool_elements.remove(i).1);
+            let card_id = state.card_id.as_ref().filter(|_| !state.streaming_closed);
+            if let (Some(element_id), Some(card_id)) = (element_id, card_id) {
+                match transport.update_card_markdown(card_id, &element_id, &line).await {
+                    Ok(()) => {
+                        state.tool_results.push(line);
+                        return;
+                    }
+                    Err(e) => {
+                        warn!("Failed to update tool element {element_id}: {e}");
+                        // Drop the stale placeholder; the result goes in the main content
+                        if let Err(e) = transport.delete_card_element(card_id, &element_id).await {
+                            warn!("Failed to delete tool element {element_id}: {e}");
+                        }
+                    }
+                }
+            }
+            state.text_buffer.push_str(&format!("\n{line}\n"));
+            update_card(state, transport).await;
+        }
+
+        // Tool output — add a one-line preview; the card is updated when
+        // the result block starts
+        AgentEvent::ToolResult {
+            tool_name,
+            output_preview,
+            line_count,
+            is_error,
+        } => {
+            let mark = if is_error { "✗" } else { "✓" };
+            let first_line = output_preview.lines().next().unwrap_or("").trim();
+            let lines = if line_count == 1 { "line" } else { "lines" };
+            state.text_buffer.push_str(&format!(
+                "{mark} {tool_name}: {first_line} ({line_count} {lines})\n"
+            ));
+        }
+
         // Tool result — update card
         AgentEvent::ContentBlockStart {
             content_block: ContentBlock::ToolResult { .. },
@@ -319,18 +901,44 @@ async fn handle_agent_event(
         // Status change
         AgentEvent::StatusChange(ref status) => {
             info!("[{thread_id}] Status: {status:?}");
+            let working = *status == AgentStatus::Working;
+            if let Some(msg_id) = state.original_msg_id.take_if(|_| working) {
+                let t = transport.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = t.add_reaction(&msg_id, ACK_EMOJI).await {
+                        warn!("Failed to react to {msg_id}: {e}");
+                    }
+                });
+            }
+            if *status == AgentStatus::Idle {
+                // The agent reset its context: close this card so the
+                // Working status that follows opens a fresh one
+                finish_card(state, transport, "reset").await;
+            }
             if *status == AgentStatus::Working && state.streaming_closed {
                 // Follow-up message: reset state and create new card
                 state.text_buffer.clear();
+                state.tool_elements.clear();
+                state.tool_results.clear();
                 state.streaming_closed = false;
+                state.card_sequence = 1;
+                state.input_tokens = 0;
+                state.output_tokens = 0;
+                state.cost_usd = None;
+                state.start_time = Instant::now();
+                state.last_active = chrono::Utc::now().timestamp();
                 state.card_id = None;
                 state.card_msg_id = None;
                 let t = transport.clone();
                 let ftx = fe_tx.clone();
                 let tid = thread_id.clone();
                 let conv_id = state.conv_id.clone();
-                let title = state.title();
+                let agent_name = state.agent_name.clone();
                 tokio::spawn(async move {
+                    // Resumed threads haven't looked up their chat yet; CardReady
+                    // copies the cached name into the state
+                    let chat_name = t.chat_name(&conv_id).await;
+                    let title = card_title(&tid, &agent_name, chat_name.as_deref(), 1);
                     match t.send_streaming_card(&conv_id, &title).await {
                         Ok((msg_id, card_id)) => {
                             let _ = ftx
@@ -356,6 +964,31 @@ async fn handle_agent_event(
             }
         }
 
+        // Summed over the turn for the finished card's footer
+        AgentEvent::Usage {
+            input_tokens,
+            output_tokens,
+            cost_usd,
+        } => {
+            state.input_tokens += input_tokens;
+            state.output_tokens += output_tokens;
+            if let Some(cost) = cost_usd {
+                *state.cost_usd.get_or_insert(0.0) += cost;
+            }
+        }
+
+        // Shown until the next card update replaces the content
+        AgentEvent::RateLimited { retry_after_ms } => {
+            let secs = retry_after_ms.div_ceil(1000);
+            warn!("[{thread_id}] Rate limited, retrying in {secs}s");
+            if let Some(card_id) = state.card_id.as_ref().filter(|_| !state.streaming_closed) {
+                let text = format!("{}\n\n⏳ Rate limited, retrying in {secs}s...", state.text_buffer);
+                if let Err(e) = transport.stream_card_content(card_id, &text).await {
+                    warn!("Failed to update card: {e}");
+                }
+            }
+        }
+
         AgentEvent::Error(ref msg) => {
             state
                 .text_buffer
@@ -367,36 +1000,120 @@ async fn handle_agent_event(
     }
 }
 
-/// Update card content (sequential, no spawn).
-async fn update_card(state: &ThreadRenderState, transport: &Arc<FeishuTransport>) {
+/// Stream the text buffer into the card's content element (sequential, no
+/// spawn). Tool elements are left as they are.
+async fn update_card(state: &mut ThreadRenderState, transport: &Arc<FeishuTransport>) {
+    split_long_card(state, transport).await;
     let Some(card_id) = state.card_id.as_ref() else {
         return;
     };
     if state.streaming_closed {
         return;
     }
-    let title = state.title();
     if let Err(e) = transport
-        .update_card_content(card_id, &title, &state.text_buffer)
+        .stream_card_content(card_id, &state.text_buffer)
         .await
     {
         warn!("Failed to update card: {e}");
     }
 }
 
+/// Strip leading `--model <name>` / `--max-tokens <n>` flags from a message.
+fn parse_overrides(text: &str) -> (ThreadOverrides, String) {
+    let mut overrides = ThreadOverrides::default();
+    let mut rest = text.trim_start();
+    while let Some((flag, after)) = rest.split_once(char::is_whitespace) {
+        let after = after.trim_start();
+        let (value, after) = after.split_once(char::is_whitespace).unwrap_or((after, ""));
+        match flag {
+            "--model" if !value.is_empty() => overrides.model = Some(value.to_string()),
+            "--max-tokens" => match value.parse() {
+                Ok(n) => overrides.max_tokens = Some(n),
+                Err(_) => break,
+            },
+            _ => break,
+        }
+        rest = after.trim_start();
+    }
+    (overrides, rest.to_string())
+}
+
+/// Split `write code ---system--- respond only in Python` into the message
+/// and a system prompt suffix for that turn.
+fn split_system_suffix(text: &str) -> (String, Option<String>) {
+    match text.split_once(SYSTEM_MARKER) {
+        Some((message, suffix)) if !suffix.trim().is_empty() => {
+            (message.trim_end().to_string(), Some(suffix.trim().to_string()))
+        }
+        Some((message, _)) => (message.trim_end().to_string(), None),
+        None => (text.to_string(), None),
+    }
+}
+
+/// While the text is longer than `max_card_chars`, finish the current card
+/// at the last paragraph break that fits and continue on a new card.
+/// A limit of 0 never splits.
+async fn split_long_card(state: &mut ThreadRenderState, transport: &Arc<FeishuTransport>) {
+    while !state.streaming_closed
+        && state.max_card_chars > 0
+        && state.text_buffer.chars().count() > state.max_card_chars
+    {
+        let Some(card_id) = state.card_id.clone() else {
+            return;
+        };
+        let limit = state
+            .text_buffer
+            .char_indices()
+            .nth(state.max_card_chars)
+            .map_or(state.text_buffer.len(), |(i, _)| i);
+        let head = &state.text_buffer[..limit];
+        let split = head
+            .rfind("\n\n")
+            .or_else(|| head.rfind('\n'))
+            .filter(|&i| i > 0)
+            .unwrap_or(limit);
+
+        let title = state.title();
+        let part = state.with_tool_results(&state.text_buffer[..split]);
+        if let Err(e) = transport.finish_card(&card_id, &title, "continued", &part, None).await {
+            warn!("Failed to finish card: {e}");
+        }
+        state.text_buffer = state.text_buffer[split..].trim_start_matches('\n').to_string();
+        state.tool_elements.clear();
+        state.tool_results.clear();
+        state.card_sequence += 1;
+        state.card_id = None;
+        state.card_msg_id = None;
+
+        match transport.send_streaming_card(&state.conv_id, &state.title()).await {
+            Ok((msg_id, card_id)) => {
+                state.card_msg_id = Some(msg_id);
+                state.card_id = Some(card_id);
+            }
+            Err(e) => {
+                error!("[{}] Failed to send continuation card: {e}", state.thread_id);
+                return;
+            }
+        }
+    }
+}
+
 /// Finish card (sequential, no spawn).
 async fn finish_card(
     state: &mut ThreadRenderState,
     transport: &Arc<FeishuTransport>,
     status: &str,
 ) {
+    split_long_card(state, transport).await;
     let Some(card_id) = state.card_id.as_ref() else {
         return;
     };
     state.streaming_closed = true;
     let title = state.title();
+    let footer = state.usage_footer();
+    let content = state.with_tool_results(&state.text_buffer);
     if let Err(e) = transport
-        .finish_card(card_id, &title, status, &state.text_buffer)
+        .finish_card(card_id, &title, status, &content, footer.as_deref())
         .await
     {
         warn!("Failed to finish card: {e}");
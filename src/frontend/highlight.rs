@@ -0,0 +1,68 @@
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::{as_24_bit_terminal_escaped, LinesWithEndings};
+
+/// Theme that reads well on dark terminal backgrounds.
+const THEME_NAME: &str = "base16-ocean.dark";
+const ANSI_RESET: &str = "\x1b[0m";
+
+/// Highlights fenced markdown code blocks for terminal output.
+/// Text outside of code fences passes through unchanged.
+pub struct CodeHighlighter {
+    syntaxes: SyntaxSet,
+    theme: Theme,
+}
+
+impl CodeHighlighter {
+    pub fn new() -> Self {
+        let syntaxes = SyntaxSet::load_defaults_newlines();
+        let mut themes = ThemeSet::load_defaults();
+        let theme = themes
+            .themes
+            .remove(THEME_NAME)
+            .unwrap_or_default();
+        Self { syntaxes, theme }
+    }
+
+    /// Render markdown text, highlighting the contents of ``` fences.
+    pub fn render(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut code: Option<HighlightLines> = None;
+
+        for line in LinesWithEndings::from(text) {
+            let is_fence = line.trim_start().starts_with("```");
+            match (&mut code, is_fence) {
+                (None, true) => {
+                    let lang = line.trim_start().trim_start_matches('`').trim();
+                    let syntax = self
+                        .syntaxes
+                        .find_syntax_by_token(lang)
+                        .unwrap_or_else(|| self.syntaxes.find_syntax_plain_text());
+                    code = Some(HighlightLines::new(syntax, &self.theme));
+                    out.push_str(line);
+                }
+                (Some(_), true) => {
+                    code = None;
+                    out.push_str(line);
+                }
+                (Some(h), false) => match h.highlight_line(line, &self.syntaxes) {
+                    Ok(ranges) => {
+                        let escaped = as_24_bit_terminal_escaped(&ranges, false);
+                        // Reset colors before the newline so they don't bleed
+                        let (body, newline) = match escaped.strip_suffix('\n') {
+                            Some(body) => (body, "\n"),
+                            None => (escaped.as_str(), ""),
+                        };
+                        out.push_str(body);
+                        out.push_str(ANSI_RESET);
+                        out.push_str(newline);
+                    }
+                    Err(_) => out.push_str(line),
+                },
+                (None, false) => out.push_str(line),
+            }
+        }
+        out
+    }
+}
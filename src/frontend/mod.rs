@@ -1,13 +1,106 @@
 pub mod cli;
+pub mod discord;
 pub mod feishu;
+pub mod recording;
+pub mod slack;
+pub mod telegram;
+pub mod webhook;
+mod cost;
+mod highlight;
 
 use anyhow::Result;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
 
+use crate::protocol::ThreadId;
 use crate::thread_manager::ThreadManager;
 
+/// Slash commands that start a thread on a specific agent type.
+const AGENT_COMMANDS: [(&str, &str); 2] = [("/claude", "claude"), ("/myagent", "myagent")];
+
 /// A frontend bridges between a user-facing I/O system and the ThreadManager.
 #[async_trait::async_trait]
 pub trait Frontend: Send + 'static {
     async fn run(self: Box<Self>, manager: Arc<ThreadManager>) -> Result<()>;
 }
+
+/// Split text into chunks of at most `max_chars`, preferring line breaks.
+pub(crate) fn split_message(text: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for line in text.split_inclusive('\n') {
+        if current.chars().count() + line.chars().count() > max_chars && !current.is_empty() {
+            chunks.push(std::mem::take(&mut current));
+        }
+        // A single line longer than the limit is hard-split
+        let mut rest = line;
+        while rest.chars().count() > max_chars {
+            let split_at = rest
+                .char_indices()
+                .nth(max_chars)
+                .map(|(i, _)| i)
+                .unwrap_or(rest.len());
+            chunks.push(rest[..split_at].to_string());
+            rest = &rest[split_at..];
+        }
+        current.push_str(rest);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// The agent messages without a command go to. A fallback list such as
+/// `myagent,claude` only applies to the CLI, so chat frontends take the first.
+pub(crate) async fn chat_default_agent(manager: &ThreadManager) -> String {
+    let default_agent = manager.config().await.default_agent;
+    default_agent.split(',').next().unwrap_or("myagent").trim().to_string()
+}
+
+/// Split a message into the agent to run it and the prompt. A leading
+/// `/command` picks the agent from `AGENT_COMMANDS`; without one the message
+/// goes to `default_agent`. Returns None for an unknown command or a command
+/// with no prompt.
+pub(crate) fn parse_command(text: &str, default_agent: &str) -> Option<(String, String)> {
+    let text = text.trim_start();
+    if !text.starts_with('/') {
+        return Some((default_agent.to_string(), text.to_string()));
+    }
+    let (command, prompt) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let (_, agent_type) = AGENT_COMMANDS.iter().find(|(name, _)| *name == command)?;
+    let prompt = prompt.trim_start();
+    if prompt.is_empty() {
+        return None;
+    }
+    Some((agent_type.to_string(), prompt.to_string()))
+}
+
+/// Reply to a message `parse_command` couldn't route.
+pub(crate) fn command_usage() -> String {
+    let commands: Vec<String> = AGENT_COMMANDS
+        .iter()
+        .map(|(name, agent)| format!("{name} <prompt> — run on the {agent} agent"))
+        .collect();
+    format!(
+        "Usage:\n{}\nMessages without a command go to the default agent.",
+        commands.join("\n")
+    )
+}
+
+/// Once the manager starts draining, wait for threads to finish and send the
+/// ones that outlived the shutdown timeout back to the main loop, wrapped by
+/// `drained`.
+pub(crate) fn spawn_drain_watcher<E: Send + 'static>(
+    manager: Arc<ThreadManager>,
+    fe_tx: mpsc::Sender<E>,
+    drained: fn(Vec<ThreadId>) -> E,
+) {
+    tokio::spawn(async move {
+        manager.wait_draining().await;
+        let timeout = Duration::from_secs(manager.config().await.shutdown_timeout_secs);
+        let remaining = manager.drain(timeout).await;
+        let _ = fe_tx.send(drained(remaining)).await;
+    });
+}
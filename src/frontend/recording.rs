@@ -0,0 +1,98 @@
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::protocol::{AgentEvent, ThreadId};
+use crate::thread_manager::ThreadManager;
+
+use super::Frontend;
+
+/// One line of a session recording.
+#[derive(Serialize, Deserialize)]
+pub struct RecordedEvent {
+    pub timestamp: String,
+    pub thread_id: String,
+    pub agent: String,
+    pub event: AgentEvent,
+}
+
+/// Appends every event read from an agent thread to a JSONL file.
+pub struct EventRecorder {
+    file: Mutex<std::fs::File>,
+}
+
+impl EventRecorder {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| anyhow::anyhow!("Failed to open recording {}: {e}", path.display()))?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn record(&self, thread_id: &ThreadId, agent: &str, event: &AgentEvent) {
+        let entry = RecordedEvent {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            thread_id: thread_id.0.clone(),
+            agent: agent.to_string(),
+            event: event.clone(),
+        };
+        let line = match serde_json::to_string(&entry) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("[{thread_id}] Failed to serialize event: {e}");
+                return;
+            }
+        };
+        if let Err(e) = writeln!(self.file.lock().unwrap(), "{line}") {
+            warn!("[{thread_id}] Failed to record event: {e}");
+        }
+    }
+}
+
+/// Wraps another frontend and records the events of every thread it runs.
+pub struct RecordingFrontend {
+    inner: Box<dyn Frontend>,
+    recorder: Arc<EventRecorder>,
+}
+
+impl RecordingFrontend {
+    pub fn new(inner: Box<dyn Frontend>, recorder: Arc<EventRecorder>) -> Self {
+        Self { inner, recorder }
+    }
+}
+
+#[async_trait::async_trait]
+impl Frontend for RecordingFrontend {
+    async fn run(self: Box<Self>, manager: Arc<ThreadManager>) -> Result<()> {
+        manager.set_recorder(self.recorder);
+        self.inner.run(manager).await
+    }
+}
+
+/// Read the events of a recording, keeping only threads run by `agent_type`
+/// ("all" keeps every thread).
+pub fn load_recording(path: &Path, agent_type: &str) -> Result<Vec<AgentEvent>> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| anyhow::anyhow!("Failed to open recording {}: {e}", path.display()))?;
+    let mut events = Vec::new();
+    for (n, line) in std::io::BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: RecordedEvent = serde_json::from_str(&line)
+            .map_err(|e| anyhow::anyhow!("{}:{}: {e}", path.display(), n + 1))?;
+        if agent_type == "all" || entry.agent.eq_ignore_ascii_case(agent_type) {
+            events.push(entry.event);
+        }
+    }
+    Ok(events)
+}
@@ -0,0 +1,431 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+use crate::config::SlackConfig;
+use crate::protocol::{AgentEvent, AgentStatus, ContentBlock, Submission, ThreadId};
+use crate::thread::AgentThread;
+use crate::thread_manager::{ThreadManager, ThreadOverrides};
+
+use super::{
+    chat_default_agent, command_usage, parse_command, spawn_drain_watcher, split_message, Frontend,
+};
+
+const SLACK_API_BASE: &str = "https://slack.com/api";
+/// Requests older than this are rejected to prevent replay attacks.
+const MAX_REQUEST_AGE_SECS: u64 = 5 * 60;
+/// Slack truncates `text` beyond ~40k chars and renders best under 4k.
+const MAX_MESSAGE_CHARS: usize = 3900;
+
+/// Per-thread state: where to reply and what the agent has said so far.
+struct ThreadRenderState {
+    channel: String,
+    thread_ts: String,
+    text_buffer: String,
+}
+
+/// Internal events for the Slack frontend's main loop.
+enum SlackInternalEvent {
+    Message {
+        channel: String,
+        user: String,
+        thread_ts: String,
+        text: String,
+    },
+    AgentOutput {
+        thread_id: ThreadId,
+        event: AgentEvent,
+    },
+    /// Shutdown drain finished; `remaining` threads did not exit in time.
+    Drained {
+        remaining: Vec<ThreadId>,
+    },
+}
+
+/// Slack frontend: receives Events API callbacks over HTTP and replies in
+/// the message's thread via `chat.postMessage`.
+pub struct SlackFrontend {
+    config: SlackConfig,
+}
+
+impl SlackFrontend {
+    pub fn new(config: SlackConfig) -> Self {
+        Self { config }
+    }
+}
+
+#[derive(Clone)]
+struct ServerState {
+    signing_secret: Arc<String>,
+    fe_tx: mpsc::Sender<SlackInternalEvent>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum EventPayload {
+    UrlVerification { challenge: String },
+    EventCallback { event: MessageEvent },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct MessageEvent {
+    #[serde(rename = "type")]
+    event_type: String,
+    #[serde(default)]
+    subtype: Option<String>,
+    #[serde(default)]
+    bot_id: Option<String>,
+    #[serde(default)]
+    user: Option<String>,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    channel: Option<String>,
+    #[serde(default)]
+    ts: Option<String>,
+    #[serde(default)]
+    thread_ts: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl Frontend for SlackFrontend {
+    async fn run(self: Box<Self>, manager: Arc<ThreadManager>) -> Result<()> {
+        let api = Arc::new(SlackApi::new(&self.config.bot_token));
+        let (fe_tx, mut fe_rx) = mpsc::channel::<SlackInternalEvent>(512);
+
+        let server = start_events_server(&self.config, fe_tx.clone()).await?;
+        spawn_drain_watcher(manager.clone(), fe_tx.clone(), |remaining| {
+            SlackInternalEvent::Drained { remaining }
+        });
+        info!("Slack frontend started");
+
+        let mut render_states: HashMap<ThreadId, ThreadRenderState> = HashMap::new();
+        let mut ts_to_thread: HashMap<String, ThreadId> = HashMap::new();
+
+        while let Some(event) = fe_rx.recv().await {
+            match event {
+                SlackInternalEvent::Message {
+                    channel,
+                    user,
+                    thread_ts,
+                    text,
+                } => {
+                    manager.record_activity();
+                    if manager.is_draining() {
+                        info!("Ignoring Slack message from {user}: shutting down");
+                        continue;
+                    }
+
+                    // A message in a known Slack thread continues that conversation
+                    if let Some(tid) = ts_to_thread.get(&thread_ts).cloned() {
+                        if let Some(thread) = manager.get_thread(&tid).await {
+                            info!("[{tid}] Routing Slack reply");
+                            let _ = thread.submit(Submission::FollowUp(text)).await;
+                            continue;
+                        }
+                        ts_to_thread.remove(&thread_ts);
+                    }
+
+                    let default_agent = chat_default_agent(&manager).await;
+                    let Some((agent_type, prompt)) = parse_command(&text, &default_agent) else {
+                        let api = api.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = api.post_message(&channel, &thread_ts, &command_usage()).await {
+                                error!("Failed to send command usage: {e}");
+                            }
+                        });
+                        continue;
+                    };
+                    let agent_type = agent_type.as_str();
+
+                    let (thread_id, thread) = match manager.create_thread(agent_type, ThreadOverrides::default()).await {
+                        Ok(v) => v,
+                        Err(e) => {
+                            error!("Failed to create thread: {e}");
+                            continue;
+                        }
+                    };
+                    info!("[{thread_id}] New Slack task: user={user}, agent={agent_type}");
+
+                    if let Err(e) = thread.submit(Submission::UserMessage(prompt)).await {
+                        error!("[{thread_id}] Failed to submit: {e}");
+                        continue;
+                    }
+
+                    ts_to_thread.insert(thread_ts.clone(), thread_id.clone());
+                    render_states.insert(
+                        thread_id,
+                        ThreadRenderState {
+                            channel,
+                            thread_ts,
+                            text_buffer: String::new(),
+                        },
+                    );
+                    spawn_event_poller(thread, fe_tx.clone());
+                }
+
+                SlackInternalEvent::AgentOutput { thread_id, event } => {
+                    if let Some(state) = render_states.get_mut(&thread_id) {
+                        handle_agent_event(state, &api, &thread_id, event).await;
+                    }
+                }
+
+                SlackInternalEvent::Drained { remaining } => {
+                    for tid in &remaining {
+                        if let Some(state) = render_states.get_mut(tid) {
+                            warn!("[{tid}] Still running at shutdown");
+                            state.text_buffer.push_str("\n\n⚠️ Agent shutting down.");
+                            flush_reply(state, &api).await;
+                        }
+                    }
+                    info!("Slack frontend drained");
+                    break;
+                }
+            }
+        }
+
+        server.abort();
+        Ok(())
+    }
+}
+
+/// Spawn a task that forwards AgentEvents from a thread's EQ.
+/// Stays alive across turns so follow-ups reuse the same thread.
+fn spawn_event_poller(thread: Arc<AgentThread>, fe_tx: mpsc::Sender<SlackInternalEvent>) {
+    let thread_id = thread.thread_id.clone();
+    tokio::spawn(async move {
+        while let Some(event) = thread.next_event().await {
+            let _ = fe_tx
+                .send(SlackInternalEvent::AgentOutput {
+                    thread_id: thread_id.clone(),
+                    event,
+                })
+                .await;
+        }
+    });
+}
+
+/// Buffer agent output and post it once the turn ends.
+async fn handle_agent_event(
+    state: &mut ThreadRenderState,
+    api: &SlackApi,
+    thread_id: &ThreadId,
+    event: AgentEvent,
+) {
+    match event {
+        AgentEvent::TextDelta { text, .. } => {
+            state.text_buffer.push_str(&text);
+        }
+        AgentEvent::ContentBlockStart {
+            content_block: ContentBlock::ToolUse { name, .. },
+            ..
+        } => {
+            info!("[{thread_id}] Tool start: {name}");
+            state.text_buffer.push_str(&format!("\n\n🔧 *Tool: {name}*\n"));
+        }
+        AgentEvent::StatusChange(ref status) => {
+            info!("[{thread_id}] Status: {status:?}");
+            if let AgentStatus::Failed(msg) = status {
+                state.text_buffer.push_str(&format!("\n\n*Failed:* {msg}"));
+            }
+            if status.is_terminal() {
+                flush_reply(state, api).await;
+            }
+        }
+        AgentEvent::Error(msg) => {
+            state.text_buffer.push_str(&format!("\n\n*Error:* {msg}"));
+            flush_reply(state, api).await;
+        }
+        _ => {}
+    }
+}
+
+/// Post the buffered text into the Slack thread and clear the buffer.
+async fn flush_reply(state: &mut ThreadRenderState, api: &SlackApi) {
+    let text = std::mem::take(&mut state.text_buffer);
+    let text = text.trim();
+    let text = if text.is_empty() { "(no output)" } else { text };
+    for chunk in split_message(text, MAX_MESSAGE_CHARS) {
+        if let Err(e) = api
+            .post_message(&state.channel, &state.thread_ts, &chunk)
+            .await
+        {
+            warn!("Failed to post Slack message: {e}");
+            return;
+        }
+    }
+}
+
+// ── Events API server ──
+
+async fn start_events_server(
+    config: &SlackConfig,
+    fe_tx: mpsc::Sender<SlackInternalEvent>,
+) -> Result<tokio::task::JoinHandle<()>> {
+    let state = ServerState {
+        signing_secret: Arc::new(config.signing_secret.clone()),
+        fe_tx,
+    };
+    let app = Router::new()
+        .route("/slack/events", post(events_handler))
+        .with_state(state);
+
+    // Slack must be able to reach this, so listen on all interfaces
+    let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to bind Slack events port {}: {e}", config.port))?;
+    info!("Slack events endpoint listening on http://{addr}/slack/events");
+
+    Ok(tokio::spawn(async move {
+        axum::serve(listener, app).await.ok();
+    }))
+}
+
+async fn events_handler(
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if let Err(e) = verify_signature(&state.signing_secret, &headers, &body) {
+        warn!("Rejected Slack request: {e}");
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    // Slack retries when we are slow to ack; the original is already being handled
+    if headers.contains_key("x-slack-retry-num") {
+        return StatusCode::OK.into_response();
+    }
+
+    let payload: EventPayload = match serde_json::from_slice(&body) {
+        Ok(p) => p,
+        Err(e) => {
+            warn!("Invalid Slack payload: {e}");
+            return StatusCode::BAD_REQUEST.into_response();
+        }
+    };
+
+    match payload {
+        EventPayload::UrlVerification { challenge } => {
+            Json(serde_json::json!({ "challenge": challenge })).into_response()
+        }
+        EventPayload::EventCallback { event } => {
+            if let Some(msg) = into_internal_message(event) {
+                let _ = state.fe_tx.send(msg).await;
+            }
+            StatusCode::OK.into_response()
+        }
+        EventPayload::Other => StatusCode::OK.into_response(),
+    }
+}
+
+/// Keep plain user messages; drop bot echoes, edits, joins and the like.
+fn into_internal_message(event: MessageEvent) -> Option<SlackInternalEvent> {
+    if event.event_type != "message" || event.subtype.is_some() || event.bot_id.is_some() {
+        return None;
+    }
+    let text = event.text.filter(|t| !t.trim().is_empty())?;
+    let ts = event.ts?;
+    Some(SlackInternalEvent::Message {
+        channel: event.channel?,
+        user: event.user.unwrap_or_default(),
+        thread_ts: event.thread_ts.unwrap_or(ts),
+        text,
+    })
+}
+
+/// Check `X-Slack-Signature` against `v0:<timestamp>:<body>` signed with the signing secret.
+fn verify_signature(secret: &str, headers: &HeaderMap, body: &[u8]) -> Result<()> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| anyhow::anyhow!("missing {name} header"))
+    };
+    let timestamp = header("x-slack-request-timestamp")?;
+    let signature = header("x-slack-signature")?;
+
+    let ts: u64 = timestamp
+        .parse()
+        .map_err(|_| anyhow::anyhow!("invalid timestamp"))?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    if now.abs_diff(ts) > MAX_REQUEST_AGE_SECS {
+        anyhow::bail!("stale request timestamp");
+    }
+
+    let expected = signature
+        .strip_prefix("v0=")
+        .and_then(|hex_sig| hex::decode(hex_sig).ok())
+        .ok_or_else(|| anyhow::anyhow!("malformed signature"))?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())?;
+    mac.update(b"v0:");
+    mac.update(timestamp.as_bytes());
+    mac.update(b":");
+    mac.update(body);
+    mac.verify_slice(&expected)
+        .map_err(|_| anyhow::anyhow!("signature mismatch"))
+}
+
+// ── Web API client ──
+
+struct SlackApi {
+    http: reqwest::Client,
+    bot_token: String,
+}
+
+#[derive(Deserialize)]
+struct ApiResponse {
+    ok: bool,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl SlackApi {
+    fn new(bot_token: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            bot_token: bot_token.to_string(),
+        }
+    }
+
+    async fn post_message(&self, channel: &str, thread_ts: &str, text: &str) -> Result<()> {
+        let resp: ApiResponse = self
+            .http
+            .post(format!("{SLACK_API_BASE}/chat.postMessage"))
+            .bearer_auth(&self.bot_token)
+            .json(&serde_json::json!({
+                "channel": channel,
+                "thread_ts": thread_ts,
+                "text": text,
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if !resp.ok {
+            anyhow::bail!(
+                "chat.postMessage failed: {}",
+                resp.error.unwrap_or_else(|| "unknown error".into())
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,434 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use teloxide::dispatching::{Dispatcher, UpdateFilterExt, UpdateHandler};
+use teloxide::prelude::*;
+use teloxide::types::{ChatId, Message, MessageId, ReplyParameters, Update};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use crate::config::TelegramConfig;
+use crate::protocol::{AgentEvent, AgentStatus, ContentBlock, Submission, ThreadId};
+use crate::thread::AgentThread;
+use crate::thread_manager::{ThreadManager, ThreadOverrides};
+
+use super::{
+    chat_default_agent, command_usage, parse_command, spawn_drain_watcher, split_message, Frontend,
+};
+
+/// Telegram rejects message text longer than this.
+const MAX_MESSAGE_CHARS: usize = 4096;
+/// Minimum gap between edits of one message, to stay under Telegram's flood limits.
+const EDIT_INTERVAL: Duration = Duration::from_secs(1);
+const PLACEHOLDER: &str = "Thinking...";
+
+/// Per-thread rendering state for the bot's reply message.
+struct ThreadRenderState {
+    chat_id: ChatId,
+    /// The user message the current reply answers.
+    request_msg_id: MessageId,
+    reply_msg_id: Option<MessageId>,
+    text_buffer: String,
+    last_edit: Option<Instant>,
+    /// Text changed since the last successful edit.
+    dirty: bool,
+    /// The current turn's reply has received its final edit.
+    turn_finished: bool,
+}
+
+/// Internal events for the Telegram frontend's main loop.
+enum TelegramInternalEvent {
+    NewMessage {
+        chat_id: ChatId,
+        msg_id: MessageId,
+        /// Set when the user replied to an earlier message.
+        reply_to: Option<MessageId>,
+        user: String,
+        text: String,
+    },
+    ReplyReady {
+        thread_id: ThreadId,
+        msg_id: MessageId,
+    },
+    AgentOutput {
+        thread_id: ThreadId,
+        event: AgentEvent,
+    },
+    /// Shutdown drain finished; `remaining` threads did not exit in time.
+    Drained {
+        remaining: Vec<ThreadId>,
+    },
+}
+
+/// Telegram bot frontend using long polling.
+pub struct TelegramFrontend {
+    config: TelegramConfig,
+}
+
+impl TelegramFrontend {
+    pub fn new(config: TelegramConfig) -> Self {
+        Self { config }
+    }
+}
+
+/// Dependencies injected into the update handler.
+#[derive(Clone)]
+struct HandlerDeps {
+    allowed_chat_ids: Arc<Vec<i64>>,
+    fe_tx: mpsc::Sender<TelegramInternalEvent>,
+}
+
+/// Route text messages from allowed chats into the main loop.
+fn schema() -> UpdateHandler<teloxide::RequestError> {
+    Update::filter_message().endpoint(|msg: Message, deps: HandlerDeps| async move {
+        // The agent can run commands on this host: unlisted chats are ignored
+        if !deps.allowed_chat_ids.contains(&msg.chat.id.0) {
+            info!("Ignoring Telegram message from chat {}", msg.chat.id);
+            return respond(());
+        }
+        let Some(text) = msg.text().map(str::trim).filter(|t| !t.is_empty()) else {
+            return respond(());
+        };
+        let user = msg
+            .from
+            .as_ref()
+            .map(|u| u.full_name())
+            .unwrap_or_default();
+        let _ = deps
+            .fe_tx
+            .send(TelegramInternalEvent::NewMessage {
+                chat_id: msg.chat.id,
+                msg_id: msg.id,
+                reply_to: msg.reply_to_message().map(|m| m.id),
+                user,
+                text: text.to_string(),
+            })
+            .await;
+        respond(())
+    })
+}
+
+#[async_trait::async_trait]
+impl Frontend for TelegramFrontend {
+    async fn run(self: Box<Self>, manager: Arc<ThreadManager>) -> Result<()> {
+        if self.config.allowed_chat_ids.is_empty() {
+            anyhow::bail!(
+                "Telegram: set channels.telegram.allowed_chat_ids; the bot answers no other chats"
+            );
+        }
+        let bot = Bot::new(&self.config.bot_token);
+        let (fe_tx, mut fe_rx) = mpsc::channel::<TelegramInternalEvent>(512);
+
+        let deps = HandlerDeps {
+            allowed_chat_ids: Arc::new(self.config.allowed_chat_ids.clone()),
+            fe_tx: fe_tx.clone(),
+        };
+        let mut dispatcher = Dispatcher::builder(bot.clone(), schema())
+            .dependencies(dptree::deps![deps])
+            .build();
+        let shutdown_token = dispatcher.shutdown_token();
+        tokio::spawn(async move {
+            dispatcher.dispatch().await;
+        });
+
+        spawn_drain_watcher(manager.clone(), fe_tx.clone(), |remaining| {
+            TelegramInternalEvent::Drained { remaining }
+        });
+        info!("Telegram frontend started");
+
+        let mut render_states: HashMap<ThreadId, ThreadRenderState> = HashMap::new();
+        let mut reply_to_thread: HashMap<(ChatId, MessageId), ThreadId> = HashMap::new();
+
+        while let Some(event) = fe_rx.recv().await {
+            match event {
+                TelegramInternalEvent::NewMessage {
+                    chat_id,
+                    msg_id,
+                    reply_to,
+                    user,
+                    text,
+                } => {
+                    manager.record_activity();
+                    if manager.is_draining() {
+                        info!("Ignoring Telegram message from {user}: shutting down");
+                        continue;
+                    }
+
+                    // Reply to one of our messages → follow-up on that thread
+                    let follow_up = match reply_to
+                        .and_then(|id| reply_to_thread.get(&(chat_id, id)))
+                        .cloned()
+                    {
+                        Some(tid) => manager.get_thread(&tid).await,
+                        None => None,
+                    };
+                    if let Some(thread) = follow_up {
+                        let tid = &thread.thread_id;
+                        info!("[{tid}] Routing Telegram reply");
+                        if let Some(state) = render_states.get_mut(tid) {
+                            state.request_msg_id = msg_id;
+                        }
+                        let _ = thread.submit(Submission::FollowUp(text)).await;
+                        continue;
+                    }
+
+                    let default_agent = chat_default_agent(&manager).await;
+                    let Some((agent_type, prompt)) = parse_command(&text, &default_agent) else {
+                        let bot = bot.clone();
+                        tokio::spawn(async move {
+                            let sent = bot
+                                .send_message(chat_id, command_usage())
+                                .reply_parameters(ReplyParameters::new(msg_id))
+                                .await;
+                            if let Err(e) = sent {
+                                error!("Failed to send command usage: {e}");
+                            }
+                        });
+                        continue;
+                    };
+                    let agent_type = agent_type.as_str();
+
+                    let (thread_id, thread) = match manager.create_thread(agent_type, ThreadOverrides::default()).await {
+                        Ok(v) => v,
+                        Err(e) => {
+                            error!("Failed to create thread: {e}");
+                            continue;
+                        }
+                    };
+                    info!("[{thread_id}] New Telegram task: user={user}, agent={agent_type}");
+
+                    if let Err(e) = thread.submit(Submission::UserMessage(prompt)).await {
+                        error!("[{thread_id}] Failed to submit: {e}");
+                        continue;
+                    }
+
+                    render_states.insert(
+                        thread_id.clone(),
+                        ThreadRenderState {
+                            chat_id,
+                            request_msg_id: msg_id,
+                            reply_msg_id: None,
+                            text_buffer: String::new(),
+                            last_edit: None,
+                            dirty: false,
+                            turn_finished: false,
+                        },
+                    );
+                    spawn_reply(&bot, &fe_tx, thread_id, chat_id, msg_id);
+                    spawn_event_poller(thread, fe_tx.clone());
+                }
+
+                TelegramInternalEvent::ReplyReady { thread_id, msg_id } => {
+                    if let Some(state) = render_states.get_mut(&thread_id) {
+                        state.reply_msg_id = Some(msg_id);
+                        reply_to_thread.insert((state.chat_id, msg_id), thread_id);
+                        // Show anything that arrived before the placeholder existed
+                        if state.turn_finished {
+                            finish_reply(state, &bot);
+                        } else if state.dirty {
+                            update_reply(state, &bot).await;
+                        }
+                    }
+                }
+
+                TelegramInternalEvent::AgentOutput { thread_id, event } => {
+                    if let Some(state) = render_states.get_mut(&thread_id) {
+                        handle_agent_event(state, &bot, &fe_tx, &thread_id, event).await;
+                    }
+                }
+
+                TelegramInternalEvent::Drained { remaining } => {
+                    let mut edits = Vec::new();
+                    for tid in &remaining {
+                        if let Some(state) = render_states.get_mut(tid) {
+                            if state.turn_finished {
+                                continue;
+                            }
+                            warn!("[{tid}] Still running at shutdown");
+                            state.text_buffer.push_str("\n\n⚠️ Agent shutting down.");
+                            state.turn_finished = true;
+                            edits.extend(finish_reply(state, &bot));
+                        }
+                    }
+                    futures_util::future::join_all(edits).await;
+                    info!("Telegram frontend drained");
+                    break;
+                }
+            }
+        }
+
+        if let Ok(stopped) = shutdown_token.shutdown() {
+            stopped.await;
+        }
+        Ok(())
+    }
+}
+
+/// Spawn a task that forwards AgentEvents from a thread's EQ.
+/// Stays alive across turns so follow-ups reuse the same thread.
+fn spawn_event_poller(thread: Arc<AgentThread>, fe_tx: mpsc::Sender<TelegramInternalEvent>) {
+    let thread_id = thread.thread_id.clone();
+    tokio::spawn(async move {
+        while let Some(event) = thread.next_event().await {
+            let _ = fe_tx
+                .send(TelegramInternalEvent::AgentOutput {
+                    thread_id: thread_id.clone(),
+                    event,
+                })
+                .await;
+        }
+    });
+}
+
+/// Post the "Thinking..." placeholder that gets edited as output streams in.
+fn spawn_reply(
+    bot: &Bot,
+    fe_tx: &mpsc::Sender<TelegramInternalEvent>,
+    thread_id: ThreadId,
+    chat_id: ChatId,
+    request_msg_id: MessageId,
+) {
+    let bot = bot.clone();
+    let fe_tx = fe_tx.clone();
+    tokio::spawn(async move {
+        let sent = bot
+            .send_message(chat_id, PLACEHOLDER)
+            .reply_parameters(ReplyParameters::new(request_msg_id))
+            .await;
+        match sent {
+            Ok(msg) => {
+                let _ = fe_tx
+                    .send(TelegramInternalEvent::ReplyReady {
+                        thread_id,
+                        msg_id: msg.id,
+                    })
+                    .await;
+            }
+            Err(e) => error!("[{thread_id}] Failed to send Telegram reply: {e}"),
+        }
+    });
+}
+
+async fn handle_agent_event(
+    state: &mut ThreadRenderState,
+    bot: &Bot,
+    fe_tx: &mpsc::Sender<TelegramInternalEvent>,
+    thread_id: &ThreadId,
+    event: AgentEvent,
+) {
+    match event {
+        AgentEvent::TextDelta { text, .. } => {
+            state.text_buffer.push_str(&text);
+            state.dirty = true;
+            let due = state.last_edit.is_none_or(|t| t.elapsed() >= EDIT_INTERVAL);
+            if due {
+                update_reply(state, bot).await;
+            }
+        }
+
+        AgentEvent::ContentBlockStart {
+            content_block: ContentBlock::ToolUse { name, .. },
+            ..
+        } => {
+            info!("[{thread_id}] Tool start: {name}");
+            state.text_buffer.push_str(&format!("\n\n🔧 Tool: {name}\n"));
+            state.dirty = true;
+        }
+
+        AgentEvent::StatusChange(ref status) => {
+            info!("[{thread_id}] Status: {status:?}");
+            if *status == AgentStatus::Working && state.turn_finished {
+                // Follow-up turn: start a fresh reply to the new request
+                state.text_buffer.clear();
+                state.reply_msg_id = None;
+                state.last_edit = None;
+                state.dirty = false;
+                state.turn_finished = false;
+                spawn_reply(bot, fe_tx, thread_id.clone(), state.chat_id, state.request_msg_id);
+            }
+            if let AgentStatus::Failed(msg) = status {
+                state.text_buffer.push_str(&format!("\n\nFailed: {msg}"));
+            }
+            if status.is_terminal() {
+                state.turn_finished = true;
+                finish_reply(state, bot);
+            }
+        }
+
+        AgentEvent::Error(msg) => {
+            state.text_buffer.push_str(&format!("\n\nError: {msg}"));
+            state.turn_finished = true;
+            finish_reply(state, bot);
+        }
+
+        _ => {}
+    }
+}
+
+/// While streaming, show the tail of the output so the latest text is visible.
+fn streaming_preview(text: &str) -> String {
+    let count = text.chars().count();
+    if count <= MAX_MESSAGE_CHARS {
+        return text.to_string();
+    }
+    let tail: String = text.chars().skip(count - (MAX_MESSAGE_CHARS - 1)).collect();
+    format!("…{tail}")
+}
+
+/// Edit the placeholder with the current output. Skips if the placeholder
+/// hasn't been posted yet; `ReplyReady` flushes it later.
+async fn update_reply(state: &mut ThreadRenderState, bot: &Bot) {
+    let Some(reply_id) = state.reply_msg_id else {
+        return;
+    };
+    if state.text_buffer.trim().is_empty() {
+        return;
+    }
+    state.last_edit = Some(Instant::now());
+    match bot
+        .edit_message_text(state.chat_id, reply_id, streaming_preview(&state.text_buffer))
+        .await
+    {
+        Ok(_) => state.dirty = false,
+        Err(e) => warn!("Failed to edit Telegram message: {e}"),
+    }
+}
+
+/// Write the final output: the first chunk replaces the placeholder, any
+/// overflow is sent as additional messages.
+/// Returns the task making the edits, if there is a placeholder to edit.
+fn finish_reply(state: &mut ThreadRenderState, bot: &Bot) -> Option<JoinHandle<()>> {
+    let reply_id = state.reply_msg_id?;
+    let text = state.text_buffer.trim();
+    let text = if text.is_empty() { "(no output)" } else { text };
+    let mut chunks = split_message(text, MAX_MESSAGE_CHARS).into_iter();
+    let first = chunks.next().unwrap_or_default();
+    let rest: Vec<String> = chunks.collect();
+    state.dirty = false;
+
+    // Respect the edit interval so the final edit isn't rejected as flooding,
+    // in its own task so other conversations keep going
+    let wait = state
+        .last_edit
+        .map(|t| EDIT_INTERVAL.saturating_sub(t.elapsed()))
+        .unwrap_or_default();
+    state.last_edit = Some(Instant::now() + wait);
+    let chat_id = state.chat_id;
+    let bot = bot.clone();
+    Some(tokio::spawn(async move {
+        tokio::time::sleep(wait).await;
+        if let Err(e) = bot.edit_message_text(chat_id, reply_id, first).await {
+            warn!("Failed to finish Telegram message: {e}");
+            return;
+        }
+        for chunk in rest {
+            if let Err(e) = bot.send_message(chat_id, chunk).await {
+                warn!("Failed to send Telegram message: {e}");
+                return;
+            }
+        }
+    }))
+}
@@ -0,0 +1,225 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::config::WebhookConfig;
+use crate::protocol::{AgentEvent, AgentStatus, Submission};
+use crate::thread_manager::{ThreadManager, ThreadOverrides};
+
+use super::{chat_default_agent, Frontend};
+
+/// HTTP frontend for automation: `POST /prompt` runs one agent turn and
+/// returns the output, or delivers it to a callback URL.
+pub struct WebhookFrontend {
+    config: WebhookConfig,
+}
+
+impl WebhookFrontend {
+    pub fn new(config: WebhookConfig) -> Self {
+        Self { config }
+    }
+}
+
+#[derive(Clone)]
+struct ServerState {
+    token: Arc<String>,
+    manager: Arc<ThreadManager>,
+    http: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct PromptRequest {
+    prompt: String,
+    #[serde(default)]
+    agent: Option<String>,
+    #[serde(default)]
+    callback_url: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PromptResult {
+    thread_id: String,
+    status: String,
+    output: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl Frontend for WebhookFrontend {
+    async fn run(self: Box<Self>, manager: Arc<ThreadManager>) -> Result<()> {
+        if self.config.token.trim().is_empty() {
+            anyhow::bail!("channels.webhook.token is empty: set a secret before enabling the webhook");
+        }
+        let state = ServerState {
+            token: Arc::new(self.config.token.clone()),
+            manager: manager.clone(),
+            http: reqwest::Client::new(),
+        };
+        let app = Router::new()
+            .route("/prompt", post(prompt_handler))
+            .with_state(state);
+
+        let addr = SocketAddr::from(([0, 0, 0, 0], self.config.port));
+        let listener = tokio::net::TcpListener::bind(addr).await.map_err(|e| {
+            anyhow::anyhow!("Failed to bind webhook port {}: {e}", self.config.port)
+        })?;
+        info!("Webhook frontend listening on http://{addr}/prompt");
+
+        let server = tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        // Keep serving until shutdown, then give in-flight prompts time to finish
+        manager.wait_draining().await;
+        let timeout = Duration::from_secs(manager.config().await.shutdown_timeout_secs);
+        let remaining = manager.drain(timeout).await;
+        if !remaining.is_empty() {
+            warn!("{} webhook thread(s) still running at shutdown", remaining.len());
+        }
+        server.abort();
+        info!("Webhook frontend drained");
+        Ok(())
+    }
+}
+
+async fn prompt_handler(
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+    Json(req): Json<PromptRequest>,
+) -> Response {
+    let authorized = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|t| constant_time_eq(t.as_bytes(), state.token.as_bytes()));
+    if !authorized {
+        return (StatusCode::UNAUTHORIZED, "Invalid or missing bearer token").into_response();
+    }
+
+    state.manager.record_activity();
+    if state.manager.is_draining() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Shutting down").into_response();
+    }
+
+    let agent_type = match req.agent {
+        Some(agent) if matches!(agent.as_str(), "claude" | "myagent") => agent,
+        Some(agent) => {
+            let msg = format!("Unknown agent: {agent} (expected \"claude\" or \"myagent\")");
+            return (StatusCode::BAD_REQUEST, msg).into_response();
+        }
+        None => chat_default_agent(&state.manager).await,
+    };
+    let (thread_id, thread) = match state.manager.create_thread(&agent_type, ThreadOverrides::default()).await {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Failed to create thread: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    };
+    info!("[{thread_id}] New webhook task: agent={agent_type}");
+
+    if let Err(e) = thread.submit(Submission::UserMessage(req.prompt)).await {
+        error!("[{thread_id}] Failed to submit: {e}");
+        return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+    }
+
+    // Spawned so the thread is still cleaned up if the client disconnects
+    // and the handler is dropped
+    let run = {
+        let manager = state.manager.clone();
+        tokio::spawn(async move {
+            let result = collect_result(&thread).await;
+            let _ = thread.submit(Submission::Shutdown).await;
+            manager.remove_thread(&thread.thread_id).await;
+            result
+        })
+    };
+
+    match req.callback_url {
+        None => match run.await {
+            Ok(result) => Json(result).into_response(),
+            Err(e) => {
+                error!("[{thread_id}] Webhook task failed: {e}");
+                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+            }
+        },
+        Some(url) => {
+            let http = state.http.clone();
+            let tid = thread_id.clone();
+            tokio::spawn(async move {
+                let result = match run.await {
+                    Ok(result) => result,
+                    Err(e) => {
+                        error!("[{tid}] Webhook task failed: {e}");
+                        return;
+                    }
+                };
+                let sent = http
+                    .post(&url)
+                    .json(&result)
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status());
+                if let Err(e) = sent {
+                    warn!("[{tid}] Failed to deliver result to callback: {e}");
+                }
+            });
+            (
+                StatusCode::ACCEPTED,
+                Json(serde_json::json!({ "thread_id": thread_id.0 })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Read the thread's events until the turn ends, accumulating text output.
+async fn collect_result(thread: &crate::thread::AgentThread) -> PromptResult {
+    let mut output = String::new();
+    let mut status = "completed";
+    let mut error = None;
+
+    while let Some(event) = thread.next_event().await {
+        match event {
+            AgentEvent::TextDelta { text, .. } => output.push_str(&text),
+            AgentEvent::StatusChange(s) if s.is_terminal() => {
+                match s {
+                    AgentStatus::Failed(msg) => {
+                        status = "failed";
+                        error = Some(msg);
+                    }
+                    AgentStatus::Cancelled => status = "cancelled",
+                    _ => {}
+                }
+                break;
+            }
+            AgentEvent::Error(msg) => {
+                status = "failed";
+                error = Some(msg);
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    PromptResult {
+        thread_id: thread.thread_id.0.clone(),
+        status: status.to_string(),
+        output,
+        error,
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -1,19 +1,40 @@
-use std::net::SocketAddr;
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Instant;
 
-use axum::extract::State;
+use axum::extract::{ConnectInfo, State};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
 use axum::routing::{get, post};
 use axum::{Json, Router};
 use serde::{Deserialize, Serialize};
-use tokio::sync::broadcast;
-use tracing::info;
+use tokio::sync::{broadcast, RwLock};
+use tracing::{info, warn};
+
+use crate::config::AppConfig;
+use crate::metrics::Metrics;
+use crate::protocol::MonitorEvent;
+use crate::thread_manager::ThreadManager;
+
+/// Most `/events` WebSocket clients connected at once.
+const MAX_EVENT_CONNECTIONS: usize = 10;
 
 #[derive(Clone)]
 struct AppState {
     start_time: Instant,
+    bind: IpAddr,
     port: u16,
     shutdown_tx: Arc<broadcast::Sender<()>>,
+    config: Arc<RwLock<AppConfig>>,
+    config_path: PathBuf,
+    manager: Arc<ThreadManager>,
+    metrics: Arc<Metrics>,
+    monitor: broadcast::Sender<MonitorEvent>,
+    event_connections: Arc<AtomicUsize>,
 }
 
 #[derive(Serialize)]
@@ -23,6 +44,27 @@ struct HealthResponse {
     uptime: u64,
     pid: u32,
     port: u16,
+    /// Unix time of the last incoming message or RPC (None if there was none).
+    last_activity_at: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct ThreadsResponse {
+    count: usize,
+    threads: Vec<ThreadInfo>,
+}
+
+#[derive(Serialize)]
+struct ThreadInfo {
+    thread_id: String,
+    agent: String,
+}
+
+#[derive(Serialize)]
+struct ToolStatsInfo {
+    calls: u64,
+    errors: u64,
+    avg_duration_ms: u64,
 }
 
 #[derive(Deserialize)]
@@ -47,14 +89,141 @@ async fn health_handler(State(state): State<AppState>) -> Json<HealthResponse> {
         uptime: state.start_time.elapsed().as_secs(),
         pid: std::process::id(),
         port: state.port,
+        last_activity_at: state.manager.last_activity().map(|t| t.timestamp()),
+    })
+}
+
+async fn threads_handler(State(state): State<AppState>) -> Json<ThreadsResponse> {
+    let threads: Vec<ThreadInfo> = state
+        .manager
+        .active_threads()
+        .await
+        .iter()
+        .map(|t| ThreadInfo {
+            thread_id: t.thread_id.to_string(),
+            agent: t.agent_name.clone(),
+        })
+        .collect();
+    Json(ThreadsResponse {
+        count: threads.len(),
+        threads,
+    })
+}
+
+/// `403` for peers other than this host. Guards the endpoints that expose
+/// the config or control the daemon when `health_bind` is not loopback.
+/// The CLI connects to a specific bind IP directly, so that IP counts as local.
+fn refuse_remote(peer: SocketAddr, bind: IpAddr) -> Option<Response> {
+    let ip = peer.ip().to_canonical();
+    if ip.is_loopback() || ip == bind.to_canonical() {
+        return None;
+    }
+    warn!("Refused {peer}: only local clients may use this endpoint");
+    Some((StatusCode::FORBIDDEN, "Only available from localhost").into_response())
+}
+
+/// Current config with secrets masked. Local clients only.
+async fn config_handler(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+) -> Response {
+    if let Some(refused) = refuse_remote(peer, state.bind) {
+        return refused;
+    }
+    let config = serde_json::to_value(&*state.config.read().await).unwrap_or_default();
+    Json(crate::cmd_config::mask_secrets(&config)).into_response()
+}
+
+async fn metrics_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let active = state.manager.active_threads().await.len();
+    let body = state
+        .metrics
+        .render(active, state.start_time.elapsed().as_secs());
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+/// Per-tool call statistics since startup or the last reload.
+async fn tool_stats_handler(
+    State(state): State<AppState>,
+) -> Json<BTreeMap<String, ToolStatsInfo>> {
+    let stats = state
+        .manager
+        .tool_stats()
+        .into_iter()
+        .map(|(name, s)| {
+            let info = ToolStatsInfo {
+                calls: s.calls,
+                errors: s.errors,
+                avg_duration_ms: s.total_duration_ms.checked_div(s.calls).unwrap_or(0),
+            };
+            (name, info)
+        })
+        .collect();
+    Json(stats)
+}
+
+/// Holds one of the `/events` connection slots until dropped.
+struct EventConnection(Arc<AtomicUsize>);
+
+impl Drop for EventConnection {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Stream thread lifecycle events as JSON text messages over a WebSocket.
+async fn events_handler(State(state): State<AppState>, ws: WebSocketUpgrade) -> impl IntoResponse {
+    if state.event_connections.fetch_add(1, Ordering::SeqCst) >= MAX_EVENT_CONNECTIONS {
+        state.event_connections.fetch_sub(1, Ordering::SeqCst);
+        return (StatusCode::SERVICE_UNAVAILABLE, "Too many /events connections").into_response();
+    }
+    let slot = EventConnection(state.event_connections.clone());
+    let rx = state.monitor.subscribe();
+    ws.on_upgrade(move |socket| async move {
+        stream_events(socket, rx).await;
+        drop(slot);
     })
 }
 
+async fn stream_events(mut socket: WebSocket, mut rx: broadcast::Receiver<MonitorEvent>) {
+    loop {
+        tokio::select! {
+            event = rx.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        warn!("/events client lagged, skipped {n} events");
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                let Ok(text) = serde_json::to_string(&event) else {
+                    continue;
+                };
+                if socket.send(Message::Text(text.into())).await.is_err() {
+                    break;
+                }
+            }
+            // Clients only listen; stop when they close or go away
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+/// `shutdown` and `reload`. Local clients only.
 async fn rpc_handler(
     State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
     Json(req): Json<RpcRequest>,
-) -> Json<RpcResponse> {
-    match req.method.as_str() {
+) -> Response {
+    if let Some(refused) = refuse_remote(peer, state.bind) {
+        return refused;
+    }
+    state.manager.record_activity();
+    let response = match req.method.as_str() {
         "shutdown" => {
             let _ = state.shutdown_tx.send(());
             Json(RpcResponse {
@@ -63,32 +232,92 @@ async fn rpc_handler(
                 id: req.id,
             })
         }
+        "reload" => {
+            let result = match reload_config(&state).await {
+                Ok(()) => serde_json::json!({"status": "reloaded"}),
+                Err(e) => serde_json::json!({"error": e.to_string()}),
+            };
+            Json(RpcResponse {
+                jsonrpc: "2.0".to_string(),
+                result,
+                id: req.id,
+            })
+        }
         _ => Json(RpcResponse {
             jsonrpc: "2.0".to_string(),
             result: serde_json::json!({"error": "method_not_found"}),
             id: req.id,
         }),
-    }
+    };
+    response.into_response()
+}
+
+/// Re-read the config file and swap it into the shared config.
+/// New threads pick it up; running threads keep the config they started with.
+async fn reload_config(state: &AppState) -> anyhow::Result<()> {
+    let config = AppConfig::load(&state.config_path)?.with_env_overrides();
+    *state.config.write().await = config;
+    state.manager.reset_tool_stats();
+    info!("Config reloaded from {}", state.config_path.display());
+    Ok(())
+}
+
+/// Reload config on SIGHUP.
+#[cfg(unix)]
+fn spawn_sighup_handler(state: AppState) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hup = match signal(SignalKind::hangup()) {
+        Ok(s) => s,
+        Err(e) => {
+            warn!("Failed to install SIGHUP handler: {e}");
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        while hup.recv().await.is_some() {
+            info!("SIGHUP received, reloading config");
+            if let Err(e) = reload_config(&state).await {
+                warn!("Config reload failed: {e}");
+            }
+        }
+    });
 }
 
 /// Start the health check HTTP server.
 /// Returns a broadcast receiver that signals when shutdown is requested via RPC.
 pub async fn start_health_server(
+    bind: IpAddr,
     port: u16,
+    config: Arc<RwLock<AppConfig>>,
+    config_path: PathBuf,
+    manager: Arc<ThreadManager>,
 ) -> anyhow::Result<broadcast::Receiver<()>> {
     let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
     let state = AppState {
         start_time: Instant::now(),
+        bind,
         port,
         shutdown_tx: Arc::new(shutdown_tx),
+        config,
+        config_path,
+        metrics: manager.metrics().clone(),
+        monitor: manager.monitor().clone(),
+        event_connections: Arc::new(AtomicUsize::new(0)),
+        manager,
     };
 
     let app = Router::new()
         .route("/health", get(health_handler))
+        .route("/threads", get(threads_handler))
+        .route("/metrics", get(metrics_handler))
+        .route("/config", get(config_handler))
         .route("/rpc", post(rpc_handler))
-        .with_state(state);
+        .route("/events", get(events_handler))
+        .route("/stats/tools", get(tool_stats_handler))
+        .with_state(state.clone());
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let addr = SocketAddr::new(bind, port);
     let listener = tokio::net::TcpListener::bind(addr).await.map_err(|e| {
         if e.kind() == std::io::ErrorKind::AddrInUse {
             anyhow::anyhow!("myagent is already running (port {} in use)", port)
@@ -98,9 +327,18 @@ pub async fn start_health_server(
     })?;
 
     info!("Health server listening on http://{}", addr);
+    if bind.is_unspecified() {
+        warn!("Health server is publicly accessible; ensure firewall rules are in place.");
+    }
+
+    #[cfg(unix)]
+    spawn_sighup_handler(state.clone());
 
     tokio::spawn(async move {
-        axum::serve(listener, app).await.ok();
+        // Peer addresses let /config and /rpc refuse remote clients
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+            .await
+            .ok();
     });
 
     Ok(shutdown_rx)
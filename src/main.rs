@@ -1,13 +1,16 @@
 mod agent;
 mod ai;
+mod audit;
 mod cmd_config;
 mod cmd_feishu;
 mod cmd_init;
+mod cmd_logs;
 mod cmd_update;
 mod config;
 mod daemon;
 mod frontend;
 mod health;
+mod metrics;
 mod protocol;
 mod thread;
 mod thread_manager;
@@ -15,18 +18,29 @@ mod tools;
 mod transport;
 mod update_check;
 
+use std::io::IsTerminal;
 use std::path::PathBuf;
+use std::process::ExitCode;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
 use clap::{Parser, Subcommand};
-use tracing::info;
+use tokio::io::AsyncReadExt;
+use tracing::{info, warn};
 use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 
 use crate::frontend::Frontend;
 
 #[derive(Parser)]
-#[command(name = "myagent", about = "AI coding agent", version)]
+#[command(
+    name = "myagent",
+    about = "AI coding agent",
+    long_about = "AI coding agent. Without a subcommand or --prompt, piped stdin \
+        (e.g. `cat task.txt | myagent`) is read as a one-shot prompt.",
+    version
+)]
 struct Cli {
     /// Path to config file
     #[arg(short, long, global = true)]
@@ -35,13 +49,43 @@ struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
 
-    /// One-shot prompt (CLI mode)
+    /// One-shot prompt (CLI mode); read from stdin when it is piped
     #[arg(short, long)]
     prompt: Option<String>,
 
-    /// Agent type (default from config)
+    /// Agent type (default from config); a comma-separated list such as
+    /// `myagent,claude` falls back to the next agent if one fails to start
     #[arg(short, long)]
     agent: Option<String>,
+
+    /// Don't read or write ~/.myagent/history in interactive mode
+    #[arg(long)]
+    no_history: bool,
+
+    /// Syntax-highlight fenced code blocks in agent output
+    #[arg(long)]
+    highlight: bool,
+
+    /// Print a preview of each tool call's output
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Show a running token count and cost estimate while output streams
+    #[arg(long)]
+    show_cost: bool,
+
+    /// In one-shot mode, cancel the agent and exit with status 124 after
+    /// this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+
+    /// Log output format (default from config, else text)
+    #[arg(long, global = true, value_parser = ["text", "json"])]
+    log_format: Option<String>,
+
+    /// Append every agent event to this JSONL file (see `myagent replay`)
+    #[arg(long, global = true)]
+    record: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -52,14 +96,42 @@ enum Commands {
     Stop,
     /// Show daemon status
     Status,
+    /// Print the daemon's PID (for scripts); exits with status 1 if it
+    /// isn't running
+    Pid,
     /// Restart the daemon (stop + start)
-    Restart,
+    Restart {
+        /// Block until the new daemon answers its health check (up to 30s)
+        #[arg(long)]
+        wait: bool,
+    },
+    /// Reload the daemon's config without restarting
+    Reload,
     /// Run the daemon in foreground (for development)
     Serve,
+    /// Interactive chat with multi-line input, Ctrl+C to cancel a turn and
+    /// /clear, /thread new and /thread list commands
+    Chat,
     /// Interactive setup wizard
-    Init,
+    Init {
+        /// Skip the wizard and build the config from environment variables
+        #[arg(short = 'y', long = "yes")]
+        non_interactive: bool,
+    },
     /// Update myagent to the latest version
-    Update,
+    Update {
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+        /// Skip SHA-256 verification of the download (air-gapped mirrors)
+        #[arg(long)]
+        skip_verify: bool,
+        /// Only report whether an update is available (exit 2 if so)
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+    /// Restore the binary that was replaced by the last update
+    Rollback,
     /// Feishu file operations (upload/download)
     Feishu {
         #[command(subcommand)]
@@ -70,7 +142,22 @@ enum Commands {
         #[command(subcommand)]
         action: ConfigAction,
     },
-    /// Show daemon logs (tail -f)
+    /// Generate a systemd unit file for running myagent as a service
+    GenerateSystemd {
+        /// Write the unit to this path instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Print the agent output from a `--record` file without running an agent
+    Replay {
+        /// JSONL recording to replay
+        file: PathBuf,
+        /// Only replay threads of this agent type (myagent, claude or all)
+        #[arg(short, long, default_value = "all")]
+        agent_type: String,
+    },
+    /// Show daemon logs. Rotated logs are gzip-compressed
+    /// (myagent.log.1.gz ...); read them with zcat.
     Logs {
         /// Number of lines to show (default: 50)
         #[arg(short = 'n', long, default_value = "50")]
@@ -90,37 +177,115 @@ enum ConfigAction {
     Init,
     /// Show current configuration (secrets masked)
     Show,
+    /// List all config keys with their types and current values
+    List,
     /// Set a config value (dot notation: agents.myagent.env.MYAGENT_API_KEY)
     Set {
         /// Config key path
         key: String,
-        /// Value to set
+        /// Value to set (JSON for objects and arrays)
         value: String,
     },
+    /// Print a single config value for scripts (dot notation, like `set`).
+    /// Exits with status 1 if the key is not set.
+    Get {
+        /// Config key path
+        key: String,
+        /// Print secrets unmasked
+        #[arg(long)]
+        raw: bool,
+    },
+    /// Define a prompt alias: typing `<name> <args>` in the CLI sends the
+    /// template with `{args}` replaced
+    Alias {
+        /// Word that triggers the alias
+        name: String,
+        /// Prompt to send, e.g. "Please review {args}"
+        template: String,
+    },
     /// Print config file path
     Path,
+    /// Export the config for another machine (secrets masked by default)
+    Export {
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Keep API keys, tokens and secrets in the export
+        #[arg(long)]
+        include_secrets: bool,
+    },
+    /// Import a config written by `config export` (or a plain settings.json)
+    Import {
+        /// File to import
+        input: PathBuf,
+        /// Deep-merge into the current config instead of replacing it
+        #[arg(long)]
+        merge: bool,
+    },
+    /// Copy known variables (MYAGENT_API_KEY, ANTHROPIC_API_KEY, ...) from a
+    /// .env file into the config
+    ImportEnv {
+        /// .env file with KEY=VALUE lines
+        path: PathBuf,
+    },
+}
+
+fn main() -> Result<ExitCode> {
+    // The runtime is dropped before exiting, taking its tasks and their
+    // kill_on_drop children with it, also when a one-shot run timed out
+    let result = tokio::runtime::Runtime::new()?.block_on(run());
+    match result {
+        Ok(()) => Ok(ExitCode::SUCCESS),
+        Err(e) if e.is::<frontend::cli::TimedOut>() => Ok(ExitCode::from(124)),
+        Err(e) if e.is::<cmd_update::UpdateAvailable>() => Ok(ExitCode::from(2)),
+        Err(e) if e.is::<daemon::NotReady>() => Ok(ExitCode::FAILURE),
+        Err(e) if e.is::<daemon::NotRunning>() => Ok(ExitCode::FAILURE),
+        Err(e) if e.is::<cmd_config::KeyNotFound>() => Ok(ExitCode::FAILURE),
+        Err(e) => Err(e),
+    }
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
+async fn run() -> Result<()> {
     let cli = Cli::parse();
 
     // Handle commands that don't need config/runtime
     match &cli.command {
         Some(Commands::Stop) => return daemon::stop_daemon(),
         Some(Commands::Status) => return daemon::show_status(),
-        Some(Commands::Start) => return daemon::daemonize(),
-        Some(Commands::Restart) => {
+        Some(Commands::Pid) => return daemon::print_pid(),
+        Some(Commands::Start) => return daemon::daemonize().map(|_| ()),
+        Some(Commands::Restart { wait }) => {
             let _ = daemon::stop_daemon();
-            return daemon::daemonize();
+            let pid = daemon::daemonize()?;
+            if !*wait {
+                return Ok(());
+            }
+            return daemon::wait_for_healthy(pid, 30);
+        }
+        Some(Commands::Reload) => return daemon::reload_daemon(),
+        Some(Commands::Init { non_interactive }) => return cmd_init::run(*non_interactive),
+        Some(Commands::Update { yes, skip_verify, dry_run }) => {
+            let path = cli.config.clone().unwrap_or_else(config::default_config_path);
+            let channel = config::AppConfig::load(&path)
+                .unwrap_or_default()
+                .with_env_overrides()
+                .update_channel;
+            return cmd_update::run(*yes, *skip_verify, *dry_run, &channel).await;
         }
-        Some(Commands::Init) => return cmd_init::run(),
-        Some(Commands::Update) => return cmd_update::run().await,
+        Some(Commands::Rollback) => return cmd_update::rollback(),
         Some(Commands::Feishu { action }) => return cmd_feishu::run(action).await,
         Some(Commands::Config { action }) => {
             let path = cli.config.unwrap_or_else(config::default_config_path);
             return cmd_config::run(action, &path);
         }
+        Some(Commands::GenerateSystemd { output }) => {
+            let path = cli.config.clone().unwrap_or_else(config::default_config_path);
+            return daemon::generate_systemd(&path, output.as_deref());
+        }
+        Some(Commands::Replay { file, agent_type }) => {
+            let events = frontend::recording::load_recording(file, agent_type)?;
+            return frontend::cli::replay(&events, cli.highlight);
+        }
         Some(Commands::Logs { lines, follow, clear }) => {
             if *clear {
                 return daemon::clear_logs();
@@ -129,41 +294,48 @@ async fn main() -> Result<()> {
             if !log_path.exists() {
                 anyhow::bail!("Log file not found: {}", log_path.display());
             }
-            let mut cmd = std::process::Command::new("tail");
-            cmd.arg("-n").arg(lines.to_string());
-            if *follow {
-                cmd.arg("-f");
-            }
-            cmd.arg(log_path);
-            let status = cmd.status()?;
-            std::process::exit(status.code().unwrap_or(1));
+            return cmd_logs::run(&log_path, *lines, *follow);
         }
         _ => {}
     }
 
     let is_serve = matches!(cli.command, Some(Commands::Serve));
+    if cli.timeout.is_some() && cli.command.is_some() {
+        anyhow::bail!("--timeout only applies to one-shot mode (-p or piped stdin)");
+    }
+
+    let config_path = cli.config.unwrap_or_else(config::default_config_path);
 
     // Init logging: CLI → stderr (warn), serve → stdout (info)
-    if is_serve {
-        tracing_subscriber::fmt()
-            .with_env_filter(
-                EnvFilter::try_from_default_env()
-                    .unwrap_or_else(|_| EnvFilter::new("info")),
-            )
-            .with_writer(std::io::stdout)
-            .init();
+    // RUST_LOG wins over the config's log_filters
+    let file_config = config::AppConfig::load(&config_path).ok();
+    let log_format = cli.log_format.clone().unwrap_or_else(|| {
+        file_config
+            .as_ref()
+            .map(|c| c.log_format.clone())
+            .unwrap_or_default()
+    });
+    let (default_level, writer) = if is_serve {
+        ("info", BoxMakeWriter::new(std::io::stdout))
     } else {
-        tracing_subscriber::fmt()
-            .with_env_filter(
-                EnvFilter::try_from_default_env()
-                    .unwrap_or_else(|_| EnvFilter::new("warn")),
-            )
-            .with_writer(std::io::stderr)
-            .init();
+        ("warn", BoxMakeWriter::new(std::io::stderr))
+    };
+    let log_filters = file_config
+        .and_then(|c| c.log_filters)
+        .unwrap_or_else(|| default_level.to_string());
+    let logger = tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new(&log_filters)),
+        )
+        .with_writer(writer);
+    if log_format == "json" {
+        logger.json().flatten_event(true).init();
+    } else {
+        logger.init();
     }
 
     // Load config (with auto-init and env var support)
-    let config_path = cli.config.unwrap_or_else(config::default_config_path);
     let config = if config_path.exists() {
         config::AppConfig::load(&config_path)?.with_env_overrides()
     } else if config::AppConfig::has_required_env_vars() {
@@ -172,7 +344,7 @@ async fn main() -> Result<()> {
     } else {
         // No config, no env vars — auto-run init wizard
         eprintln!("No config found. Running setup wizard...\n");
-        cmd_init::run()?;
+        cmd_init::run(false)?;
         if config_path.exists() {
             config::AppConfig::load(&config_path)?.with_env_overrides()
         } else {
@@ -182,7 +354,7 @@ async fn main() -> Result<()> {
     info!("Config loaded");
 
     // Background update check (non-blocking, only in release builds)
-    let update_hint = update_check::check_on_startup();
+    let update_hint = update_check::check_on_startup(&config.update_channel);
 
     // Resolve workspace: serve uses config value, CLI uses pwd
     let workspace = if is_serve {
@@ -192,45 +364,151 @@ async fn main() -> Result<()> {
     };
     std::fs::create_dir_all(&workspace)?;
 
+    let shared_config = Arc::new(tokio::sync::RwLock::new(config.clone()));
     let manager = Arc::new(thread_manager::ThreadManager::new(
-        config.clone(),
+        shared_config.clone(),
         workspace,
     ));
 
+    let recorder = cli
+        .record
+        .as_deref()
+        .map(frontend::recording::EventRecorder::create)
+        .transpose()?
+        .map(Arc::new);
+
     if is_serve {
         // Start health server (also acts as single-instance guard)
-        let mut shutdown_rx = health::start_health_server(config.port).await?;
+        let mut shutdown_rx =
+            health::start_health_server(
+                config.health_bind_ip()?,
+                config.port,
+                shared_config,
+                config_path,
+                manager.clone(),
+            )
+            .await?;
 
         daemon::write_pid_file()?;
-        let feishu = config
-            .feishu_config()
-            .ok_or_else(|| {
-                anyhow::anyhow!("Feishu channel not configured in settings.json")
-            })?
-            .clone();
-        let fe = frontend::feishu::FeishuFrontend::new(feishu);
-
-        // Run frontend until either it finishes or shutdown RPC is received
+        // Resolve before an auto-update replaces the file on disk
+        let exe = std::env::current_exe()?;
+        let mut frontends: Vec<Box<dyn Frontend>> = Vec::new();
+        if let Some(feishu) = config.feishu_config() {
+            frontends.push(Box::new(frontend::feishu::FeishuFrontend::new(feishu.clone())));
+        }
+        if let Some(slack) = config.slack_config() {
+            frontends.push(Box::new(frontend::slack::SlackFrontend::new(slack.clone())));
+        }
+        if let Some(webhook) = config.webhook_config() {
+            frontends.push(Box::new(frontend::webhook::WebhookFrontend::new(webhook.clone())));
+        }
+        if let Some(discord) = config.discord_config() {
+            frontends.push(Box::new(frontend::discord::DiscordFrontend::new(discord.clone())));
+        }
+        if let Some(telegram) = config.telegram_config() {
+            frontends.push(Box::new(frontend::telegram::TelegramFrontend::new(telegram.clone())));
+        }
+        if frontends.is_empty() {
+            anyhow::bail!(
+                "No channel configured in settings.json (feishu, slack, webhook, discord or telegram)"
+            );
+        }
+        if let Some(recorder) = &recorder {
+            frontends = frontends
+                .into_iter()
+                .map(|fe| with_recording(fe, recorder.clone()))
+                .collect();
+        }
+
+        // Run frontends until they all finish or shutdown RPC is received
+        let drain_timeout = std::time::Duration::from_secs(config.shutdown_timeout_secs);
+        let fe_run = async {
+            futures_util::future::try_join_all(
+                frontends.into_iter().map(|fe| fe.run(manager.clone())),
+            )
+            .await
+            .map(|_| ())
+        };
+        tokio::pin!(fe_run);
+        let auto_update = async {
+            if config.auto_update {
+                cmd_update::run_auto_update(config.update_channel.clone(), config.audit_log).await
+            } else {
+                std::future::pending().await
+            }
+        };
+        let mut restart = false;
         tokio::select! {
-            result = Box::new(fe).run(manager) => {
+            result = &mut fe_run => {
                 daemon::remove_pid_file();
-                result
+                return result;
             }
             _ = shutdown_rx.recv() => {
-                info!("Shutdown signal received via RPC");
-                daemon::remove_pid_file();
+                info!("Shutdown signal received via RPC, draining threads");
+                manager.start_draining();
+            }
+            version = auto_update => {
+                info!("Updated to v{version}, draining threads before restart");
+                manager.set_shutdown_notice(format!("myagent updated to v{version}, reconnecting..."));
+                restart = true;
+                manager.start_draining();
+            }
+        }
+
+        // The frontend exits once in-flight threads finish or the drain times out.
+        // Allow a little extra time for final card updates.
+        let grace = drain_timeout + std::time::Duration::from_secs(5);
+        let result = match tokio::time::timeout(grace, fe_run).await {
+            Ok(result) => result,
+            Err(_) => {
+                warn!("Frontend did not finish draining in time");
                 Ok(())
             }
+        };
+        daemon::remove_pid_file();
+        if restart {
+            return daemon::restart_in_place(&exe);
         }
+        result
     } else {
         let agent_type = cli
             .agent
             .unwrap_or_else(|| config.default_agent.clone());
+        let mut prompt = cli.prompt;
+        if prompt.is_none() && cli.command.is_none() && !std::io::stdin().is_terminal() {
+            let mut input = String::new();
+            tokio::io::stdin().read_to_string(&mut input).await?;
+            if input.trim().is_empty() {
+                anyhow::bail!("No prompt given: stdin is empty (use -p or pipe a prompt)");
+            }
+            prompt = Some(input);
+        }
+        if cli.timeout.is_some() && prompt.is_none() {
+            anyhow::bail!("--timeout only applies to one-shot mode (-p or piped stdin)");
+        }
         let fe = frontend::cli::CliFrontend {
-            prompt: cli.prompt,
+            prompt,
             agent_type,
             update_hint,
+            history: !cli.no_history,
+            highlight: cli.highlight,
+            chat: matches!(cli.command, Some(Commands::Chat)),
+            aliases: config.aliases.clone(),
+            verbose: cli.verbose,
+            timeout: cli.timeout.map(Duration::from_secs),
+            show_cost: cli.show_cost,
         };
-        Box::new(fe).run(manager).await
+        let mut fe: Box<dyn Frontend> = Box::new(fe);
+        if let Some(recorder) = recorder {
+            fe = with_recording(fe, recorder);
+        }
+        fe.run(manager).await
     }
 }
+
+fn with_recording(
+    fe: Box<dyn Frontend>,
+    recorder: Arc<frontend::recording::EventRecorder>,
+) -> Box<dyn Frontend> {
+    Box::new(frontend::recording::RecordingFrontend::new(fe, recorder))
+}
@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Process-wide counters exposed on the health server's `/metrics` route.
+#[derive(Default)]
+pub struct Metrics {
+    requests: LabeledCounter,
+    tool_calls: LabeledCounter,
+    api_errors: AtomicU64,
+}
+
+/// A counter keyed by a single label value (e.g. agent or tool name).
+#[derive(Default)]
+struct LabeledCounter {
+    values: Mutex<BTreeMap<String, AtomicU64>>,
+}
+
+impl LabeledCounter {
+    fn inc(&self, label: &str) {
+        let mut values = self.values.lock().unwrap();
+        values
+            .entry(label.to_string())
+            .or_default()
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> Vec<(String, u64)> {
+        self.values
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(k, v)| (k.clone(), v.load(Ordering::Relaxed)))
+            .collect()
+    }
+}
+
+impl Metrics {
+    pub fn inc_requests(&self, agent: &str) {
+        self.requests.inc(agent);
+    }
+
+    pub fn inc_tool_calls(&self, tool: &str) {
+        self.tool_calls.inc(tool);
+    }
+
+    pub fn inc_api_errors(&self) {
+        self.api_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Render all metrics in the Prometheus text exposition format (v0.0.4).
+    pub fn render(&self, active_threads: usize, uptime_secs: u64) -> String {
+        let mut out = String::new();
+
+        write_header(
+            &mut out,
+            "myagent_active_threads",
+            "gauge",
+            "Number of running agent threads.",
+        );
+        let _ = writeln!(out, "myagent_active_threads {active_threads}");
+
+        write_header(
+            &mut out,
+            "myagent_uptime_seconds",
+            "gauge",
+            "Seconds since the daemon started.",
+        );
+        let _ = writeln!(out, "myagent_uptime_seconds {uptime_secs}");
+
+        write_header(
+            &mut out,
+            "myagent_requests_total",
+            "counter",
+            "Agent threads created, by agent type.",
+        );
+        for (agent, n) in self.requests.snapshot() {
+            let _ = writeln!(
+                out,
+                "myagent_requests_total{{agent=\"{}\"}} {n}",
+                escape_label(&agent)
+            );
+        }
+
+        write_header(
+            &mut out,
+            "myagent_tool_calls_total",
+            "counter",
+            "Tool calls executed, by tool name.",
+        );
+        for (tool, n) in self.tool_calls.snapshot() {
+            let _ = writeln!(
+                out,
+                "myagent_tool_calls_total{{tool=\"{}\"}} {n}",
+                escape_label(&tool)
+            );
+        }
+
+        write_header(
+            &mut out,
+            "myagent_api_errors_total",
+            "counter",
+            "Failed AI API turns.",
+        );
+        let _ = writeln!(
+            out,
+            "myagent_api_errors_total {}",
+            self.api_errors.load(Ordering::Relaxed)
+        );
+
+        out
+    }
+}
+
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
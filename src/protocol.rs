@@ -50,17 +50,23 @@ pub enum ContentBlock {
 
 // ── SQ: Submission Queue (frontend → agent) ──
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Submission {
     UserMessage(String),
     FollowUp(String),
+    /// Extra system prompt text for the next message only.
+    Configure {
+        system_suffix: String,
+    },
     Cancel,
     Shutdown,
 }
 
 // ── EQ: Event Queue (agent → frontend, Anthropic SSE streaming format) ──
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum AgentEvent {
     // Anthropic streaming events
     ContentBlockStart {
@@ -82,14 +88,48 @@ pub enum AgentEvent {
         stop_reason: Option<String>,
     },
     MessageStop,
+    /// Tokens used by one API response (myagent) or a whole run (claude).
+    Usage {
+        input_tokens: u64,
+        output_tokens: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cost_usd: Option<f64>,
+    },
+    /// A tool call began executing (after its `ToolUse` block was streamed).
+    ToolStart {
+        name: String,
+        input_summary: String,
+    },
+    /// A tool call finished executing.
+    ToolEnd {
+        name: String,
+        duration_ms: u64,
+        is_error: bool,
+    },
+    /// What a finished tool call returned to the model, sent after `ToolEnd`.
+    ToolResult {
+        tool_name: String,
+        /// The first 200 characters of the output (or error).
+        output_preview: String,
+        /// Lines in the full output.
+        line_count: usize,
+        is_error: bool,
+    },
     // Agent lifecycle events
     StatusChange(AgentStatus),
+    /// The API returned 429; the agent retries after the given delay.
+    RateLimited {
+        retry_after_ms: u64,
+    },
     Error(String),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum AgentStatus {
     Starting,
+    /// Re-created after a restart with an empty history; waits for a follow-up.
+    Resumed,
     Working,
     Idle,
     Completed,
@@ -104,6 +144,32 @@ impl AgentStatus {
             Self::Completed | Self::Failed(_) | Self::Cancelled
         )
     }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Starting => "starting",
+            Self::Resumed => "resumed",
+            Self::Working => "working",
+            Self::Idle => "idle",
+            Self::Completed => "completed",
+            Self::Failed(_) => "failed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+// ── Monitor events (thread manager → health `/events` clients) ──
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type")]
+pub enum MonitorEvent {
+    #[serde(rename = "thread_created")]
+    Created { thread_id: String, agent: String },
+    #[serde(rename = "thread_status")]
+    Status { thread_id: String, status: String },
+    /// The agent task exited.
+    #[serde(rename = "thread_completed")]
+    Completed { thread_id: String },
 }
 
 // ── Helper functions ──
@@ -1,10 +1,12 @@
 use std::sync::Arc;
 
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{broadcast, mpsc, Mutex};
+use tokio::task::JoinHandle;
 use tracing::info;
 
 use crate::agent::Agent;
-use crate::protocol::{AgentEvent, Submission, ThreadId};
+use crate::frontend::recording::EventRecorder;
+use crate::protocol::{AgentEvent, MonitorEvent, Submission, ThreadId};
 
 const SQ_CAPACITY: usize = 64;
 const EQ_CAPACITY: usize = 512;
@@ -15,22 +17,35 @@ pub struct AgentThread {
     pub agent_name: String,
     tx_sub: mpsc::Sender<Submission>,
     rx_event: Mutex<mpsc::Receiver<AgentEvent>>,
+    task: JoinHandle<()>,
+    recorder: Option<Arc<EventRecorder>>,
+    monitor: broadcast::Sender<MonitorEvent>,
 }
 
 impl AgentThread {
     /// Spawn a new agent thread. Creates channels, spawns the agent
     /// as a tokio task, and returns the AgentThread handle.
-    pub fn spawn(thread_id: ThreadId, agent: Box<dyn Agent>) -> Arc<Self> {
+    /// With a `recorder`, every event read by `next_event` is also recorded.
+    /// Status changes and the agent's exit are published on `monitor`.
+    pub fn spawn(
+        thread_id: ThreadId,
+        agent: Box<dyn Agent>,
+        recorder: Option<Arc<EventRecorder>>,
+        monitor: broadcast::Sender<MonitorEvent>,
+    ) -> Arc<Self> {
         let agent_name = agent.name().to_string();
         let (tx_sub, rx_sub) = mpsc::channel::<Submission>(SQ_CAPACITY);
         let (tx_event, rx_event) = mpsc::channel::<AgentEvent>(EQ_CAPACITY);
 
         let tid = thread_id.clone();
         let name = agent_name.clone();
-        tokio::spawn(async move {
+        let task_monitor = monitor.clone();
+        let task = tokio::spawn(async move {
             info!("[{tid}] Agent '{name}' started");
             agent.run(rx_sub, tx_event).await;
             info!("[{tid}] Agent '{name}' stopped");
+            // No receivers is fine: nobody is watching
+            let _ = task_monitor.send(MonitorEvent::Completed { thread_id: tid.0 });
         });
 
         Arc::new(Self {
@@ -38,6 +53,9 @@ impl AgentThread {
             agent_name,
             tx_sub,
             rx_event: Mutex::new(rx_event),
+            task,
+            recorder,
+            monitor,
         })
     }
 
@@ -49,9 +67,24 @@ impl AgentThread {
             .map_err(|_| anyhow::anyhow!("Agent thread closed"))
     }
 
+    /// Whether the agent task has exited.
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
     /// Receive the next event from the agent (EQ).
     /// Returns None when the agent has finished.
     pub async fn next_event(&self) -> Option<AgentEvent> {
-        self.rx_event.lock().await.recv().await
+        let event = self.rx_event.lock().await.recv().await;
+        if let (Some(recorder), Some(event)) = (&self.recorder, &event) {
+            recorder.record(&self.thread_id, &self.agent_name, event);
+        }
+        if let Some(AgentEvent::StatusChange(status)) = &event {
+            let _ = self.monitor.send(MonitorEvent::Status {
+                thread_id: self.thread_id.0.clone(),
+                status: status.as_str().to_string(),
+            });
+        }
+        event
     }
 }
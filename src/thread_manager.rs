@@ -1,57 +1,214 @@
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
-use tokio::sync::RwLock;
+use chrono::{DateTime, Utc};
+use tokio::sync::{broadcast, Notify, RwLock};
 use tracing::info;
 
+use crate::audit::AuditLogger;
 use crate::config::AppConfig;
-use crate::protocol::ThreadId;
+use crate::frontend::recording::EventRecorder;
+use crate::metrics::Metrics;
+use crate::protocol::{AgentStatus, MonitorEvent, Submission, ThreadId};
 use crate::thread::AgentThread;
+use crate::tools::{ToolStats, ToolStatsMap};
+
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Monitor events buffered for slow `/events` clients before they lag.
+const MONITOR_CAPACITY: usize = 256;
+
+/// Per-thread settings that take precedence over the agent's config.
+#[derive(Debug, Clone, Default)]
+pub struct ThreadOverrides {
+    pub model: Option<String>,
+    pub max_tokens: Option<u32>,
+    /// Appended to the system prompt.
+    pub system_prompt_suffix: Option<String>,
+    /// Set on threads created by `delegate_to_agent`, which can't delegate
+    /// further.
+    pub delegated: bool,
+    /// Feishu chat_id the thread serves.
+    pub conv_id: Option<String>,
+    /// Feishu open_id of the user who started the thread.
+    pub user_id: Option<String>,
+}
+
+/// Who and where a thread is serving, added to the agent's system prompt.
+#[derive(Debug, Clone, Default)]
+pub struct ThreadContext {
+    pub thread_id: String,
+    /// Feishu chat_id.
+    pub conv_id: Option<String>,
+    /// Feishu open_id of the user who started the thread.
+    pub user_id: Option<String>,
+}
+
+impl ThreadContext {
+    /// Render as a `<context>` tag for the system prompt.
+    pub fn to_prompt(&self) -> String {
+        let Some(chat_id) = &self.conv_id else {
+            return format!("<context thread_id=\"{}\"/>", self.thread_id);
+        };
+        let user = self
+            .user_id
+            .as_ref()
+            .map(|id| format!(" user_id=\"{id}\""))
+            .unwrap_or_default();
+        format!(
+            "<context thread_id=\"{}\">\n<feishu_context chat_id=\"{chat_id}\"{user}/>\n</context>",
+            self.thread_id
+        )
+    }
+}
 
 /// Manages all active agent threads.
 pub struct ThreadManager {
     threads: Arc<RwLock<HashMap<ThreadId, Arc<AgentThread>>>>,
-    config: AppConfig,
+    config: Arc<RwLock<AppConfig>>,
     workspace: String,
+    draining: AtomicBool,
+    drain_notify: Notify,
+    last_activity: Mutex<Option<DateTime<Utc>>>,
+    metrics: Arc<Metrics>,
+    recorder: OnceLock<Arc<EventRecorder>>,
+    /// Sent to chats by frontends that announce a shutdown (e.g. an update).
+    shutdown_notice: Mutex<Option<String>>,
+    /// Thread lifecycle events for the health server's `/events` endpoint.
+    monitor: broadcast::Sender<MonitorEvent>,
+    tool_stats: ToolStatsMap,
 }
 
 impl ThreadManager {
-    pub fn new(config: AppConfig, workspace: String) -> Self {
+    /// `config` is shared with the health server so a reload is picked up
+    /// by threads created afterwards.
+    pub fn new(config: Arc<RwLock<AppConfig>>, workspace: String) -> Self {
         Self {
             threads: Arc::new(RwLock::new(HashMap::new())),
             config,
             workspace,
+            draining: AtomicBool::new(false),
+            drain_notify: Notify::new(),
+            last_activity: Mutex::new(None),
+            metrics: Arc::new(Metrics::default()),
+            recorder: OnceLock::new(),
+            shutdown_notice: Mutex::new(None),
+            monitor: broadcast::channel(MONITOR_CAPACITY).0,
+            tool_stats: ToolStatsMap::default(),
         }
     }
 
     /// Create a new thread with the given agent type.
+    ///
+    /// `conv_id` identifies the Feishu chat the thread serves; with
+    /// `per_chat_workspace` enabled the thread works in `<workspace>/<conv_id>/`.
+    /// `conv_id` and `user_id` are passed to the agent as [`ThreadContext`].
+    /// `overrides` only apply to the myagent agent.
     pub async fn create_thread(
-        &self,
+        self: &Arc<Self>,
         agent_type: &str,
+        overrides: ThreadOverrides,
     ) -> Result<(ThreadId, Arc<AgentThread>)> {
         let thread_id = ThreadId::new();
+        let thread = self.spawn_thread(thread_id.clone(), agent_type, overrides).await?;
+        let agent_label = if agent_type == "claude" { "claude" } else { "myagent" };
+        self.metrics.inc_requests(agent_label);
+        Ok((thread_id, thread))
+    }
+
+    /// Re-create a thread saved before a restart under its old ID. The agent
+    /// starts with an empty history and waits for a follow-up.
+    pub async fn resume_thread(
+        self: &Arc<Self>,
+        thread_id: ThreadId,
+        agent_type: &str,
+        overrides: ThreadOverrides,
+    ) -> Result<Arc<AgentThread>> {
+        info!("[{thread_id}] Resuming {agent_type} thread");
+        let thread = self.spawn_thread(thread_id.clone(), agent_type, overrides).await?;
+        let _ = self.monitor.send(MonitorEvent::Status {
+            thread_id: thread_id.0,
+            status: AgentStatus::Resumed.as_str().to_string(),
+        });
+        Ok(thread)
+    }
+
+    async fn spawn_thread(
+        self: &Arc<Self>,
+        thread_id: ThreadId,
+        agent_type: &str,
+        overrides: ThreadOverrides,
+    ) -> Result<Arc<AgentThread>> {
+        if self.is_draining() {
+            anyhow::bail!("Shutting down, not accepting new threads");
+        }
+        let config = self.config.read().await;
+        let per_chat = config.feishu_config().is_some_and(|f| f.per_chat_workspace);
+        let workspace = match overrides.conv_id.as_deref().filter(|_| per_chat) {
+            Some(id) => {
+                let dir = Path::new(&self.workspace).join(sanitize_dir_name(id));
+                std::fs::create_dir_all(&dir).map_err(|e| {
+                    anyhow::anyhow!("Failed to create workspace {}: {e}", dir.display())
+                })?;
+                dir.to_string_lossy().to_string()
+            }
+            None => self.workspace.clone(),
+        };
+        let context = ThreadContext {
+            thread_id: thread_id.0.clone(),
+            conv_id: overrides.conv_id.clone(),
+            user_id: overrides.user_id.clone(),
+        };
+        // Delegated threads can't reach the manager, so they can't delegate again
+        let manager = (!overrides.delegated).then(|| Arc::downgrade(self));
         let agent: Box<dyn crate::agent::Agent> = match agent_type {
             "claude" => Box::new(crate::agent::claude::ClaudeAgent::new(
-                self.config.claude_env(),
-                self.workspace.clone(),
-                self.config.feishu_config().is_some(),
+                config.claude_env(),
+                workspace,
+                config.feishu_config().is_some(),
+                context,
             )),
             _ => Box::new(crate::agent::ai::AiAgent::new(
-                self.config.myagent_env(),
-                self.workspace.clone(),
-                self.config.feishu_config().is_some(),
-            )),
+                config.myagent_env(),
+                workspace,
+                config.feishu_config().is_some(),
+                self.metrics.clone(),
+                overrides,
+                &config.extra_path(),
+                config
+                    .audit_log
+                    .then(|| Arc::new(AuditLogger::new(&thread_id.0))),
+            )
+            .with_context(context)
+            .with_tool_stats(self.tool_stats.clone())
+            .with_allowed_hosts(config.allowed_hosts.clone().unwrap_or_default())
+            .with_clipboard(config.allow_clipboard_tool)
+            .with_manager(manager)
+            .with_http_client(config.api_http_client()?)),
         };
+        drop(config);
 
         info!("[{thread_id}] Creating {agent_type} thread");
-        let thread = AgentThread::spawn(thread_id.clone(), agent);
+        let thread = AgentThread::spawn(
+            thread_id.clone(),
+            agent,
+            self.recorder.get().cloned(),
+            self.monitor.clone(),
+        );
+        let _ = self.monitor.send(MonitorEvent::Created {
+            thread_id: thread_id.0.clone(),
+            agent: thread.agent_name.clone(),
+        });
         self.threads
             .write()
             .await
-            .insert(thread_id.clone(), thread.clone());
+            .insert(thread_id, thread.clone());
 
-        Ok((thread_id, thread))
+        Ok(thread)
     }
 
     /// Get an existing thread by ID.
@@ -59,16 +216,124 @@ impl ThreadManager {
         self.threads.read().await.get(id).cloned()
     }
 
+    /// Threads whose agent task is still running.
+    pub async fn active_threads(&self) -> Vec<Arc<AgentThread>> {
+        self.threads
+            .read()
+            .await
+            .values()
+            .filter(|t| !t.is_finished())
+            .cloned()
+            .collect()
+    }
+
+    /// Record the events of threads created from now on. Only the first
+    /// recorder set is used.
+    pub fn set_recorder(&self, recorder: Arc<EventRecorder>) {
+        let _ = self.recorder.set(recorder);
+    }
+
+    /// Record that a message or RPC was received.
+    pub fn record_activity(&self) {
+        *self.last_activity.lock().unwrap() = Some(Utc::now());
+    }
+
+    /// When the last message or RPC was received, if any.
+    pub fn last_activity(&self) -> Option<DateTime<Utc>> {
+        *self.last_activity.lock().unwrap()
+    }
+
     /// Remove a completed thread.
     pub async fn remove_thread(&self, id: &ThreadId) {
         self.threads.write().await.remove(id);
     }
 
-    pub fn config(&self) -> &AppConfig {
-        &self.config
+    /// Stop accepting new threads and wake anyone waiting in `wait_draining`.
+    pub fn start_draining(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+        self.drain_notify.notify_waiters();
+    }
+
+    /// Set the message frontends send to their chats once draining finishes.
+    pub fn set_shutdown_notice(&self, notice: String) {
+        *self.shutdown_notice.lock().unwrap() = Some(notice);
+    }
+
+    pub fn shutdown_notice(&self) -> Option<String> {
+        self.shutdown_notice.lock().unwrap().clone()
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// Wait until `start_draining` has been called.
+    pub async fn wait_draining(&self) {
+        loop {
+            let notified = self.drain_notify.notified();
+            if self.is_draining() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Ask every thread to shut down after its current turn, then wait up to
+    /// `timeout` for them to exit. Returns the threads still running.
+    pub async fn drain(&self, timeout: Duration) -> Vec<ThreadId> {
+        self.start_draining();
+        let threads: Vec<Arc<AgentThread>> =
+            self.threads.read().await.values().cloned().collect();
+        info!("Draining {} thread(s)", threads.len());
+        for thread in &threads {
+            let _ = thread.submit(Submission::Shutdown).await;
+        }
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let running: Vec<ThreadId> = threads
+                .iter()
+                .filter(|t| !t.is_finished())
+                .map(|t| t.thread_id.clone())
+                .collect();
+            if running.is_empty() || Instant::now() >= deadline {
+                return running;
+            }
+            tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+        }
+    }
+
+    pub fn metrics(&self) -> &Arc<Metrics> {
+        &self.metrics
+    }
+
+    /// Per-tool call counts and durations since startup or the last reload.
+    pub fn tool_stats(&self) -> HashMap<String, ToolStats> {
+        self.tool_stats.lock().unwrap().clone()
+    }
+
+    pub fn reset_tool_stats(&self) {
+        self.tool_stats.lock().unwrap().clear();
+    }
+
+    /// Sender of thread lifecycle events; subscribe to watch them.
+    pub fn monitor(&self) -> &broadcast::Sender<MonitorEvent> {
+        &self.monitor
+    }
+
+    /// Snapshot of the current config.
+    pub async fn config(&self) -> AppConfig {
+        self.config.read().await.clone()
     }
 
     pub fn workspace(&self) -> &str {
         &self.workspace
     }
 }
+
+/// Keep only characters that are safe in a single path component.
+fn sanitize_dir_name(id: &str) -> String {
+    id.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
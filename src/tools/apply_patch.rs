@@ -1,15 +1,24 @@
+use std::collections::HashMap;
 use std::path::Path;
 
 use anyhow::{bail, Result};
 
 /// Patch hunk types matching Codex's apply_patch format.
 enum PatchHunk {
-    AddFile { path: String, contents: String },
+    AddFile {
+        path: String,
+        contents: String,
+        chmod: Option<u32>,
+        /// Replace the file if it exists (`*** Overwrite: true`).
+        overwrite: bool,
+    },
     DeleteFile { path: String },
+    RenameFile { from: String, to: String },
     UpdateFile {
         path: String,
         move_to: Option<String>,
         chunks: Vec<UpdateChunk>,
+        chmod: Option<u32>,
     },
 }
 
@@ -49,6 +58,7 @@ fn parse_patch(input: &str) -> Result<Vec<PatchHunk>> {
             break;
         } else if let Some(path) = line.strip_prefix("*** Add File: ") {
             i += 1;
+            let overwrite = parse_overwrite(&lines, &mut i)?;
             let mut contents = String::new();
             while i < lines.len() && lines[i].starts_with('+') {
                 if !contents.is_empty() {
@@ -57,15 +67,27 @@ fn parse_patch(input: &str) -> Result<Vec<PatchHunk>> {
                 contents.push_str(&lines[i][1..]);
                 i += 1;
             }
+            let chmod = parse_chmod(&lines, &mut i)?;
             hunks.push(PatchHunk::AddFile {
                 path: path.trim().to_string(),
                 contents,
+                chmod,
+                overwrite,
             });
         } else if let Some(path) = line.strip_prefix("*** Delete File: ") {
             hunks.push(PatchHunk::DeleteFile {
                 path: path.trim().to_string(),
             });
             i += 1;
+        } else if let Some(spec) = line.strip_prefix("*** Rename File: ") {
+            let Some((from, to)) = spec.split_once(" -> ") else {
+                bail!("Invalid rename '{}': expected '*** Rename File: from -> to'", spec.trim());
+            };
+            hunks.push(PatchHunk::RenameFile {
+                from: from.trim().to_string(),
+                to: to.trim().to_string(),
+            });
+            i += 1;
         } else if let Some(path) = line.strip_prefix("*** Update File: ") {
             let path = path.trim().to_string();
             i += 1;
@@ -97,9 +119,11 @@ fn parse_patch(input: &str) -> Result<Vec<PatchHunk>> {
                     let mut new_lines = Vec::new();
                     let mut is_end_of_file = false;
 
+                    // The end-of-file marker belongs to this chunk; any other
+                    // `*** ` line starts the next hunk
                     while i < lines.len()
                         && !lines[i].starts_with("@@")
-                        && !lines[i].starts_with("*** ")
+                        && (!lines[i].starts_with("*** ") || lines[i].trim() == "*** End of File")
                     {
                         let l = lines[i];
                         if l.trim() == "*** End of File" {
@@ -132,10 +156,12 @@ fn parse_patch(input: &str) -> Result<Vec<PatchHunk>> {
                 }
             }
 
+            let chmod = parse_chmod(&lines, &mut i)?;
             hunks.push(PatchHunk::UpdateFile {
                 path,
                 move_to,
                 chunks,
+                chmod,
             });
         } else {
             i += 1;
@@ -148,74 +174,298 @@ fn parse_patch(input: &str) -> Result<Vec<PatchHunk>> {
     Ok(hunks)
 }
 
+/// Consume an optional `*** Chmod: <octal>` line following a file block.
+fn parse_chmod(lines: &[&str], i: &mut usize) -> Result<Option<u32>> {
+    let Some(mode) = lines.get(*i).and_then(|l| l.strip_prefix("*** Chmod: ")) else {
+        return Ok(None);
+    };
+    let mode = mode.trim();
+    let parsed = u32::from_str_radix(mode, 8)
+        .ok()
+        .filter(|m| *m <= 0o7777)
+        .ok_or_else(|| anyhow::anyhow!("Invalid chmod mode '{mode}'"))?;
+    *i += 1;
+    Ok(Some(parsed))
+}
+
+/// Consume an optional `*** Overwrite: true|false` line following `*** Add File:`.
+fn parse_overwrite(lines: &[&str], i: &mut usize) -> Result<bool> {
+    let Some(value) = lines.get(*i).and_then(|l| l.strip_prefix("*** Overwrite: ")) else {
+        return Ok(false);
+    };
+    let overwrite = match value.trim() {
+        "true" => true,
+        "false" => false,
+        other => bail!("Invalid overwrite value '{other}': expected true or false"),
+    };
+    *i += 1;
+    Ok(overwrite)
+}
+
 // --- Applier (matches Codex logic) ---
 
+/// A hunk that passed validation, with its final file contents computed.
+struct AppliedHunk {
+    summary: String,
+    op: FileOp,
+}
+
+enum FileOp {
+    Write {
+        path: String,
+        content: String,
+        chmod: Option<u32>,
+    },
+    Delete {
+        path: String,
+    },
+    Rename {
+        from: String,
+        to: String,
+    },
+    Move {
+        from: String,
+        to: String,
+        content: String,
+        chmod: Option<u32>,
+    },
+}
+
 fn apply_hunks(hunks: &[PatchHunk], work_dir: &str) -> Result<String> {
-    let mut summary = Vec::new();
+    let applied = validate_hunks(hunks, work_dir)?;
+    write_hunks(&applied, work_dir)?;
+    Ok(applied
+        .iter()
+        .map(|a| a.summary.as_str())
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Check every hunk against in-memory copies of the files without touching
+/// the disk. Fails with all hunk errors combined if any hunk doesn't apply.
+fn validate_hunks(hunks: &[PatchHunk], work_dir: &str) -> Result<Vec<AppliedHunk>> {
+    // Contents after the hunks so far; `None` marks a file deleted by the patch
+    let mut files: HashMap<String, Option<String>> = HashMap::new();
+    let mut applied = Vec::new();
+    let mut errors = Vec::new();
 
     for hunk in hunks {
-        match hunk {
-            PatchHunk::AddFile { path, contents } => {
+        match validate_hunk(hunk, work_dir, &mut files) {
+            Ok(a) => applied.push(a),
+            Err(e) => errors.push(e.to_string()),
+        }
+    }
+
+    if !errors.is_empty() {
+        bail!(
+            "Patch not applied, {} of {} hunk(s) failed:\n{}",
+            errors.len(),
+            hunks.len(),
+            errors.join("\n")
+        );
+    }
+    Ok(applied)
+}
+
+fn validate_hunk(
+    hunk: &PatchHunk,
+    work_dir: &str,
+    files: &mut HashMap<String, Option<String>>,
+) -> Result<AppliedHunk> {
+    match hunk {
+        PatchHunk::AddFile {
+            path,
+            contents,
+            chmod,
+            overwrite,
+        } => {
+            let full = resolve_path(work_dir, path);
+            let exists = file_exists(files, &full);
+            if exists && !overwrite {
+                bail!("File already exists: {path}. Use '*** Overwrite: true' to replace.");
+            }
+            files.insert(full, Some(contents.clone()));
+            Ok(AppliedHunk {
+                summary: if exists {
+                    format!("Overwrote {path}")
+                } else {
+                    format!("Created {path}")
+                },
+                op: FileOp::Write {
+                    path: path.clone(),
+                    content: contents.clone(),
+                    chmod: *chmod,
+                },
+            })
+        }
+        PatchHunk::DeleteFile { path } => {
+            let full = resolve_path(work_dir, path);
+            if !file_exists(files, &full) {
+                bail!("Failed to delete {path}: file not found");
+            }
+            files.insert(full, None);
+            Ok(AppliedHunk {
+                summary: format!("Deleted {path}"),
+                op: FileOp::Delete { path: path.clone() },
+            })
+        }
+        PatchHunk::RenameFile { from, to } => {
+            let from_full = resolve_path(work_dir, from);
+            let to_full = resolve_path(work_dir, to);
+            if !file_exists(files, &from_full) {
+                bail!("Failed to rename {from}: file not found");
+            }
+            if file_exists(files, &to_full) {
+                bail!("Failed to rename {from}: {to} already exists");
+            }
+            // Carry the contents over so later hunks can edit the new path
+            let content = match files.remove(&from_full) {
+                Some(current) => current,
+                None => std::fs::read_to_string(&from_full).ok(),
+            };
+            files.insert(from_full, None);
+            if let Some(content) = content {
+                files.insert(to_full, Some(content));
+            }
+            Ok(AppliedHunk {
+                summary: format!("Renamed {from} → {to}"),
+                op: FileOp::Rename {
+                    from: from.clone(),
+                    to: to.clone(),
+                },
+            })
+        }
+        PatchHunk::UpdateFile {
+            path,
+            move_to,
+            chunks,
+            chmod,
+        } => {
+            let full = resolve_path(work_dir, path);
+            let content = match files.get(&full) {
+                Some(Some(current)) => current.clone(),
+                Some(None) => bail!("Failed to read {path}: deleted earlier in this patch"),
+                None => std::fs::read_to_string(&full)
+                    .map_err(|e| anyhow::anyhow!("Failed to read {path}: {e}"))?,
+            };
+
+            // Split by \n (not .lines()) to match Codex behavior
+            let mut file_lines: Vec<String> =
+                content.split('\n').map(String::from).collect();
+
+            // Drop trailing empty element from final newline
+            if file_lines.last().is_some_and(String::is_empty) {
+                file_lines.pop();
+            }
+
+            let replacements = compute_replacements(&file_lines, path, chunks)?;
+            let mut new_lines = apply_replacements(file_lines, &replacements);
+
+            // Ensure trailing newline
+            if !new_lines.last().is_some_and(String::is_empty) {
+                new_lines.push(String::new());
+            }
+            let new_content = new_lines.join("\n");
+
+            if let Some(dest) = move_to {
+                files.insert(full, None);
+                files.insert(resolve_path(work_dir, dest), Some(new_content.clone()));
+                Ok(AppliedHunk {
+                    summary: format!("Moved {path} → {dest}"),
+                    op: FileOp::Move {
+                        from: path.clone(),
+                        to: dest.clone(),
+                        content: new_content,
+                        chmod: *chmod,
+                    },
+                })
+            } else {
+                files.insert(full, Some(new_content.clone()));
+                Ok(AppliedHunk {
+                    summary: format!("Updated {path} ({} chunks applied)", chunks.len()),
+                    op: FileOp::Write {
+                        path: path.clone(),
+                        content: new_content,
+                        chmod: *chmod,
+                    },
+                })
+            }
+        }
+    }
+}
+
+/// Whether `full` exists once the hunks validated so far are applied.
+fn file_exists(files: &HashMap<String, Option<String>>, full: &str) -> bool {
+    match files.get(full) {
+        Some(current) => current.is_some(),
+        None => Path::new(full).exists(),
+    }
+}
+
+/// Write validated hunks to disk in patch order.
+fn write_hunks(applied: &[AppliedHunk], work_dir: &str) -> Result<()> {
+    for hunk in applied {
+        match &hunk.op {
+            FileOp::Write {
+                path,
+                content,
+                chmod,
+            } => {
                 let full = resolve_path(work_dir, path);
                 if let Some(parent) = Path::new(&full).parent() {
                     std::fs::create_dir_all(parent)?;
                 }
-                std::fs::write(&full, contents)?;
-                summary.push(format!("Created {path}"));
+                std::fs::write(&full, content)?;
+                set_mode(&full, *chmod)?;
             }
-            PatchHunk::DeleteFile { path } => {
+            FileOp::Delete { path } => {
                 let full = resolve_path(work_dir, path);
                 std::fs::remove_file(&full)
                     .map_err(|e| anyhow::anyhow!("Failed to delete {path}: {e}"))?;
-                summary.push(format!("Deleted {path}"));
             }
-            PatchHunk::UpdateFile {
-                path,
-                move_to,
-                chunks,
-            } => {
-                let full = resolve_path(work_dir, path);
-                let content = std::fs::read_to_string(&full)
-                    .map_err(|e| anyhow::anyhow!("Failed to read {path}: {e}"))?;
-
-                // Split by \n (not .lines()) to match Codex behavior
-                let mut file_lines: Vec<String> =
-                    content.split('\n').map(String::from).collect();
-
-                // Drop trailing empty element from final newline
-                if file_lines.last().is_some_and(String::is_empty) {
-                    file_lines.pop();
+            FileOp::Rename { from, to } => {
+                let from_full = resolve_path(work_dir, from);
+                let to_full = resolve_path(work_dir, to);
+                if Path::new(&to_full).exists() {
+                    bail!("Failed to rename {from}: {to} already exists");
                 }
-
-                let replacements = compute_replacements(&file_lines, path, chunks)?;
-                let mut new_lines = apply_replacements(file_lines, &replacements);
-
-                // Ensure trailing newline
-                if !new_lines.last().is_some_and(String::is_empty) {
-                    new_lines.push(String::new());
+                if let Some(parent) = Path::new(&to_full).parent() {
+                    std::fs::create_dir_all(parent)?;
                 }
-                let new_content = new_lines.join("\n");
-
-                if let Some(dest) = move_to {
-                    let dest_full = resolve_path(work_dir, dest);
-                    if let Some(parent) = Path::new(&dest_full).parent() {
-                        std::fs::create_dir_all(parent)?;
-                    }
-                    std::fs::write(&dest_full, &new_content)?;
-                    std::fs::remove_file(&full)?;
-                    summary.push(format!("Moved {path} → {dest}"));
-                } else {
-                    std::fs::write(&full, &new_content)?;
-                    summary.push(format!(
-                        "Updated {path} ({} chunks applied)",
-                        chunks.len()
-                    ));
+                std::fs::rename(&from_full, &to_full)
+                    .map_err(|e| anyhow::anyhow!("Failed to rename {from}: {e}"))?;
+            }
+            FileOp::Move {
+                from,
+                to,
+                content,
+                chmod,
+            } => {
+                let from_full = resolve_path(work_dir, from);
+                let to_full = resolve_path(work_dir, to);
+                if let Some(parent) = Path::new(&to_full).parent() {
+                    std::fs::create_dir_all(parent)?;
                 }
+                std::fs::write(&to_full, content)?;
+                set_mode(&to_full, *chmod)?;
+                std::fs::remove_file(&from_full)?;
             }
         }
     }
+    Ok(())
+}
 
-    Ok(summary.join("\n"))
+/// Apply a `*** Chmod:` mode. Ignored on platforms without Unix permissions.
+fn set_mode(path: &str, mode: Option<u32>) -> Result<()> {
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+            .map_err(|e| anyhow::anyhow!("Failed to chmod {path}: {e}"))?;
+    }
+    #[cfg(not(unix))]
+    let _ = (path, mode);
+    Ok(())
 }
 
 /// Compute replacements matching Codex's compute_replacements logic.
@@ -317,7 +567,8 @@ fn apply_replacements(
 // --- seek_sequence: 4-level fuzzy matching (matches Codex) ---
 
 /// Find `pattern` lines within `lines` starting at or after `start`.
-/// When `eof` is true, first tries matching from end-of-file.
+/// When `eof` is true, only a match that ends at end-of-file counts, so
+/// identical lines earlier in the file are never picked.
 /// 4 levels: exact → trim_end → trim → Unicode normalization.
 fn seek_sequence(
     lines: &[String],
@@ -332,8 +583,9 @@ fn seek_sequence(
         return None;
     }
 
-    let search_start = if eof && lines.len() >= pattern.len() {
-        lines.len() - pattern.len()
+    // Anchored at the end, but never before lines earlier chunks consumed
+    let search_start = if eof {
+        start.max(lines.len() - pattern.len())
     } else {
         start
     };
@@ -414,3 +666,32 @@ fn resolve_path(work_dir: &str, path: &str) -> String {
         Path::new(work_dir).join(path).to_string_lossy().to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn end_of_file_chunk_replaces_the_last_copy() {
+        let dir = std::env::temp_dir().join(format!("myagent-apply-patch-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("lib.rs");
+        std::fs::write(&file, "fn a() {\n    done();\n}\n\nfn b() {\n    done();\n}\n").unwrap();
+
+        let patch = "*** Begin Patch\n\
+                     *** Update File: lib.rs\n\
+                     @@\n\
+                     -    done();\n\
+                     -}\n\
+                     +    finish();\n\
+                     +}\n\
+                     *** End of File\n\
+                     *** End Patch\n";
+        execute(patch, dir.to_str().unwrap()).await.unwrap();
+
+        let patched = std::fs::read_to_string(&file).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(patched, "fn a() {\n    done();\n}\n\nfn b() {\n    finish();\n}\n");
+    }
+}
@@ -0,0 +1,53 @@
+use std::sync::{Mutex, OnceLock};
+
+use anyhow::Result;
+use arboard::Clipboard;
+
+/// Kept for the life of the process: on X11 and Wayland the clipboard only
+/// holds what was written while its owner is still running.
+static CLIPBOARD: OnceLock<Mutex<Clipboard>> = OnceLock::new();
+
+/// Whether an action only reads the clipboard and can run alongside other
+/// read-only tools.
+pub fn is_read_only(action: &str) -> bool {
+    action == "read"
+}
+
+/// Read the clipboard text, or replace it with `content` for `write`.
+pub async fn execute(action: &str, content: Option<&str>) -> Result<String> {
+    let content = match action {
+        "read" => None,
+        "write" => Some(
+            content
+                .ok_or_else(|| anyhow::anyhow!("clipboard write requires 'content'"))?
+                .to_string(),
+        ),
+        other => anyhow::bail!("Unknown clipboard action: {other} (expected read or write)"),
+    };
+
+    // arboard talks to the display server synchronously
+    tokio::task::spawn_blocking(move || {
+        let clipboard = match CLIPBOARD.get() {
+            Some(clipboard) => clipboard,
+            None => {
+                let clipboard = Clipboard::new()
+                    .map_err(|e| anyhow::anyhow!("Clipboard unavailable: {e}"))?;
+                CLIPBOARD.get_or_init(|| Mutex::new(clipboard))
+            }
+        };
+        let mut clipboard = clipboard.lock().unwrap();
+        match content {
+            None => clipboard
+                .get_text()
+                .map_err(|e| anyhow::anyhow!("Failed to read clipboard: {e}")),
+            Some(text) => {
+                clipboard
+                    .set_text(text.as_str())
+                    .map_err(|e| anyhow::anyhow!("Failed to write clipboard: {e}"))?;
+                Ok(format!("Clipboard set ({} bytes)", text.len()))
+            }
+        }
+    })
+    .await
+    .map_err(|e| anyhow::anyhow!("Clipboard task failed: {e}"))?
+}
@@ -0,0 +1,29 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+/// Create a directory. With `recursive`, missing parents are created too and
+/// an existing directory is not an error.
+pub async fn execute(path: &str, recursive: bool, work_dir: &str) -> Result<String> {
+    let full = if Path::new(path).is_absolute() {
+        Path::new(path).to_path_buf()
+    } else {
+        Path::new(work_dir).join(path)
+    };
+
+    if full.is_dir() {
+        if recursive {
+            return Ok(format!("Directory already exists: {path}"));
+        }
+        anyhow::bail!("Directory already exists: {path}");
+    }
+
+    let result = if recursive {
+        tokio::fs::create_dir_all(&full).await
+    } else {
+        tokio::fs::create_dir(&full).await
+    };
+    result.map_err(|e| anyhow::anyhow!("Failed to create {}: {e}", full.display()))?;
+
+    Ok(format!("Created {path}"))
+}
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio_util::sync::CancellationToken;
+
+use crate::agent::Cancelled;
+use crate::protocol::{AgentEvent, AgentStatus, ContentBlock, Submission, ThreadId};
+use crate::thread::AgentThread;
+use crate::thread_manager::{ThreadManager, ThreadOverrides};
+
+/// How long a delegated task may run before it is abandoned.
+const DELEGATE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Longest text output returned from a delegated task.
+const MAX_OUTPUT_BYTES: usize = 256 * 1024;
+
+/// Stops and removes a delegated thread when dropped, so it doesn't outlive
+/// `execute` when the calling turn is cancelled or times out.
+struct DelegatedThread {
+    manager: Arc<ThreadManager>,
+    thread_id: ThreadId,
+    thread: Arc<AgentThread>,
+}
+
+impl Drop for DelegatedThread {
+    fn drop(&mut self) {
+        let manager = self.manager.clone();
+        let thread_id = self.thread_id.clone();
+        let thread = self.thread.clone();
+        tokio::spawn(async move {
+            // Cancel stops a running turn; Shutdown ends the agent either way
+            let _ = thread.submit(Submission::Cancel).await;
+            let _ = thread.submit(Submission::Shutdown).await;
+            manager.remove_thread(&thread_id).await;
+        });
+    }
+}
+
+/// Run `prompt` on a new `agent_type` thread and wait for its turn to end.
+/// The delegated thread's events are read here instead of by a frontend;
+/// only its text output and a short summary are returned. Fails with
+/// `Cancelled` when `cancel` fires first.
+pub async fn execute(
+    agent_type: &str,
+    prompt: &str,
+    manager: Option<&Arc<ThreadManager>>,
+    cancel: &CancellationToken,
+) -> Result<String> {
+    let manager = manager
+        .ok_or_else(|| anyhow::anyhow!("delegate_to_agent is not available in this thread"))?;
+    if !matches!(agent_type, "claude" | "myagent") {
+        anyhow::bail!("Unknown agent_type: {agent_type} (expected \"claude\" or \"myagent\")");
+    }
+
+    let overrides = ThreadOverrides {
+        delegated: true,
+        ..ThreadOverrides::default()
+    };
+    let (thread_id, thread) = manager
+        .create_thread(agent_type, overrides)
+        .await?;
+    let _guard = DelegatedThread {
+        manager: manager.clone(),
+        thread_id: thread_id.clone(),
+        thread: thread.clone(),
+    };
+    thread.submit(Submission::UserMessage(prompt.to_string())).await?;
+
+    let mut output = String::new();
+    let mut tool_calls = 0usize;
+    let collect = async {
+        while let Some(event) = thread.next_event().await {
+            match event {
+                AgentEvent::TextDelta { text, .. } => output.push_str(&text),
+                AgentEvent::ContentBlockStart {
+                    content_block: ContentBlock::ToolUse { .. },
+                    ..
+                } => tool_calls += 1,
+                AgentEvent::StatusChange(status) if status.is_terminal() => {
+                    return match status {
+                        AgentStatus::Failed(msg) => format!("failed: {msg}"),
+                        other => other.as_str().to_string(),
+                    };
+                }
+                AgentEvent::Error(msg) => return format!("failed: {msg}"),
+                _ => {}
+            }
+        }
+        "stopped".to_string()
+    };
+    let status = tokio::select! {
+        status = tokio::time::timeout(DELEGATE_TIMEOUT, collect) => status,
+        _ = cancel.cancelled() => return Err(Cancelled.into()),
+    };
+
+    let status = status.map_err(|_| {
+        anyhow::anyhow!(
+            "Delegated {agent_type} task (thread {thread_id}) timed out after {}s",
+            DELEGATE_TIMEOUT.as_secs()
+        )
+    })?;
+    if output.len() > MAX_OUTPUT_BYTES {
+        let mut end = MAX_OUTPUT_BYTES;
+        while !output.is_char_boundary(end) {
+            end -= 1;
+        }
+        output.truncate(end);
+        output.push_str(&format!("\n\n... (output truncated at {MAX_OUTPUT_BYTES} bytes)"));
+    }
+    let output = if output.trim().is_empty() { "(no text output)" } else { output.trim() };
+    Ok(format!(
+        "[delegated to {agent_type}, thread {thread_id}: {status}, {tool_calls} tool call(s)]\n{output}"
+    ))
+}
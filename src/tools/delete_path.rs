@@ -0,0 +1,46 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+/// Delete a file, symlink or directory. Non-empty directories are only
+/// removed with `recursive`. The workspace root itself is never deleted.
+pub async fn execute(path: &str, recursive: bool, work_dir: &str) -> Result<String> {
+    let full = if Path::new(path).is_absolute() {
+        Path::new(path).to_path_buf()
+    } else {
+        Path::new(work_dir).join(path)
+    };
+
+    if std::fs::canonicalize(&full).ok() == std::fs::canonicalize(work_dir).ok() {
+        anyhow::bail!("Refusing to delete the workspace root: {path}");
+    }
+
+    // Don't follow symlinks: deleting a link must not touch its target
+    let meta = tokio::fs::symlink_metadata(&full)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to delete {}: {e}", full.display()))?;
+
+    let result = if !meta.is_dir() {
+        tokio::fs::remove_file(&full).await
+    } else if recursive {
+        tokio::fs::remove_dir_all(&full).await
+    } else {
+        tokio::fs::remove_dir(&full).await
+    };
+    result.map_err(|e| {
+        if meta.is_dir() && !recursive {
+            anyhow::anyhow!(
+                "Failed to delete {}: {e}. Set recursive to delete a non-empty directory.",
+                full.display()
+            )
+        } else {
+            anyhow::anyhow!("Failed to delete {}: {e}", full.display())
+        }
+    })?;
+
+    if meta.is_dir() {
+        Ok(format!("Deleted directory {path}"))
+    } else {
+        Ok(format!("Deleted {path}"))
+    }
+}
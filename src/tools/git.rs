@@ -0,0 +1,85 @@
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio::process::Command;
+
+const GIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub const ACTIONS: [&str; 7] = ["status", "diff", "log", "add", "commit", "branch", "checkout"];
+
+/// Actions that don't change the repository and can run alongside other
+/// read-only tools.
+pub fn is_read_only(action: &str) -> bool {
+    matches!(action, "status" | "diff" | "log")
+}
+
+/// Run a curated git action in `work_dir`. `args` are appended to the
+/// action's own arguments; `message` is required for `commit`.
+pub async fn execute(
+    action: &str,
+    args: &[String],
+    message: Option<&str>,
+    work_dir: &str,
+) -> Result<String> {
+    if !Path::new(work_dir).ancestors().any(|dir| dir.join(".git").exists()) {
+        anyhow::bail!(
+            "Not a git repository: {work_dir} (no .git here or in any parent directory)"
+        );
+    }
+
+    let mut git_args: Vec<String> = match action {
+        "status" => vec!["status".into(), "--short".into(), "--branch".into()],
+        "log" => vec!["log".into(), "--oneline".into(), "-n".into(), "20".into()],
+        "diff" | "branch" => vec![action.into()],
+        "add" | "checkout" => {
+            if args.is_empty() {
+                anyhow::bail!("git {action} requires args (e.g. [\".\"] or a branch name)");
+            }
+            vec![action.into()]
+        }
+        "commit" => {
+            let message = message
+                .filter(|m| !m.trim().is_empty())
+                .ok_or_else(|| anyhow::anyhow!("git commit requires 'message'"))?;
+            vec!["commit".into(), "-m".into(), message.into()]
+        }
+        other => anyhow::bail!(
+            "Unknown git action: {other} (expected one of {})",
+            ACTIONS.join(", ")
+        ),
+    };
+    git_args.extend(args.iter().cloned());
+
+    let mut cmd = Command::new("git");
+    cmd.args(&git_args)
+        .current_dir(work_dir)
+        .env("GIT_PAGER", "cat")
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true);
+
+    let child = cmd
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Failed to run git: {e}. Is git installed and in PATH?"))?;
+    let output = tokio::time::timeout(GIT_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| anyhow::anyhow!("git {action} timed out after {}s", GIT_TIMEOUT.as_secs()))??;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        let code = output.status.code().unwrap_or(-1);
+        anyhow::bail!("git {action} failed (exit code {code}):\n{}{stderr}", stdout);
+    }
+
+    // git reports progress (e.g. checkout) on stderr even when it succeeds
+    let text = format!("{stdout}{stderr}");
+    if text.trim().is_empty() {
+        Ok(format!("git {action}: done (no output)"))
+    } else {
+        Ok(text.trim_end().to_string())
+    }
+}
@@ -1,20 +1,31 @@
-use std::path::Path;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::process::Output;
 use std::time::Duration;
 
 use anyhow::Result;
+use regex::Regex;
 use tokio::process::Command;
+use tracing::debug;
+use walkdir::WalkDir;
 
 const DEFAULT_LIMIT: usize = 100;
 const MAX_LIMIT: usize = 2000;
 const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_MAX_FILE_SIZE_KB: u64 = 1024;
+/// Files passed to one grep invocation, to stay under the argument limit.
+const GREP_BATCH_FILES: usize = 500;
 
 /// Search files matching a regex pattern, returning file paths sorted by modification time.
-/// Uses ripgrep (rg) only — matches Codex behavior.
+/// Uses ripgrep (rg) like Codex, falling back to grep and then to a built-in
+/// regex search when neither is installed. Fallback results are sorted by path.
+/// Files larger than `max_file_size_kb` are not searched.
 pub async fn execute(
     pattern: &str,
     include: Option<&str>,
     search_path: Option<&str>,
     limit: usize,
+    max_file_size_kb: u64,
     work_dir: &str,
 ) -> Result<String> {
     let pattern = pattern.trim();
@@ -39,12 +50,37 @@ pub async fn execute(
         .map(|s| s.trim())
         .and_then(|s| if s.is_empty() { None } else { Some(s) });
 
-    let results = run_rg_search(pattern, include, &path, limit, work_dir).await?;
+    let max_size = max_file_size_kb.saturating_mul(1024);
 
-    if results.is_empty() {
-        Ok("No matches found.".to_string())
+    let (results, header) = match run_rg_search(pattern, include, &path, limit, max_size, work_dir).await? {
+        Some(results) => (results, None),
+        None => match run_grep_search(pattern, include, &path, limit, max_size, work_dir).await? {
+            Some(results) => (results, Some("Searched with grep (rg not found); sorted by path.")),
+            None => (
+                run_builtin_search(pattern, include, &path, limit, max_size).await?,
+                Some("Searched with the built-in engine (rg and grep not found); sorted by path."),
+            ),
+        },
+    };
+
+    let body = if results.is_empty() {
+        "No matches found.".to_string()
     } else {
-        Ok(results.join("\n"))
+        results.join("\n")
+    };
+    match header {
+        Some(header) => Ok(format!("{header}\n{body}")),
+        None => Ok(body),
+    }
+}
+
+/// Run a search command. `Ok(None)` means the program isn't installed.
+async fn run_search_command(mut cmd: Command, program: &str) -> Result<Option<Output>> {
+    match tokio::time::timeout(COMMAND_TIMEOUT, cmd.output()).await {
+        Err(_) => Err(anyhow::anyhow!("{program} timed out after 30 seconds")),
+        Ok(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Ok(Err(e)) => Err(anyhow::anyhow!("failed to launch {program}: {e}")),
+        Ok(Ok(output)) => Ok(Some(output)),
     }
 }
 
@@ -53,15 +89,18 @@ async fn run_rg_search(
     include: Option<&str>,
     search_path: &Path,
     limit: usize,
+    max_size: u64,
     cwd: &str,
-) -> Result<Vec<String>> {
+) -> Result<Option<Vec<String>>> {
     let mut cmd = Command::new("rg");
     cmd.current_dir(cwd)
         .arg("--files-with-matches")
         .arg("--sortr=modified")
         .arg("--regexp")
         .arg(pattern)
-        .arg("--no-messages");
+        .arg("--no-messages")
+        .arg("--max-filesize")
+        .arg(max_size.to_string());
 
     if let Some(glob) = include {
         cmd.arg("--glob").arg(glob);
@@ -69,14 +108,12 @@ async fn run_rg_search(
 
     cmd.arg("--").arg(search_path);
 
-    let output = tokio::time::timeout(COMMAND_TIMEOUT, cmd.output())
-        .await
-        .map_err(|_| anyhow::anyhow!("rg timed out after 30 seconds"))?
-        .map_err(|e| anyhow::anyhow!("failed to launch rg: {e}. Ensure ripgrep is installed and on PATH."))?;
-
+    let Some(output) = run_search_command(cmd, "rg").await? else {
+        return Ok(None);
+    };
     match output.status.code() {
-        Some(0) => Ok(parse_results(&output.stdout, limit)),
-        Some(1) => Ok(Vec::new()),
+        Some(0) => Ok(Some(parse_results(&output.stdout, limit))),
+        Some(1) => Ok(Some(Vec::new())),
         _ => {
             let stderr = String::from_utf8_lossy(&output.stderr);
             Err(anyhow::anyhow!("rg failed: {stderr}"))
@@ -84,6 +121,153 @@ async fn run_rg_search(
     }
 }
 
+/// grep has no size limit of its own, so the files are listed up front and
+/// passed to grep in batches; files over `max_size` are never read.
+async fn run_grep_search(
+    pattern: &str,
+    include: Option<&str>,
+    search_path: &Path,
+    limit: usize,
+    max_size: u64,
+    cwd: &str,
+) -> Result<Option<Vec<String>>> {
+    let files = grep_candidates(search_path, include, max_size).await?;
+    let mut results = Vec::new();
+    for batch in files.chunks(GREP_BATCH_FILES) {
+        let mut cmd = Command::new("grep");
+        cmd.current_dir(cwd)
+            .arg("--files-with-matches")
+            .arg("--extended-regexp")
+            .arg("--no-messages")
+            .arg("--regexp")
+            .arg(pattern)
+            .arg("--")
+            .args(batch);
+
+        let Some(output) = run_search_command(cmd, "grep").await? else {
+            return Ok(None);
+        };
+        // With --no-messages, unreadable files still make grep exit 2 but
+        // print nothing; a bad pattern exits 2 with an error on stderr.
+        match output.status.code() {
+            Some(0) | Some(2) if !output.stdout.is_empty() => {
+                results.extend(parse_results(&output.stdout, usize::MAX));
+            }
+            Some(1) => {}
+            Some(2) if output.stderr.is_empty() => {}
+            _ => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(anyhow::anyhow!("grep failed: {stderr}"));
+            }
+        }
+    }
+    results.sort();
+    results.truncate(limit);
+    Ok(Some(results))
+}
+
+/// Every file under `search_path` that `grep --recursive --include` would
+/// search and that is at most `max_size` bytes.
+async fn grep_candidates(
+    search_path: &Path,
+    include: Option<&str>,
+    max_size: u64,
+) -> Result<Vec<PathBuf>> {
+    let include = include
+        .map(glob::Pattern::new)
+        .transpose()
+        .map_err(|e| anyhow::anyhow!("invalid include glob: {e}"))?;
+    let root = search_path.to_path_buf();
+
+    let walk = tokio::task::spawn_blocking(move || {
+        WalkDir::new(&root)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter(|e| {
+                include
+                    .as_ref()
+                    .is_none_or(|glob| glob.matches(&e.file_name().to_string_lossy()))
+            })
+            .filter(|e| {
+                e.metadata()
+                    .map_or(true, |meta| within_size(e.path(), meta.len(), max_size))
+            })
+            .map(|e| e.into_path())
+            .collect()
+    });
+    tokio::time::timeout(COMMAND_TIMEOUT, walk)
+        .await
+        .map_err(|_| anyhow::anyhow!("search timed out after 30 seconds"))?
+        .map_err(|e| anyhow::anyhow!("search failed: {e}"))
+}
+
+/// Walk `search_path` and scan each file line by line. Like rg, hidden files
+/// and directories are skipped.
+async fn run_builtin_search(
+    pattern: &str,
+    include: Option<&str>,
+    search_path: &Path,
+    limit: usize,
+    max_size: u64,
+) -> Result<Vec<String>> {
+    let regex = Regex::new(pattern).map_err(|e| anyhow::anyhow!("invalid pattern: {e}"))?;
+    let include = include
+        .map(glob::Pattern::new)
+        .transpose()
+        .map_err(|e| anyhow::anyhow!("invalid include glob: {e}"))?;
+    let root = search_path.to_path_buf();
+
+    let search = tokio::task::spawn_blocking(move || {
+        let mut results: Vec<String> = WalkDir::new(&root)
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter(|e| {
+                include.as_ref().is_none_or(|glob| {
+                    glob.matches(&e.file_name().to_string_lossy())
+                        || e.path().strip_prefix(&root).is_ok_and(|rel| glob.matches_path(rel))
+                })
+            })
+            .filter(|e| {
+                e.metadata()
+                    .map_or(true, |meta| within_size(e.path(), meta.len(), max_size))
+            })
+            .filter(|e| file_matches(e.path(), &regex))
+            .map(|e| e.path().to_string_lossy().to_string())
+            .collect();
+        results.sort();
+        results.truncate(limit);
+        results
+    });
+    tokio::time::timeout(COMMAND_TIMEOUT, search)
+        .await
+        .map_err(|_| anyhow::anyhow!("search timed out after 30 seconds"))?
+        .map_err(|e| anyhow::anyhow!("search failed: {e}"))
+}
+
+/// Whether a file of `size` bytes is small enough to search.
+fn within_size(path: &Path, size: u64, max_size: u64) -> bool {
+    if size <= max_size {
+        return true;
+    }
+    debug!("grep_files: skipping {} ({size} bytes, limit {max_size})", path.display());
+    false
+}
+
+/// Whether any line of the file matches. Unreadable and non-UTF-8 files
+/// don't match.
+fn file_matches(path: &Path, regex: &Regex) -> bool {
+    let Ok(file) = std::fs::File::open(path) else {
+        return false;
+    };
+    std::io::BufReader::new(file)
+        .lines()
+        .map_while(|line| line.ok())
+        .any(|line| regex.is_match(&line))
+}
+
 fn parse_results(stdout: &[u8], limit: usize) -> Vec<String> {
     let mut results = Vec::new();
     for line in stdout.split(|byte| *byte == b'\n') {
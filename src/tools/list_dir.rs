@@ -1,20 +1,27 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::Result;
 use tokio::fs;
+use tokio::process::Command;
+use tracing::debug;
 
 const MAX_ENTRY_LENGTH: usize = 500;
+const GIT_STATUS_TIMEOUT: Duration = Duration::from_secs(5);
 const INDENTATION_SPACES: usize = 2;
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".myagentignore"];
 
 /// List directory entries with BFS traversal, depth control, pagination, and type indicators.
-/// Matches Codex list_dir behavior.
+/// Matches Codex list_dir behavior. With `show_git_status`, entries that
+/// `git status` reports are marked with their status, e.g. `main.rs [M]`.
 pub async fn execute(
     dir_path: &str,
     depth: usize,
     offset: usize,
     limit: usize,
+    show_git_status: bool,
     work_dir: &str,
 ) -> Result<String> {
     let depth = if depth == 0 { 2 } else { depth };
@@ -31,8 +38,22 @@ pub async fn execute(
         return Err(anyhow::anyhow!("{} is not a directory", path.display()));
     }
 
+    // Ignore files apply from the workspace root down to the listed directory
+    let mut dirs: Vec<&Path> = path
+        .ancestors()
+        .take_while(|dir| dir.starts_with(work_dir))
+        .collect();
+    if dirs.is_empty() {
+        dirs.push(&path);
+    }
+    let ignore: Vec<IgnorePattern> = dirs
+        .iter()
+        .rev()
+        .flat_map(|dir| load_ignore_patterns(dir))
+        .collect();
+
     let mut entries = Vec::new();
-    collect_entries(&path, Path::new(""), depth, &mut entries).await?;
+    collect_entries(&path, Path::new(""), depth, &ignore, &mut entries).await?;
 
     if entries.is_empty() {
         return Ok("(empty directory)".to_string());
@@ -50,11 +71,23 @@ pub async fn execute(
     let end_index = start_index + capped_limit;
     let selected = &entries[start_index..end_index];
 
+    let statuses = if show_git_status {
+        git_status(&path).await
+    } else {
+        HashMap::new()
+    };
+    // git reports paths below the resolved repository root
+    let base = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+
     let mut output = Vec::with_capacity(selected.len() + 2);
     output.push(format!("Absolute path: {}", path.display()));
 
     for entry in selected {
-        output.push(format_entry_line(entry));
+        let mut line = format_entry_line(entry);
+        if let Some(status) = statuses.get(&base.join(&entry.sort_key)) {
+            line.push_str(&format!(" [{status}]"));
+        }
+        output.push(line);
     }
 
     if end_index < entries.len() {
@@ -64,6 +97,14 @@ pub async fn execute(
     Ok(output.join("\n"))
 }
 
+/// A pattern from an ignore file, kept with the directory holding the file.
+struct IgnorePattern {
+    base: PathBuf,
+    pattern: glob::Pattern,
+    /// Written with a leading or inner `/`: only matches the path below `base`.
+    anchored: bool,
+}
+
 struct DirEntry {
     sort_key: String,
     display_name: String,
@@ -83,6 +124,7 @@ async fn collect_entries(
     dir_path: &Path,
     relative_prefix: &Path,
     depth: usize,
+    ignore: &[IgnorePattern],
     entries: &mut Vec<DirEntry>,
 ) -> Result<()> {
     let mut queue = VecDeque::new();
@@ -106,6 +148,10 @@ async fn collect_entries(
                 prefix.join(&file_name)
             };
 
+            if is_ignored(ignore, &file_name, &entry.path()) {
+                continue;
+            }
+
             let display_name = truncate_name(&file_name);
             let display_depth = prefix.components().count();
             let sort_key = normalize_path(&relative_path);
@@ -132,6 +178,51 @@ async fn collect_entries(
     Ok(())
 }
 
+/// Read the `.gitignore` and `.myagentignore` patterns in `root`.
+/// Blank lines, comments and negations (`!`) are skipped; a trailing slash
+/// is dropped, so `target/` works like `target`.
+fn load_ignore_patterns(root: &Path) -> Vec<IgnorePattern> {
+    IGNORE_FILES
+        .iter()
+        .filter_map(|name| std::fs::read_to_string(root.join(name)).ok())
+        .flat_map(|content| {
+            content
+                .lines()
+                .map(|line| line.trim().trim_end_matches('/').to_string())
+                .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('!'))
+                .collect::<Vec<_>>()
+        })
+        .filter_map(|line| {
+            let pattern = glob::Pattern::new(line.trim_start_matches('/')).ok()?;
+            Some(IgnorePattern {
+                base: root.to_path_buf(),
+                pattern,
+                anchored: line.contains('/'),
+            })
+        })
+        .collect()
+}
+
+/// Anchored patterns match the entry's path below their ignore file's
+/// directory; others match its name at any depth.
+fn is_ignored(ignore: &[IgnorePattern], name: &OsStr, path: &Path) -> bool {
+    let name = name.to_string_lossy();
+    let separator = glob::MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
+    };
+    ignore.iter().any(|p| {
+        let Ok(relative) = path.strip_prefix(&p.base) else {
+            return false;
+        };
+        if p.anchored {
+            p.pattern.matches_path_with(relative, separator)
+        } else {
+            p.pattern.matches(&name)
+        }
+    })
+}
+
 fn normalize_path(path: &Path) -> String {
     let s = path.to_string_lossy();
     let normalized = s.replace('\\', "/");
@@ -171,6 +262,76 @@ fn format_entry_line(entry: &DirEntry) -> String {
     format!("{indent}{name}")
 }
 
+/// Status letters (M, A, D or ?) of the changed paths in the repository
+/// containing `dir`, keyed by absolute path. Empty when `dir` isn't in a
+/// repository or git can't be run.
+async fn git_status(dir: &Path) -> HashMap<PathBuf, char> {
+    let mut statuses = HashMap::new();
+    let Some(root) = run_git(dir, &["rev-parse", "--show-toplevel"]).await else {
+        return statuses;
+    };
+    let root = PathBuf::from(root.trim_end());
+    // -z leaves paths unquoted; renames are followed by their old path
+    let Some(output) = run_git(dir, &["status", "--porcelain", "-z", "--", "."]).await else {
+        return statuses;
+    };
+    let mut records = output.split('\0');
+    while let Some(record) = records.next() {
+        let (Some(code), Some(rel)) = (record.get(..2), record.get(3..)) else {
+            continue;
+        };
+        let mut flags = code.chars();
+        let (index, worktree) = (flags.next().unwrap_or(' '), flags.next().unwrap_or(' '));
+        if matches!(index, 'R' | 'C') {
+            records.next();
+        }
+        let flag = if index == ' ' || index == '?' { worktree } else { index };
+        let status = match flag {
+            'R' | 'C' => 'A',
+            'T' | 'U' => 'M',
+            other => other,
+        };
+        statuses.insert(root.join(rel.trim_end_matches('/')), status);
+    }
+    statuses
+}
+
+/// Stdout of a successful git command run in `dir`, or `None` (logged).
+async fn run_git(dir: &Path, args: &[&str]) -> Option<String> {
+    let child = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .spawn();
+    let child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            debug!("Skipping git status in list_dir: failed to run git: {e}");
+            return None;
+        }
+    };
+    match tokio::time::timeout(GIT_STATUS_TIMEOUT, child.wait_with_output()).await {
+        Ok(Ok(output)) if output.status.success() => {
+            Some(String::from_utf8_lossy(&output.stdout).into_owned())
+        }
+        Ok(Ok(_)) => {
+            debug!("Skipping git status in list_dir: git {} failed", args[0]);
+            None
+        }
+        Ok(Err(e)) => {
+            debug!("Skipping git status in list_dir: {e}");
+            None
+        }
+        Err(_) => {
+            debug!("Skipping git status in list_dir: git {} timed out", args[0]);
+            None
+        }
+    }
+}
+
 fn classify(ft: &std::fs::FileType) -> DirEntryKind {
     if ft.is_symlink() {
         DirEntryKind::Symlink
@@ -1,23 +1,88 @@
 pub mod apply_patch;
+pub mod clipboard;
+pub mod create_dir;
+pub mod delegate;
+pub mod delete_path;
+pub mod git;
 pub mod grep_files;
 pub mod list_dir;
+pub mod patch_json;
 pub mod read_file;
+pub mod remote_shell;
 pub mod shell;
+pub mod sql_query;
+pub mod write_patch;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use anyhow::Result;
 use serde_json::{json, Value};
+use tokio_util::sync::CancellationToken;
 
 use crate::ai::ToolDef;
+use crate::audit::{AuditLogger, ToolCallRecord};
+use crate::thread_manager::ThreadManager;
 use shell::Shell;
 
-/// Whether a tool supports parallel execution (read lock).
-/// Tools that return `false` take an exclusive write lock.
-pub fn supports_parallel(name: &str) -> bool {
-    matches!(name, "read_file" | "list_dir" | "grep_files")
+/// Running totals for one tool, across all threads.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ToolStats {
+    pub calls: u64,
+    pub errors: u64,
+    pub total_duration_ms: u64,
+}
+
+/// Per-tool statistics keyed by tool name, shared by every thread.
+pub type ToolStatsMap = Arc<Mutex<HashMap<String, ToolStats>>>;
+
+/// What an agent's tool calls run with.
+#[derive(Clone)]
+pub struct ToolContext {
+    pub work_dir: String,
+    pub shell: Shell,
+    /// With a logger every call is also recorded in the audit log.
+    pub audit: Option<Arc<AuditLogger>>,
+    pub stats: ToolStatsMap,
+    /// Hosts `remote_shell` may reach.
+    pub allowed_hosts: Vec<String>,
+    /// Runs `delegate_to_agent` subtasks; `None` when the agent can't delegate.
+    pub manager: Option<Arc<ThreadManager>>,
+    /// Shell and remote shell timeout used when a call doesn't set one.
+    pub shell_timeout_ms: u64,
+    /// Fired when the turn making the calls is cancelled.
+    pub cancel: CancellationToken,
+}
+
+/// Pseudo-tool handled by the agent loop itself: it clears the message
+/// history instead of running anything.
+pub const RESET_CONTEXT: &str = "reset_context";
+
+/// Whether a tool call supports parallel execution (read lock).
+/// Calls that return `false` take an exclusive write lock.
+pub fn supports_parallel(name: &str, input: &Value) -> bool {
+    match name {
+        // Different hosts are independent of each other and of local files
+        "read_file" | "list_dir" | "grep_files" | "write_patch" | "remote_shell" => true,
+        "git" => git::is_read_only(input["action"].as_str().unwrap_or("")),
+        "clipboard" => clipboard::is_read_only(input["action"].as_str().unwrap_or("")),
+        "sql_query" => !input["allow_write"].as_bool().unwrap_or(false),
+        _ => false,
+    }
 }
 
-/// Build all tool definitions for the AI loop.
-pub fn build_tool_definitions(shell: &Shell) -> Vec<ToolDef> {
+/// Build all tool definitions for the AI loop. `remote_shell` is only
+/// offered when some hosts are allowed, `delegate_to_agent` only with
+/// `can_delegate` and `clipboard` only with `clipboard`. `shell_timeout_ms`
+/// is the shell and remote shell timeout used when a call doesn't set one.
+pub fn build_tool_definitions(
+    shell: &Shell,
+    allowed_hosts: &[String],
+    can_delegate: bool,
+    clipboard: bool,
+    shell_timeout_ms: u64,
+) -> Vec<ToolDef> {
     let shell_name = shell.shell_type.name();
     let shell_desc = match shell.shell_type {
         shell::ShellType::PowerShell => format!(
@@ -31,6 +96,15 @@ pub fn build_tool_definitions(shell: &Shell) -> Vec<ToolDef> {
             This is a Windows system using cmd.exe. Use Windows CMD syntax. \
             Use this to run shell commands, including invoking AI coding agents."
         ),
+        shell::ShellType::Fish => format!(
+            "Execute a {shell_name} command and return its output. \
+            The shell is fish, not bash. Use fish syntax: `set VAR value` instead of \
+            `VAR=value`, `(cmd)` instead of `$(cmd)`, `; and` / `; or` for chaining, \
+            and `if ...; end` blocks. \
+            Use this to run shell commands, including invoking AI coding agents \
+            like `claude`, `codex`, or `gemini`. Always prefer specific commands \
+            over interactive shells."
+        ),
         _ => format!(
             "Execute a {shell_name} command and return its output. \
             Use this to run shell commands, including invoking AI coding agents \
@@ -39,7 +113,7 @@ pub fn build_tool_definitions(shell: &Shell) -> Vec<ToolDef> {
         ),
     };
 
-    vec![
+    let mut tools = vec![
         ToolDef {
             name: "shell".to_string(),
             description: shell_desc,
@@ -52,7 +126,11 @@ pub fn build_tool_definitions(shell: &Shell) -> Vec<ToolDef> {
                     },
                     "timeout_ms": {
                         "type": "integer",
-                        "description": "Timeout in milliseconds (default: 120000)"
+                        "description": format!("Timeout in milliseconds (default: {shell_timeout_ms}, configured per agent)")
+                    },
+                    "return_json": {
+                        "type": "boolean",
+                        "description": "Return {\"stdout\", \"stderr\", \"exit_code\"} JSON instead of text (default: false)"
                     }
                 },
                 "required": ["command"]
@@ -61,7 +139,8 @@ pub fn build_tool_definitions(shell: &Shell) -> Vec<ToolDef> {
         ToolDef {
             name: "read_file".to_string(),
             description: "Read a file with 1-indexed line numbers. \
-                Returns lines formatted as L{number}: {content}."
+                Returns lines formatted as L{number}: {content}. \
+                Pass offset and end_line to read an inclusive range such as lines 50-100."
                 .to_string(),
             input_schema: json!({
                 "type": "object",
@@ -77,6 +156,14 @@ pub fn build_tool_definitions(shell: &Shell) -> Vec<ToolDef> {
                     "limit": {
                         "type": "integer",
                         "description": "Maximum number of lines to return (default: 2000)"
+                    },
+                    "end_line": {
+                        "type": "integer",
+                        "description": "1-indexed last line to return (inclusive); overrides limit"
+                    },
+                    "encoding": {
+                        "type": "string",
+                        "description": "Text encoding of the file, e.g. \"latin1\", \"gbk\", \"windows-1252\", or \"auto\" to detect it (default: UTF-8)"
                     }
                 },
                 "required": ["file_path"]
@@ -85,7 +172,8 @@ pub fn build_tool_definitions(shell: &Shell) -> Vec<ToolDef> {
         ToolDef {
             name: "list_dir".to_string(),
             description: "List directory entries recursively with type indicators. \
-                Directories end with /, symlinks with @. Supports pagination."
+                Directories end with /, symlinks with @. Supports pagination. \
+                Entries matched by .gitignore or .myagentignore are skipped."
                 .to_string(),
             input_schema: json!({
                 "type": "object",
@@ -105,6 +193,10 @@ pub fn build_tool_definitions(shell: &Shell) -> Vec<ToolDef> {
                     "limit": {
                         "type": "integer",
                         "description": "Maximum entries to return (default: 25)"
+                    },
+                    "show_git_status": {
+                        "type": "boolean",
+                        "description": "Mark entries git reports as changed: [M] modified, [A] added, [D] deleted, [?] untracked (default: false)"
                     }
                 },
                 "required": ["dir_path"]
@@ -113,7 +205,8 @@ pub fn build_tool_definitions(shell: &Shell) -> Vec<ToolDef> {
         ToolDef {
             name: "grep_files".to_string(),
             description: "Search for files whose contents match a regex pattern. \
-                Returns file paths sorted by modification time."
+                Returns file paths sorted by modification time (by path when \
+                ripgrep is not installed)."
                 .to_string(),
             input_schema: json!({
                 "type": "object",
@@ -129,6 +222,10 @@ pub fn build_tool_definitions(shell: &Shell) -> Vec<ToolDef> {
                     "path": {
                         "type": "string",
                         "description": "Directory or file to search in (default: workspace)"
+                    },
+                    "max_file_size_kb": {
+                        "type": "integer",
+                        "description": "Skip files larger than this size in KiB (default 1024)"
                     }
                 },
                 "required": ["pattern"]
@@ -142,11 +239,15 @@ pub fn build_tool_definitions(shell: &Shell) -> Vec<ToolDef> {
                 *** Add File: path\n\
                 +new line\n\
                 *** Delete File: path\n\
+                *** Rename File: old/path -> new/path\n\
                 *** Update File: path\n\
                 @@ context line to locate\n\
                 -old line\n\
                 +new line\n\
-                *** End Patch"
+                *** End Patch\n\
+                Add File fails if the file already exists; put `*** Overwrite: true` \
+                on the line right after `*** Add File:` to replace it. \
+                A file block may be followed by `*** Chmod: 755` to set its mode."
                 .to_string(),
             input_schema: json!({
                 "type": "object",
@@ -159,23 +260,339 @@ pub fn build_tool_definitions(shell: &Shell) -> Vec<ToolDef> {
                 "required": ["patch"]
             }),
         },
-    ]
+        ToolDef {
+            name: "sql_query".to_string(),
+            description: "Run one SQL statement against a SQLite database file and get \
+                the rows back as a table. The database is opened read-only; statements \
+                that change it (INSERT, UPDATE, DELETE, CREATE, ...) need allow_write."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "db_path": {
+                        "type": "string",
+                        "description": "Absolute or relative path of the SQLite database"
+                    },
+                    "query": {
+                        "type": "string",
+                        "description": "A single SQL statement, e.g. SELECT * FROM users WHERE id = 1"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": "Maximum rows to return (default: 100, max: 1000)"
+                    },
+                    "allow_write": {
+                        "type": "boolean",
+                        "description": "Open the database for writing and allow statements that modify it (default: false)"
+                    }
+                },
+                "required": ["db_path", "query"]
+            }),
+        },
+        ToolDef {
+            name: "patch_json".to_string(),
+            description: "Edit a JSON file in place with a JSON Patch (RFC 6902). Prefer \
+                this over apply_patch for JSON files such as package.json. The file is \
+                rewritten pretty-printed; a failing operation leaves it unchanged."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {
+                        "type": "string",
+                        "description": "Absolute or relative path of the JSON file"
+                    },
+                    "operations": {
+                        "type": "array",
+                        "description": "RFC 6902 operations, applied in order. `path` and `from` are JSON Pointers, e.g. [{\"op\": \"replace\", \"path\": \"/version\", \"value\": \"1.2.0\"}, {\"op\": \"add\", \"path\": \"/dependencies/lodash\", \"value\": \"^4.17.21\"}]",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "op": {
+                                    "type": "string",
+                                    "enum": ["add", "remove", "replace", "move", "copy", "test"]
+                                },
+                                "path": {
+                                    "type": "string",
+                                    "description": "JSON Pointer to the target location (\"/a/b/0\"; \"/arr/-\" appends)"
+                                },
+                                "from": {
+                                    "type": "string",
+                                    "description": "JSON Pointer to the source location, for move and copy"
+                                },
+                                "value": {
+                                    "description": "Value for add, replace and test"
+                                }
+                            },
+                            "required": ["op", "path"]
+                        }
+                    }
+                },
+                "required": ["file_path", "operations"]
+            }),
+        },
+        ToolDef {
+            name: "write_patch".to_string(),
+            description: "Generate an apply_patch patch that turns the original content of \
+                a file into the updated content, without writing anything. Use it to \
+                propose changes for review; pass the result to apply_patch to apply them."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "original": {
+                        "type": "string",
+                        "description": "Current file content (empty for a new file)"
+                    },
+                    "updated": {
+                        "type": "string",
+                        "description": "Proposed file content"
+                    },
+                    "path": {
+                        "type": "string",
+                        "description": "File path to use in the patch"
+                    }
+                },
+                "required": ["original", "updated", "path"]
+            }),
+        },
+        ToolDef {
+            name: "create_directory".to_string(),
+            description: "Create a directory. With recursive (the default), missing \
+                parent directories are created and an existing directory is not an error."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute or relative directory path"
+                    },
+                    "recursive": {
+                        "type": "boolean",
+                        "description": "Create missing parent directories (default: true)"
+                    }
+                },
+                "required": ["path"]
+            }),
+        },
+        ToolDef {
+            name: "delete_path".to_string(),
+            description: "Delete a file or directory. Symlinks are removed, not followed. \
+                Non-empty directories require recursive."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute or relative path to delete"
+                    },
+                    "recursive": {
+                        "type": "boolean",
+                        "description": "Delete a directory and everything in it (default: false)"
+                    }
+                },
+                "required": ["path"]
+            }),
+        },
+        ToolDef {
+            name: "git".to_string(),
+            description: "Run a git operation in the working directory's repository. \
+                Prefer this over shell for git: it checks the directory is a repository \
+                and never opens a pager. status is short format and log shows the last \
+                20 commits one per line unless args say otherwise."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": git::ACTIONS,
+                        "description": "The git operation to run"
+                    },
+                    "args": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Extra arguments, e.g. [\"--staged\"] for diff, paths for add, a branch for checkout"
+                    },
+                    "message": {
+                        "type": "string",
+                        "description": "Commit message (required for commit)"
+                    }
+                },
+                "required": ["action"]
+            }),
+        },
+        ToolDef {
+            name: RESET_CONTEXT.to_string(),
+            description: "Clear the conversation history and start over from a single \
+                message. Use only when the context has become stale or you are going in \
+                circles. Everything said so far is forgotten, so put what you still need \
+                to continue (the task, key findings) in reason."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "reason": {
+                        "type": "string",
+                        "description": "Why the context is reset and what to do next; kept after the reset"
+                    }
+                }
+            }),
+        },
+    ];
+    if !allowed_hosts.is_empty() {
+        tools.push(ToolDef {
+            name: "remote_shell".to_string(),
+            description: format!(
+                "Run a command on a remote host over ssh (non-interactive, key-based auth). \
+                 Pass the command as-is; it is not run through a local shell. \
+                 Allowed hosts: {}",
+                allowed_hosts.join(", ")
+            ),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "host": {
+                        "type": "string",
+                        "description": "Host to run on, as host or user@host"
+                    },
+                    "command": {
+                        "type": "string",
+                        "description": "The command for the remote shell"
+                    },
+                    "timeout_ms": {
+                        "type": "integer",
+                        "description": format!("Timeout in milliseconds (default: {shell_timeout_ms}, configured per agent)")
+                    }
+                },
+                "required": ["host", "command"]
+            }),
+        });
+    }
+    if can_delegate {
+        tools.push(ToolDef {
+            name: "delegate_to_agent".to_string(),
+            description: "Run a subtask on another agent and wait for it to finish. \
+                Returns the agent's text output. Use agent_type \"claude\" for Claude Code \
+                instead of invoking the claude CLI through the shell."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "agent_type": {
+                        "type": "string",
+                        "enum": ["claude", "myagent"],
+                        "description": "Agent to run the subtask"
+                    },
+                    "prompt": {
+                        "type": "string",
+                        "description": "Complete instructions for the subtask"
+                    }
+                },
+                "required": ["agent_type", "prompt"]
+            }),
+        });
+    }
+    if clipboard {
+        tools.push(ToolDef {
+            name: "clipboard".to_string(),
+            description: "Read text from or write text to the user's system clipboard. \
+                Use read when the user refers to something they copied."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["read", "write"],
+                        "description": "read returns the clipboard text; write replaces it"
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "Text to put on the clipboard (required for write)"
+                    }
+                },
+                "required": ["action"]
+            }),
+        });
+    }
+    tools
 }
 
 /// Execute a tool by name and return the result as a string.
-pub async fn execute_tool(
-    name: &str,
-    input: &Value,
-    work_dir: &str,
-    detected_shell: &Shell,
-) -> Result<String> {
+/// The call is counted in `ctx.stats` and recorded by `ctx.audit`, if set.
+pub async fn execute_tool(name: &str, input: &Value, ctx: &ToolContext) -> Result<String> {
+    let started = Instant::now();
+    let result = run_tool(name, input, ctx).await;
+
+    {
+        let mut stats = ctx.stats.lock().unwrap();
+        let entry = stats.entry(name.to_string()).or_default();
+        entry.calls += 1;
+        entry.errors += u64::from(result.is_err());
+        entry.total_duration_ms += started.elapsed().as_millis() as u64;
+    }
+
+    if let Some(audit) = &ctx.audit {
+        let (exit_code, output_truncated) = match &result {
+            Ok(out) if matches!(name, "shell" | "remote_shell") => {
+                (shell::parse_exit_code(out), shell::output_was_truncated(out))
+            }
+            Ok(_) => (Some(0), false),
+            Err(_) => (None, false),
+        };
+        audit
+            .record(&ToolCallRecord {
+                tool_name: name,
+                tool_input: input,
+                work_dir: &ctx.work_dir,
+                exit_code,
+                output_truncated,
+                duration_ms: started.elapsed().as_millis() as u64,
+            })
+            .await;
+    }
+    result
+}
+
+async fn run_tool(name: &str, input: &Value, ctx: &ToolContext) -> Result<String> {
+    let work_dir = ctx.work_dir.as_str();
+    let shell_timeout_ms = ctx.shell_timeout_ms;
     match name {
         "shell" => {
             let command = input["command"]
                 .as_str()
                 .ok_or_else(|| anyhow::anyhow!("shell tool requires 'command' string"))?;
-            let timeout_ms = input["timeout_ms"].as_u64().unwrap_or(120_000);
-            shell::execute(detected_shell, command, timeout_ms, work_dir).await
+            let timeout_ms = input["timeout_ms"].as_u64().unwrap_or(shell_timeout_ms);
+            let return_json = input["return_json"].as_bool().unwrap_or(false);
+            shell::execute(&ctx.shell, command, timeout_ms, return_json, work_dir).await
+        }
+        "remote_shell" => {
+            let host = input["host"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("remote_shell requires 'host' string"))?;
+            let command = input["command"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("remote_shell requires 'command' string"))?;
+            let timeout_ms = input["timeout_ms"].as_u64().unwrap_or(shell_timeout_ms);
+            remote_shell::execute(host, command, timeout_ms, &ctx.allowed_hosts, work_dir).await
+        }
+        "clipboard" => {
+            let action = input["action"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("clipboard requires 'action' string"))?;
+            clipboard::execute(action, input["content"].as_str()).await
+        }
+        "delegate_to_agent" => {
+            let agent_type = input["agent_type"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("delegate_to_agent requires 'agent_type' string"))?;
+            let prompt = input["prompt"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("delegate_to_agent requires 'prompt' string"))?;
+            delegate::execute(agent_type, prompt, ctx.manager.as_ref(), &ctx.cancel).await
         }
         "read_file" => {
             let file_path = input["file_path"]
@@ -183,7 +600,9 @@ pub async fn execute_tool(
                 .ok_or_else(|| anyhow::anyhow!("read_file requires 'file_path' string"))?;
             let offset = input["offset"].as_u64().unwrap_or(1) as usize;
             let limit = input["limit"].as_u64().unwrap_or(2000) as usize;
-            read_file::execute(file_path, offset, limit, work_dir).await
+            let end_line = input["end_line"].as_u64().map(|n| n as usize);
+            let encoding = input["encoding"].as_str();
+            read_file::execute(file_path, offset, limit, end_line, encoding, work_dir).await
         }
         "list_dir" => {
             let dir_path = input["dir_path"]
@@ -192,7 +611,8 @@ pub async fn execute_tool(
             let depth = input["depth"].as_u64().unwrap_or(2) as usize;
             let offset = input["offset"].as_u64().unwrap_or(1) as usize;
             let limit = input["limit"].as_u64().unwrap_or(25) as usize;
-            list_dir::execute(dir_path, depth, offset, limit, work_dir).await
+            let show_git_status = input["show_git_status"].as_bool().unwrap_or(false);
+            list_dir::execute(dir_path, depth, offset, limit, show_git_status, work_dir).await
         }
         "grep_files" => {
             let pattern = input["pattern"]
@@ -201,7 +621,10 @@ pub async fn execute_tool(
             let include = input["include"].as_str();
             let path = input["path"].as_str();
             let limit = input["limit"].as_u64().unwrap_or(100) as usize;
-            grep_files::execute(pattern, include, path, limit, work_dir).await
+            let max_file_size_kb = input["max_file_size_kb"]
+                .as_u64()
+                .unwrap_or(grep_files::DEFAULT_MAX_FILE_SIZE_KB);
+            grep_files::execute(pattern, include, path, limit, max_file_size_kb, work_dir).await
         }
         "apply_patch" => {
             let patch = input["patch"]
@@ -209,6 +632,67 @@ pub async fn execute_tool(
                 .ok_or_else(|| anyhow::anyhow!("apply_patch requires 'patch' string"))?;
             apply_patch::execute(patch, work_dir).await
         }
+        "sql_query" => {
+            let db_path = input["db_path"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("sql_query requires 'db_path' string"))?;
+            let query = input["query"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("sql_query requires 'query' string"))?;
+            let limit = input["limit"]
+                .as_u64()
+                .map_or(sql_query::DEFAULT_LIMIT, |n| n as usize);
+            let allow_write = input["allow_write"].as_bool().unwrap_or(false);
+            sql_query::execute(db_path, query, limit, allow_write, work_dir).await
+        }
+        "patch_json" => {
+            let file_path = input["file_path"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("patch_json requires 'file_path' string"))?;
+            let operations = input
+                .get("operations")
+                .filter(|ops| ops.is_array())
+                .ok_or_else(|| anyhow::anyhow!("patch_json requires 'operations' array"))?;
+            patch_json::execute(file_path, operations, work_dir).await
+        }
+        "write_patch" => {
+            let original = input["original"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("write_patch requires 'original' string"))?;
+            let updated = input["updated"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("write_patch requires 'updated' string"))?;
+            let path = input["path"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("write_patch requires 'path' string"))?;
+            write_patch::execute(original, updated, path)
+        }
+        "create_directory" => {
+            let path = input["path"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("create_directory requires 'path' string"))?;
+            let recursive = input["recursive"].as_bool().unwrap_or(true);
+            create_dir::execute(path, recursive, work_dir).await
+        }
+        "delete_path" => {
+            let path = input["path"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("delete_path requires 'path' string"))?;
+            let recursive = input["recursive"].as_bool().unwrap_or(false);
+            delete_path::execute(path, recursive, work_dir).await
+        }
+        "git" => {
+            let action = input["action"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("git requires 'action' string"))?;
+            let args: Vec<String> = input["args"]
+                .as_array()
+                .map(|a| a.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+                .unwrap_or_default();
+            let message = input["message"].as_str();
+            git::execute(action, &args, message, work_dir).await
+        }
+        RESET_CONTEXT => Err(anyhow::anyhow!("{RESET_CONTEXT} is handled by the agent loop")),
         _ => Err(anyhow::anyhow!("Unknown tool: {name}")),
     }
 }
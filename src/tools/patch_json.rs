@@ -0,0 +1,53 @@
+use std::path::Path;
+
+use anyhow::Result;
+use serde_json::Value;
+use similar::TextDiff;
+
+/// Apply a JSON Patch (RFC 6902) to a JSON file and rewrite it
+/// pretty-printed, keeping key order. Either every operation applies or the
+/// file is untouched.
+pub async fn execute(file_path: &str, operations: &Value, work_dir: &str) -> Result<String> {
+    let path = if Path::new(file_path).is_absolute() {
+        Path::new(file_path).to_path_buf()
+    } else {
+        Path::new(work_dir).join(file_path)
+    };
+
+    let patch: json_patch::Patch = serde_json::from_value(operations.clone())
+        .map_err(|e| anyhow::anyhow!("Invalid JSON Patch operations: {e}"))?;
+    if patch.0.is_empty() {
+        anyhow::bail!("operations must contain at least one operation");
+    }
+
+    let content = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", path.display()))?;
+    let mut doc: Value = serde_json::from_str(&content)
+        .map_err(|e| anyhow::anyhow!("{} is not valid JSON: {e}", path.display()))?;
+    let before = format!("{}\n", serde_json::to_string_pretty(&doc)?);
+
+    json_patch::patch(&mut doc, &patch)
+        .map_err(|e| anyhow::anyhow!("Failed to apply patch to {}: {e}", path.display()))?;
+    let after = format!("{}\n", serde_json::to_string_pretty(&doc)?);
+
+    tokio::fs::write(&path, &after)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {e}", path.display()))?;
+
+    let count = patch.0.len();
+    let noun = if count == 1 { "operation" } else { "operations" };
+    if before == after {
+        return Ok(format!(
+            "Applied {count} {noun} to {}: no changes to the document",
+            path.display()
+        ));
+    }
+    // Diff the pretty-printed forms so the summary shows what changed in the
+    // document rather than in its original formatting
+    let diff = TextDiff::from_lines(&before, &after)
+        .unified_diff()
+        .context_radius(2)
+        .to_string();
+    Ok(format!("Applied {count} {noun} to {}:\n{}", path.display(), diff.trim_end()))
+}
@@ -1,16 +1,39 @@
 use std::path::Path;
 
 use anyhow::Result;
+use encoding_rs::Encoding;
 use tokio::fs::File;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, BufReader};
 
 const MAX_LINE_LENGTH: usize = 500;
 
+/// Bytes read from the start of the file when `encoding` is "auto".
+const DETECT_SAMPLE_BYTES: u64 = 64 * 1024;
+
 /// Read a file with 1-indexed line numbers, offset, and limit.
+/// `end_line` (inclusive) replaces `limit` when given.
+/// `encoding` is an encoding label such as "latin1" or "gbk", or "auto" to
+/// detect it; without it lines are decoded as UTF-8 with lossy replacement.
 /// Output format: `L{line_number}: {content}`
-pub async fn execute(file_path: &str, offset: usize, limit: usize, work_dir: &str) -> Result<String> {
+pub async fn execute(
+    file_path: &str,
+    offset: usize,
+    limit: usize,
+    end_line: Option<usize>,
+    encoding: Option<&str>,
+    work_dir: &str,
+) -> Result<String> {
     let offset = if offset == 0 { 1 } else { offset };
-    let limit = if limit == 0 { 2000 } else { limit };
+    let limit = match end_line {
+        Some(end) if end < offset => {
+            return Err(anyhow::anyhow!(
+                "end_line {end} is before offset {offset}"
+            ));
+        }
+        Some(end) => end - offset + 1,
+        None if limit == 0 => 2000,
+        None => limit,
+    };
 
     let path = if Path::new(file_path).is_absolute() {
         Path::new(file_path).to_path_buf()
@@ -18,9 +41,19 @@ pub async fn execute(file_path: &str, offset: usize, limit: usize, work_dir: &st
         Path::new(work_dir).join(file_path)
     };
 
-    let file = File::open(&path).await
+    let mut file = File::open(&path).await
         .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", path.display()))?;
 
+    let (encoding, label) = match encoding {
+        None => (encoding_rs::UTF_8, None),
+        Some(label) if label.eq_ignore_ascii_case("auto") => {
+            let detected = detect_encoding(&mut file).await
+                .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", path.display()))?;
+            (detected, Some(detected.name().to_ascii_lowercase()))
+        }
+        Some(label) => (lookup_encoding(label)?, Some(label.to_string())),
+    };
+
     let mut reader = BufReader::new(file);
     let mut collected = Vec::new();
     let mut line_num = 0usize;
@@ -52,7 +85,7 @@ pub async fn execute(file_path: &str, offset: usize, limit: usize, work_dir: &st
             break;
         }
 
-        let line = format_line(&buf);
+        let line = format_line(&buf, encoding);
         collected.push(format!("L{line_num}: {line}"));
     }
 
@@ -63,14 +96,55 @@ pub async fn execute(file_path: &str, offset: usize, limit: usize, work_dir: &st
     }
 
     if collected.is_empty() {
-        Ok("(empty file)".to_string())
-    } else {
-        Ok(collected.join("\n"))
+        return Ok("(empty file)".to_string());
     }
+    let mut output = collected.join("\n");
+    if let Some(label) = label.filter(|_| encoding != encoding_rs::UTF_8) {
+        output = format!("Note: file decoded from {label}\n{output}");
+    }
+    // Reaching EOF before end_line means it was past the last line
+    if let Some(end) = end_line.filter(|&end| line_num < end) {
+        output.push_str(&format!(
+            "\n(end_line {end} exceeds file length; clamped to line {line_num})"
+        ));
+    }
+    Ok(output)
+}
+
+/// Map an encoding name to an ASCII-compatible encoding, so lines can still be
+/// split on `\n`.
+fn lookup_encoding(label: &str) -> Result<&'static Encoding> {
+    // Common spellings the WHATWG label list doesn't include
+    let normalized = match label.to_ascii_lowercase().as_str() {
+        "latin-1" => "latin1",
+        "win1252" | "windows1252" => "windows-1252",
+        _ => label,
+    };
+    let encoding = Encoding::for_label(normalized.as_bytes()).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Unknown encoding: {label} (e.g. \"latin1\", \"gbk\", \"windows-1252\", \"shift_jis\" or \"auto\")"
+        )
+    })?;
+    if !encoding.is_ascii_compatible() {
+        anyhow::bail!("Unsupported encoding: {label} (only ASCII-compatible encodings can be read by line)");
+    }
+    Ok(encoding)
+}
+
+/// Guess the encoding from the start of the file, then rewind it.
+async fn detect_encoding(file: &mut File) -> std::io::Result<&'static Encoding> {
+    let mut sample = Vec::new();
+    (&mut *file).take(DETECT_SAMPLE_BYTES).read_to_end(&mut sample).await?;
+    file.rewind().await?;
+
+    let mut detector = chardetng::EncodingDetector::new();
+    let is_whole_file = (sample.len() as u64) < DETECT_SAMPLE_BYTES;
+    detector.feed(&sample, is_whole_file);
+    Ok(detector.guess(None, true))
 }
 
-fn format_line(bytes: &[u8]) -> String {
-    let s = String::from_utf8_lossy(bytes);
+fn format_line(bytes: &[u8], encoding: &'static Encoding) -> String {
+    let (s, _) = encoding.decode_without_bom_handling(bytes);
     if s.len() > MAX_LINE_LENGTH {
         // Truncate at char boundary
         let mut end = MAX_LINE_LENGTH;
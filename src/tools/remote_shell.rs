@@ -0,0 +1,49 @@
+use anyhow::Result;
+use tokio::process::Command;
+use tracing::info;
+
+use super::shell;
+
+/// Run `command` on `host` over ssh. `host` (`host` or `user@host`) must be
+/// in `allowed_hosts`; output is rendered like the local shell tool's.
+pub async fn execute(
+    host: &str,
+    command: &str,
+    timeout_ms: u64,
+    allowed_hosts: &[String],
+    work_dir: &str,
+) -> Result<String> {
+    if allowed_hosts.is_empty() {
+        anyhow::bail!("remote_shell is disabled: add hosts to allowed_hosts in the config");
+    }
+    // A leading '-' would be taken as an ssh option
+    if host.starts_with('-') || !is_allowed(host, allowed_hosts) {
+        anyhow::bail!(
+            "Host {host} is not in allowed_hosts ({})",
+            allowed_hosts.join(", ")
+        );
+    }
+    info!("Remote shell on {host}: {}", command.lines().next().unwrap_or(""));
+
+    let mut cmd = Command::new("ssh");
+    cmd.args([
+        "-o",
+        "StrictHostKeyChecking=no",
+        "-o",
+        "BatchMode=yes",
+        "-o",
+        "ConnectTimeout=10",
+        "--",
+        host,
+        command,
+    ])
+    .stdin(std::process::Stdio::null())
+    .current_dir(work_dir);
+    shell::run(cmd, timeout_ms, false).await
+}
+
+/// `user@host` matches an entry for `user@host` or for the bare `host`.
+fn is_allowed(host: &str, allowed_hosts: &[String]) -> bool {
+    let bare = host.rsplit_once('@').map_or(host, |(_, h)| h);
+    allowed_hosts.iter().any(|h| h == host || h == bare)
+}
@@ -1,7 +1,9 @@
+use std::ffi::{OsStr, OsString};
 use std::path::PathBuf;
 use std::time::Duration;
 
 use anyhow::Result;
+use serde::Serialize;
 use tokio::process::Command;
 use tracing::{debug, info};
 
@@ -14,6 +16,7 @@ pub enum ShellType {
     Bash,
     Zsh,
     Sh,
+    Fish,
     PowerShell,
     Cmd,
 }
@@ -25,6 +28,7 @@ impl ShellType {
             Self::Bash => "bash",
             Self::Zsh => "zsh",
             Self::Sh => "sh",
+            Self::Fish => "fish",
             Self::PowerShell => "powershell",
             Self::Cmd => "cmd",
         }
@@ -36,52 +40,58 @@ impl ShellType {
 pub struct Shell {
     pub shell_type: ShellType,
     pub path: PathBuf,
+    /// PATH for spawned commands: the configured extra dirs, then the inherited PATH.
+    pub search_path: OsString,
 }
 
 impl Shell {
-    /// Detect the best available shell for the current platform.
-    pub fn detect() -> Self {
+    /// Detect the best available shell for the current platform,
+    /// searching `extra_path` before the inherited PATH.
+    pub fn detect(extra_path: &[String]) -> Self {
+        let search_path = build_search_path(extra_path);
         #[cfg(unix)]
-        {
-            Self::detect_unix()
-        }
+        let (shell_type, path) = Self::detect_unix(&search_path);
         #[cfg(windows)]
-        {
-            Self::detect_windows()
-        }
+        let (shell_type, path) = Self::detect_windows(&search_path);
+        Self { shell_type, path, search_path }
     }
 
     #[cfg(unix)]
-    fn detect_unix() -> Self {
+    fn detect_unix(search_path: &OsStr) -> (ShellType, PathBuf) {
         // Try user's login shell from $SHELL
         if let Ok(shell_path) = std::env::var("SHELL") {
             let path = PathBuf::from(&shell_path);
             if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
                 match name {
-                    "bash" => return Self { shell_type: ShellType::Bash, path },
-                    "zsh" => return Self { shell_type: ShellType::Zsh, path },
+                    "bash" => return (ShellType::Bash, path),
+                    "zsh" => return (ShellType::Zsh, path),
+                    "fish" => return (ShellType::Fish, path),
                     _ => {}
                 }
             }
         }
-        // Fallback: prefer bash > zsh > sh
-        for (name, st) in [("bash", ShellType::Bash), ("zsh", ShellType::Zsh)] {
-            if let Ok(p) = which(name) {
-                return Self { shell_type: st, path: p };
+        // Fallback: prefer bash > zsh > fish > sh
+        for (name, st) in [
+            ("bash", ShellType::Bash),
+            ("zsh", ShellType::Zsh),
+            ("fish", ShellType::Fish),
+        ] {
+            if let Ok(p) = which(name, search_path) {
+                return (st, p);
             }
         }
-        Self { shell_type: ShellType::Sh, path: PathBuf::from("/bin/sh") }
+        (ShellType::Sh, PathBuf::from("/bin/sh"))
     }
 
     #[cfg(windows)]
-    fn detect_windows() -> Self {
+    fn detect_windows(search_path: &OsStr) -> (ShellType, PathBuf) {
         // Prefer pwsh (PowerShell 7+) > powershell (5.1) > cmd
         for name in ["pwsh.exe", "powershell.exe"] {
-            if let Ok(p) = which(name) {
-                return Self { shell_type: ShellType::PowerShell, path: p };
+            if let Ok(p) = which(name, search_path) {
+                return (ShellType::PowerShell, p);
             }
         }
-        Self { shell_type: ShellType::Cmd, path: PathBuf::from("cmd.exe") }
+        (ShellType::Cmd, PathBuf::from("cmd.exe"))
     }
 
     /// Build the command args for executing a string command.
@@ -92,7 +102,8 @@ impl Shell {
                 "-lc".to_string(),
                 command.to_string(),
             ],
-            ShellType::Sh => vec![
+            // fish always sources its config, so no login flag is needed
+            ShellType::Sh | ShellType::Fish => vec![
                 self.path.to_string_lossy().to_string(),
                 "-c".to_string(),
                 command.to_string(),
@@ -113,14 +124,9 @@ impl Shell {
 }
 
 /// Simple which: find executable in PATH.
-fn which(name: &str) -> std::result::Result<PathBuf, ()> {
-    let path_var = std::env::var("PATH").map_err(|_| ())?;
-    #[cfg(unix)]
-    let sep = ':';
-    #[cfg(windows)]
-    let sep = ';';
-    for dir in path_var.split(sep) {
-        let candidate = PathBuf::from(dir).join(name);
+fn which(name: &str, search_path: &OsStr) -> std::result::Result<PathBuf, ()> {
+    for dir in std::env::split_paths(search_path) {
+        let candidate = dir.join(name);
         if candidate.is_file() {
             return Ok(candidate);
         }
@@ -128,11 +134,61 @@ fn which(name: &str) -> std::result::Result<PathBuf, ()> {
     Err(())
 }
 
-/// Execute a shell command with timeout and output capping.
+/// Prepend `extra_path` (with `~/` expanded) to the inherited PATH.
+fn build_search_path(extra_path: &[String]) -> OsString {
+    let home = dirs::home_dir();
+    let mut dirs: Vec<PathBuf> = extra_path
+        .iter()
+        .map(|d| match (d.strip_prefix("~/"), &home) {
+            (Some(rest), Some(home)) => home.join(rest),
+            _ => PathBuf::from(d),
+        })
+        .collect();
+    if let Some(path) = std::env::var_os("PATH") {
+        dirs.extend(std::env::split_paths(&path));
+    }
+    std::env::join_paths(dirs).unwrap_or_else(|_| std::env::var_os("PATH").unwrap_or_default())
+}
+
+/// Structured result returned when the caller asks for JSON.
+#[derive(Serialize)]
+struct ShellOutput {
+    stdout: String,
+    stderr: String,
+    exit_code: i32,
+}
+
+impl ShellOutput {
+    fn render(&self, return_json: bool) -> Result<String> {
+        if return_json {
+            return Ok(serde_json::to_string(self)?);
+        }
+        let mut result = String::new();
+        if !self.stdout.is_empty() {
+            result.push_str(&self.stdout);
+        }
+        if !self.stderr.is_empty() {
+            if !result.is_empty() {
+                result.push_str("\n--- stderr ---\n");
+            }
+            result.push_str(&self.stderr);
+        }
+        if result.is_empty() {
+            result = "(no output)".to_string();
+        }
+        result.push_str(&format!("\n\nExit code: {}", self.exit_code));
+        Ok(result)
+    }
+}
+
+/// Execute a shell command with timeout and output capping. With
+/// `return_json` the result is `{"stdout", "stderr", "exit_code"}` JSON
+/// instead of text.
 pub async fn execute(
     shell: &Shell,
     command: &str,
     timeout_ms: u64,
+    return_json: bool,
     work_dir: &str,
 ) -> Result<String> {
     debug!("Executing {} in {work_dir}: {command}", shell.shell_type.name());
@@ -141,8 +197,15 @@ pub async fn execute(
     let args = shell.exec_args(command);
     let mut cmd = Command::new(&args[0]);
     cmd.args(&args[1..])
-        .current_dir(work_dir)
-        .stdout(std::process::Stdio::piped())
+        .env("PATH", &shell.search_path)
+        .current_dir(work_dir);
+    run(cmd, timeout_ms, return_json).await
+}
+
+/// Run a prepared command with a timeout and render its output the way the
+/// shell tool does.
+pub async fn run(mut cmd: Command, timeout_ms: u64, return_json: bool) -> Result<String> {
+    cmd.stdout(std::process::Stdio::piped())
         .stderr(std::process::Stdio::piped())
         .kill_on_drop(true);
 
@@ -151,37 +214,44 @@ pub async fn execute(
     let result = tokio::time::timeout(timeout, child.wait_with_output()).await;
 
     match result {
-        Ok(Ok(output)) => {
-            let exit_code = output.status.code().unwrap_or(-1);
-            let stdout = truncate_output(&output.stdout);
-            let stderr = truncate_output(&output.stderr);
-
-            let mut result = String::new();
-            if !stdout.is_empty() {
-                result.push_str(&stdout);
-            }
-            if !stderr.is_empty() {
-                if !result.is_empty() {
-                    result.push_str("\n--- stderr ---\n");
-                }
-                result.push_str(&stderr);
-            }
-            if result.is_empty() {
-                result = "(no output)".to_string();
-            }
-            result.push_str(&format!("\n\nExit code: {exit_code}"));
-            Ok(result)
+        Ok(Ok(output)) => ShellOutput {
+            stdout: truncate_output(&output.stdout),
+            stderr: truncate_output(&output.stderr),
+            exit_code: output.status.code().unwrap_or(-1),
         }
+        .render(return_json),
         Ok(Err(e)) => Err(anyhow::anyhow!("Failed to execute command: {e}")),
         Err(_) => {
             debug!("Shell command timed out after {timeout_ms}ms");
-            Ok(format!(
-                "Command timed out after {timeout_ms}ms.\n\nExit code: 124"
-            ))
+            let message = format!("Command timed out after {timeout_ms}ms.");
+            if return_json {
+                ShellOutput {
+                    stdout: String::new(),
+                    stderr: message,
+                    exit_code: 124,
+                }
+                .render(true)
+            } else {
+                Ok(format!("{message}\n\nExit code: 124"))
+            }
         }
     }
 }
 
+/// Exit code from the trailer that `execute` appends to its output, or from
+/// its JSON result.
+pub fn parse_exit_code(output: &str) -> Option<i32> {
+    if let Ok(json) = serde_json::from_str::<serde_json::Value>(output) {
+        return json["exit_code"].as_i64().map(|code| code as i32);
+    }
+    output.rsplit_once("Exit code: ")?.1.trim().parse().ok()
+}
+
+/// Whether `execute` cut stdout or stderr short.
+pub fn output_was_truncated(output: &str) -> bool {
+    output.contains("... (output truncated at ")
+}
+
 fn truncate_output(bytes: &[u8]) -> String {
     let s = String::from_utf8_lossy(bytes);
     if s.len() > MAX_OUTPUT_BYTES {
@@ -0,0 +1,146 @@
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Result;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+
+pub const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+
+/// Widest a column is printed; longer values are cut off.
+const MAX_COLUMN_WIDTH: usize = 40;
+
+/// How long to wait for another connection's lock before giving up.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Run one SQL statement against the SQLite database at `db_path`. The
+/// database is opened read-only unless `allow_write` is set; statements that
+/// modify it are refused without it. Rows come back as a table of at most
+/// `limit` rows, writes as the number of rows changed.
+pub async fn execute(
+    db_path: &str,
+    query: &str,
+    limit: usize,
+    allow_write: bool,
+    work_dir: &str,
+) -> Result<String> {
+    let path = if Path::new(db_path).is_absolute() {
+        Path::new(db_path).to_path_buf()
+    } else {
+        Path::new(work_dir).join(db_path)
+    };
+    if !path.is_file() {
+        anyhow::bail!("Database not found: {}", path.display());
+    }
+    let query = query.to_string();
+    let limit = limit.clamp(1, MAX_LIMIT);
+
+    tokio::task::spawn_blocking(move || run_query(&path, &query, limit, allow_write))
+        .await
+        .map_err(|e| anyhow::anyhow!("sql_query task failed: {e}"))?
+}
+
+fn run_query(path: &Path, query: &str, limit: usize, allow_write: bool) -> Result<String> {
+    // Never create a database that doesn't exist
+    let flags = if allow_write {
+        OpenFlags::SQLITE_OPEN_READ_WRITE
+    } else {
+        OpenFlags::SQLITE_OPEN_READ_ONLY
+    };
+    let conn = Connection::open_with_flags(path, flags | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+        .map_err(|e| anyhow::anyhow!("Failed to open {}: {e}", path.display()))?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+
+    let mut stmt = conn
+        .prepare(query)
+        .map_err(|e| anyhow::anyhow!("Invalid query: {e}"))?;
+    if !stmt.readonly() && !allow_write {
+        anyhow::bail!("This statement modifies the database; set allow_write: true to run it");
+    }
+
+    if stmt.column_count() == 0 {
+        let changed = stmt.execute([])?;
+        let noun = if changed == 1 { "row" } else { "rows" };
+        return Ok(format!("{changed} {noun} affected"));
+    }
+
+    let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut truncated = false;
+    let mut result = stmt.query([])?;
+    while let Some(row) = result.next()? {
+        if rows.len() == limit {
+            truncated = true;
+            break;
+        }
+        let cells = (0..columns.len())
+            .map(|i| row.get_ref(i).map(format_value))
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows.push(cells);
+    }
+
+    let mut out = format_table(&columns, &rows);
+    let noun = if rows.len() == 1 { "row" } else { "rows" };
+    if truncated {
+        out.push_str(&format!(
+            "\n(first {} {noun}; raise limit or narrow the query for more)",
+            rows.len()
+        ));
+    } else {
+        out.push_str(&format!("\n({} {noun})", rows.len()));
+    }
+    Ok(out)
+}
+
+fn format_value(value: ValueRef<'_>) -> String {
+    match value {
+        ValueRef::Null => "NULL".to_string(),
+        ValueRef::Integer(n) => n.to_string(),
+        ValueRef::Real(f) => f.to_string(),
+        ValueRef::Text(text) => String::from_utf8_lossy(text).replace(['\n', '\r', '\t'], " "),
+        ValueRef::Blob(blob) => format!("<blob {} bytes>", blob.len()),
+    }
+}
+
+/// Fixed-width columns separated by ` | `, with a rule under the header.
+fn format_table(columns: &[String], rows: &[Vec<String>]) -> String {
+    let cell = |s: &str| -> String {
+        if s.chars().count() > MAX_COLUMN_WIDTH {
+            let head: String = s.chars().take(MAX_COLUMN_WIDTH - 3).collect();
+            format!("{head}...")
+        } else {
+            s.to_string()
+        }
+    };
+    let header: Vec<String> = columns.iter().map(|c| cell(c)).collect();
+    let body: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| row.iter().map(|c| cell(c)).collect())
+        .collect();
+    let widths: Vec<usize> = (0..columns.len())
+        .map(|i| {
+            body.iter()
+                .map(|row| row[i].chars().count())
+                .chain(std::iter::once(header[i].chars().count()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let line = |cells: &[String]| -> String {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(c, w)| format!("{c:<w$}"))
+            .collect::<Vec<_>>()
+            .join(" | ")
+            .trim_end()
+            .to_string()
+    };
+    let rule = widths.iter().map(|w| "-".repeat(*w)).collect::<Vec<_>>().join("-+-");
+
+    let mut lines = vec![line(&header), rule];
+    lines.extend(body.iter().map(|row| line(row)));
+    lines.join("\n")
+}
@@ -0,0 +1,44 @@
+use anyhow::Result;
+use similar::{ChangeTag, TextDiff};
+
+/// Lines of unchanged context kept around each change.
+const CONTEXT_LINES: usize = 3;
+
+/// Build an apply_patch-format patch that turns `original` into `updated`
+/// for the file at `path`. Nothing is written to disk.
+pub fn execute(original: &str, updated: &str, path: &str) -> Result<String> {
+    let path = path.trim();
+    if path.is_empty() {
+        anyhow::bail!("path must not be empty");
+    }
+    if original == updated {
+        return Ok("No changes: original and updated content are identical.".to_string());
+    }
+
+    let mut out = vec!["*** Begin Patch".to_string()];
+    if original.is_empty() {
+        out.push(format!("*** Add File: {path}"));
+        out.extend(updated.lines().map(|line| format!("+{line}")));
+    } else {
+        out.push(format!("*** Update File: {path}"));
+        let diff = TextDiff::from_lines(original, updated);
+        for group in diff.grouped_ops(CONTEXT_LINES) {
+            out.push("@@".to_string());
+            for op in &group {
+                for change in diff.iter_changes(op) {
+                    let sign = match change.tag() {
+                        ChangeTag::Equal => ' ',
+                        ChangeTag::Delete => '-',
+                        ChangeTag::Insert => '+',
+                    };
+                    let line = change.value();
+                    let line = line.strip_suffix('\n').unwrap_or(line);
+                    let line = line.strip_suffix('\r').unwrap_or(line);
+                    out.push(format!("{sign}{line}"));
+                }
+            }
+        }
+    }
+    out.push("*** End Patch".to_string());
+    Ok(out.join("\n"))
+}
@@ -251,6 +251,48 @@ impl FeishuApi {
         Ok(msg_id.to_string())
     }
 
+    /// Add an emoji reaction (e.g. "THUMBSUP") to a message.
+    pub async fn add_emoji_reaction(&self, msg_id: &str, emoji_type: &str) -> Result<()> {
+        let token = self.get_token().await?;
+        let body = serde_json::json!({ "reaction_type": { "emoji_type": emoji_type } });
+        let url = format!("{BASE_URL}/im/v1/messages/{msg_id}/reactions");
+
+        let resp: Value = self
+            .http
+            .post(&url)
+            .bearer_auth(&token)
+            .json(&body)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        let code = resp["code"].as_i64().unwrap_or(-1);
+        if Self::is_token_error(code) {
+            warn!("Token expired on add_emoji_reaction, refreshing...");
+            let new_token = self.invalidate_and_refresh().await?;
+            let resp: Value = self
+                .http
+                .post(&url)
+                .bearer_auth(&new_token)
+                .json(&body)
+                .send()
+                .await?
+                .json()
+                .await?;
+            if resp["code"].as_i64().unwrap_or(-1) != 0 {
+                anyhow::bail!("Failed to add reaction: {}", resp["msg"]);
+            }
+            return Ok(());
+        }
+
+        if code != 0 {
+            anyhow::bail!("Failed to add reaction: {}", resp["msg"]);
+        }
+        debug!("Added {emoji_type} reaction to {msg_id}");
+        Ok(())
+    }
+
     // ── File APIs ──
 
     /// Upload a local file to Feishu. Returns the file_key.
@@ -438,6 +480,112 @@ impl FeishuApi {
         Ok((items, has_more, page_token))
     }
 
+    /// Fetch a chat's `name`, `description`, `chat_type` and `chat_mode`
+    /// (`p2p` for direct messages).
+    pub async fn get_chat_info(&self, chat_id: &str) -> Result<Value> {
+        let url = format!("{BASE_URL}/im/v1/chats/{chat_id}");
+        let token = self.get_token().await?;
+        let mut resp: Value = self
+            .http
+            .get(&url)
+            .bearer_auth(&token)
+            .send()
+            .await?
+            .json()
+            .await?;
+        if Self::is_token_error(resp["code"].as_i64().unwrap_or(-1)) {
+            let new_token = self.invalidate_and_refresh().await?;
+            resp = self
+                .http
+                .get(&url)
+                .bearer_auth(&new_token)
+                .send()
+                .await?
+                .json()
+                .await?;
+        }
+        let code = resp["code"].as_i64().unwrap_or(-1);
+        if code != 0 {
+            anyhow::bail!("get_chat_info failed: {} (code={code})", resp["msg"]);
+        }
+        let data = &resp["data"];
+        Ok(serde_json::json!({
+            "name": data["name"],
+            "description": data["description"],
+            "chat_type": data["chat_type"],
+            "chat_mode": data["chat_mode"],
+        }))
+    }
+
+    /// The bot's own open_id, as it appears in message mentions.
+    pub async fn bot_open_id(&self) -> Result<String> {
+        let token = self.get_token().await?;
+        let resp: Value = self
+            .http
+            .get(format!("{BASE_URL}/bot/v3/info"))
+            .bearer_auth(&token)
+            .send()
+            .await?
+            .json()
+            .await?;
+        let code = resp["code"].as_i64().unwrap_or(-1);
+        if code != 0 {
+            anyhow::bail!("bot info failed: {} (code={code})", resp["msg"]);
+        }
+        resp["bot"]["open_id"]
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| anyhow::anyhow!("bot info has no open_id"))
+    }
+
+    /// List the ids of every chat the bot is a member of.
+    pub async fn list_chats(&self) -> Result<Vec<String>> {
+        let mut chat_ids = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut url = format!("{BASE_URL}/im/v1/chats?page_size=100");
+            if let Some(pt) = &page_token {
+                url.push_str(&format!("&page_token={pt}"));
+            }
+
+            let token = self.get_token().await?;
+            let mut resp: Value = self
+                .http
+                .get(&url)
+                .bearer_auth(&token)
+                .send()
+                .await?
+                .json()
+                .await?;
+            if Self::is_token_error(resp["code"].as_i64().unwrap_or(-1)) {
+                let new_token = self.invalidate_and_refresh().await?;
+                resp = self
+                    .http
+                    .get(&url)
+                    .bearer_auth(&new_token)
+                    .send()
+                    .await?
+                    .json()
+                    .await?;
+            }
+            let code = resp["code"].as_i64().unwrap_or(-1);
+            if code != 0 {
+                anyhow::bail!("list_chats failed: {} (code={code})", resp["msg"]);
+            }
+
+            let (items, has_more, next_token) = Self::parse_list_response(&resp)?;
+            chat_ids.extend(
+                items
+                    .iter()
+                    .filter_map(|c| c["chat_id"].as_str().map(|s| s.to_string())),
+            );
+            if !has_more || next_token.is_none() {
+                return Ok(chat_ids);
+            }
+            page_token = next_token;
+        }
+    }
+
     /// Send a file message to a chat using an already-uploaded file_key.
     pub async fn send_file_message(
         &self,
@@ -580,4 +728,32 @@ impl FeishuApi {
             .await?;
         Ok(())
     }
+
+    /// Replace a single card element.
+    pub async fn update_card_element(
+        &self,
+        card_id: &str,
+        element_id: &str,
+        element_json: &str,
+    ) -> Result<()> {
+        let body = serde_json::json!({
+            "element": element_json,
+            "sequence": self.next_seq(),
+        });
+        let path = format!("/cardkit/v1/cards/{card_id}/elements/{element_id}");
+        self.cardkit_call(reqwest::Method::PUT, &path, &body)
+            .await?;
+        Ok(())
+    }
+
+    /// Remove an element from a card.
+    pub async fn delete_card_element(&self, card_id: &str, element_id: &str) -> Result<()> {
+        let body = serde_json::json!({
+            "sequence": self.next_seq(),
+        });
+        let path = format!("/cardkit/v1/cards/{card_id}/elements/{element_id}");
+        self.cardkit_call(reqwest::Method::DELETE, &path, &body)
+            .await?;
+        Ok(())
+    }
 }
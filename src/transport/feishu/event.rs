@@ -1,8 +1,10 @@
 use std::collections::HashMap;
+use std::num::NonZeroUsize;
 use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
 use futures_util::{SinkExt, StreamExt};
+use lru::LruCache;
 use prost::Message as ProstMessage;
 use serde::Deserialize;
 use serde_json::Value;
@@ -12,11 +14,17 @@ use tracing::{debug, error, info, warn};
 
 use crate::config::FeishuConfig;
 
-use super::TransportEvent;
+use super::{Attachment, TransportEvent};
 use super::proto::*;
 
 const WS_ENDPOINT: &str = "https://open.feishu.cn/callback/ws/endpoint";
 
+/// Message ids remembered per connection to drop duplicate deliveries.
+const MAX_SEEN_MESSAGES: usize = 1000;
+
+/// Consecutive failed connection attempts before giving up on WebSocket.
+const MAX_CONNECT_ATTEMPTS: u32 = 3;
+
 #[derive(Deserialize)]
 struct EndpointResponse {
     code: i32,
@@ -47,27 +55,48 @@ struct CacheEntry {
     created: Instant,
 }
 
-/// Start the Feishu WebSocket event loop.
+/// Start the Feishu WebSocket event loop. Reconnects whenever a connection
+/// drops, and returns an error once `MAX_CONNECT_ATTEMPTS` attempts in a row
+/// fail to connect at all. `ever_connected` is set once a connection opens.
 pub async fn start_event_loop(
     config: &FeishuConfig,
     tx: mpsc::Sender<TransportEvent>,
+    ever_connected: &mut bool,
 ) -> Result<()> {
+    let mut failures = 0;
     loop {
-        match run_ws_connection(config, &tx).await {
+        let mut connected = false;
+        let result = run_ws_connection(config, &tx, &mut connected).await;
+        *ever_connected |= connected;
+        match result {
             Ok(()) => {
                 info!("Feishu WebSocket closed, reconnecting...");
             }
+            Err(e) if connected => {
+                error!("Feishu WebSocket error: {e}, reconnecting...");
+            }
             Err(e) => {
+                failures += 1;
+                if failures >= MAX_CONNECT_ATTEMPTS {
+                    anyhow::bail!("WebSocket unavailable after {failures} attempts: {e}");
+                }
                 error!("Feishu WebSocket error: {e}, reconnecting...");
             }
         }
+        if connected {
+            failures = 0;
+        }
         tokio::time::sleep(Duration::from_secs(3)).await;
     }
 }
 
+/// Run one WebSocket connection until it closes. `connected` is set once the
+/// socket is up, so callers can tell a dropped connection from one that
+/// never opened.
 async fn run_ws_connection(
     config: &FeishuConfig,
     tx: &mpsc::Sender<TransportEvent>,
+    connected: &mut bool,
 ) -> Result<()> {
     // 1. Get WebSocket endpoint URL
     let http = reqwest::Client::new();
@@ -114,9 +143,13 @@ async fn run_ws_connection(
             .context("WebSocket connect failed")?;
 
     info!("Feishu WebSocket connected");
+    *connected = true;
 
     let (mut ws_write, mut ws_read) = ws_stream.split();
     let mut msg_cache: HashMap<String, CacheEntry> = HashMap::new();
+    // Events are delivered at least once; a fresh connection starts a fresh set
+    let mut seen_message_ids: LruCache<String, ()> =
+        LruCache::new(NonZeroUsize::new(MAX_SEEN_MESSAGES).unwrap());
     let mut ping_timer = tokio::time::interval(ping_interval);
     ping_timer.tick().await; // consume first immediate tick
 
@@ -152,6 +185,7 @@ async fn run_ws_connection(
                             frame,
                             tx,
                             &mut msg_cache,
+                            &mut seen_message_ids,
                             &mut ws_write,
                             service_id,
                         ).await;
@@ -181,6 +215,7 @@ async fn handle_frame(
     frame: Frame,
     tx: &mpsc::Sender<TransportEvent>,
     cache: &mut HashMap<String, CacheEntry>,
+    seen: &mut LruCache<String, ()>,
     ws_write: &mut WsWriter,
     service_id: i32,
 ) {
@@ -233,7 +268,12 @@ async fn handle_frame(
     let resp_code = match serde_json::from_str::<Value>(&data_str) {
         Ok(json) => {
             if let Some(evt) = parse_event_json(&json) {
-                let _ = tx.send(evt).await;
+                let id = evt.message_id();
+                if !id.is_empty() && seen.put(id.to_string(), ()).is_some() {
+                    debug!("Skipping duplicate delivery of message {id}");
+                } else {
+                    let _ = tx.send(evt).await;
+                }
             }
             200
         }
@@ -302,10 +342,14 @@ fn merge_parts(
     }
 }
 
-fn parse_event_json(json: &Value) -> Option<TransportEvent> {
+pub(super) fn parse_event_json(json: &Value) -> Option<TransportEvent> {
     let header = json.get("header")?;
     let event_type = header.get("event_type")?.as_str()?;
 
+    // Feishu names the event `im.message.reaction.created_v1`
+    if event_type.starts_with("im.message.reaction.created") {
+        return parse_reaction(json.get("event")?);
+    }
     if event_type != "im.message.receive_v1" {
         debug!("Ignoring event type: {event_type}");
         return None;
@@ -320,49 +364,74 @@ fn parse_event_json(json: &Value) -> Option<TransportEvent> {
         .and_then(|v| v.as_str())
         .unwrap_or("unknown");
 
-    if msg_type != "text" && msg_type != "file" {
+    if !matches!(msg_type, "text" | "file" | "image" | "post") {
         debug!("Ignoring unsupported message type: {msg_type}");
         return None;
     }
 
     let content_str = message.get("content")?.as_str()?;
     let content: Value = serde_json::from_str(content_str).ok()?;
+    let parent_id = message
+        .get("parent_id")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
+    let (text, files) = match msg_type {
+        "file" => {
+            let file_key = content.get("file_key")?.as_str()?.to_string();
+            let file_name = content
+                .get("file_name")
+                .and_then(|v| v.as_str())
+                .unwrap_or(&file_key)
+                .to_string();
+            let file = Attachment {
+                file_key,
+                file_name,
+                resource_type: "file",
+            };
+            (String::new(), vec![file])
+        }
+        "image" => {
+            let image_key = content.get("image_key")?.as_str()?.to_string();
+            let file = Attachment {
+                file_name: image_key.clone(),
+                file_key: image_key,
+                resource_type: "image",
+            };
+            (String::new(), vec![file])
+        }
+        "post" => parse_post(&content),
+        _ => (content.get("text")?.as_str()?.to_string(), Vec::new()),
+    };
 
     // Handle file messages
-    if msg_type == "file" {
-        let file_key = content.get("file_key")?.as_str()?.to_string();
-        let file_name = content
-            .get("file_name")
-            .and_then(|v| v.as_str())
-            .unwrap_or(&file_key)
-            .to_string();
+    if !files.is_empty() {
         let message_id = message.get("message_id")?.as_str()?.to_string();
-        let parent_id = message
-            .get("parent_id")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-        info!("File message in chat_id={chat_id}: {file_name} (msg_id={message_id})");
+        info!(
+            "File message in chat_id={chat_id}: {} attachment(s) (msg_id={message_id})",
+            files.len()
+        );
         return Some(TransportEvent::FileMessage {
             conv_id: chat_id.to_string(),
             user_id: sender_id.to_string(),
             message_id,
-            file_key,
-            file_name,
+            files,
+            text,
             parent_id,
         });
     }
 
-    let text = content.get("text")?.as_str()?.to_string();
-
-    let parent_id = message
-        .get("parent_id")
+    let message_id = message
+        .get("message_id")
         .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
+        .unwrap_or("")
+        .to_string();
 
     if let Some(parent_msg_id) = parent_id {
         info!("Reply detected: parent_id={parent_msg_id}");
         Some(TransportEvent::ReplyMessage {
             card_msg_id: parent_msg_id,
+            message_id,
             text,
         })
     } else {
@@ -370,7 +439,81 @@ fn parse_event_json(json: &Value) -> Option<TransportEvent> {
         Some(TransportEvent::NewMessage {
             conv_id: chat_id.to_string(),
             user_id: sender_id.to_string(),
+            message_id,
             text,
         })
     }
 }
+
+/// Collect the text and attachments of a rich-text (`post`) message. The
+/// content is either `{title, content}` or wrapped in a locale key.
+fn parse_post(content: &Value) -> (String, Vec<Attachment>) {
+    let post = if content.get("content").is_some() {
+        content
+    } else {
+        content
+            .as_object()
+            .and_then(|o| o.values().next())
+            .unwrap_or(content)
+    };
+    let mut lines = Vec::new();
+    let mut files = Vec::new();
+    if let Some(title) = post.get("title").and_then(|v| v.as_str()).filter(|t| !t.is_empty()) {
+        lines.push(title.to_string());
+    }
+    let paragraphs = post.get("content").and_then(|v| v.as_array());
+    for paragraph in paragraphs.into_iter().flatten() {
+        let mut line = String::new();
+        for element in paragraph.as_array().into_iter().flatten() {
+            match element.get("tag").and_then(|v| v.as_str()) {
+                Some("text" | "a") => {
+                    line.push_str(element.get("text").and_then(|v| v.as_str()).unwrap_or(""));
+                }
+                Some("img") => {
+                    if let Some(key) = element.get("image_key").and_then(|v| v.as_str()) {
+                        files.push(Attachment {
+                            file_key: key.to_string(),
+                            file_name: key.to_string(),
+                            resource_type: "image",
+                        });
+                    }
+                }
+                Some("media") => {
+                    if let Some(key) = element.get("file_key").and_then(|v| v.as_str()) {
+                        files.push(Attachment {
+                            file_key: key.to_string(),
+                            file_name: key.to_string(),
+                            resource_type: "file",
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !line.is_empty() {
+            lines.push(line);
+        }
+    }
+    (lines.join("\n"), files)
+}
+
+/// Parse a reaction added to a message. Reactions made by the app itself
+/// (such as the acknowledgement on user messages) are ignored.
+fn parse_reaction(event: &Value) -> Option<TransportEvent> {
+    if event.get("operator_type").and_then(|v| v.as_str()) == Some("app") {
+        return None;
+    }
+    let msg_id = event.get("message_id")?.as_str()?.to_string();
+    let emoji_type = event.pointer("/reaction_type/emoji_type")?.as_str()?.to_string();
+    let user_id = event
+        .pointer("/user_id/open_id")
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown")
+        .to_string();
+    info!("Reaction {emoji_type} on msg_id={msg_id}");
+    Some(TransportEvent::Reaction {
+        msg_id,
+        user_id,
+        emoji_type,
+    })
+}
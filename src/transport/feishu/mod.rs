@@ -1,7 +1,12 @@
 mod api;
 mod event;
+mod polling;
 mod proto;
 
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
 use anyhow::Result;
 use tokio::sync::mpsc;
 use tracing::{debug, error, info, warn};
@@ -12,32 +17,70 @@ pub use api::FeishuApi;
 
 const CONTENT_ELEMENT_ID: &str = "content_md";
 
+/// How long to poll before trying an unreachable WebSocket again.
+const WS_RETRY_INTERVAL: Duration = Duration::from_secs(300);
+
+/// A file or image attached to a user message.
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub file_key: String,
+    pub file_name: String,
+    /// "file" or "image", as the message resource API expects.
+    pub resource_type: &'static str,
+}
+
 /// Transport-level events from Feishu (decoupled from agent events).
 #[derive(Debug)]
 pub enum TransportEvent {
     NewMessage {
         conv_id: String,
         user_id: String,
+        message_id: String,
         text: String,
     },
     ReplyMessage {
         card_msg_id: String,
+        message_id: String,
         text: String,
     },
+    /// A file, image or rich-text message with attachments.
     FileMessage {
         conv_id: String,
         user_id: String,
         message_id: String,
-        file_key: String,
-        file_name: String,
+        files: Vec<Attachment>,
+        /// Text sent along with the attachments (rich-text messages).
+        text: String,
         /// If this file is a reply to an existing card
         parent_id: Option<String>,
     },
+    /// A user reacted to a message. Needs the app to subscribe to
+    /// `im.message.reaction.created_v1`.
+    Reaction {
+        msg_id: String,
+        user_id: String,
+        emoji_type: String,
+    },
+}
+
+impl TransportEvent {
+    /// Feishu message_id of the message behind the event; empty for
+    /// reactions, which may target the same message many times.
+    pub fn message_id(&self) -> &str {
+        match self {
+            Self::NewMessage { message_id, .. }
+            | Self::ReplyMessage { message_id, .. }
+            | Self::FileMessage { message_id, .. } => message_id,
+            Self::Reaction { .. } => "",
+        }
+    }
 }
 
 pub struct FeishuTransport {
     config: FeishuConfig,
     api: FeishuApi,
+    /// Chat names by chat id, kept for the life of the process.
+    chat_names: Mutex<HashMap<String, String>>,
 }
 
 impl FeishuTransport {
@@ -45,20 +88,88 @@ impl FeishuTransport {
         Self {
             config: config.clone(),
             api: FeishuApi::new(config),
+            chat_names: Mutex::new(HashMap::new()),
         }
     }
 
+    /// The name of `chat_id` if it has been looked up before.
+    pub fn cached_chat_name(&self, chat_id: &str) -> Option<String> {
+        self.chat_names
+            .lock()
+            .unwrap()
+            .get(chat_id)
+            .filter(|name| !name.is_empty())
+            .cloned()
+    }
+
+    /// The name of `chat_id`, looked up on first use. `None` for chats
+    /// without a name (e.g. direct messages) or when the lookup fails.
+    pub async fn chat_name(&self, chat_id: &str) -> Option<String> {
+        if let Some(name) = self.chat_names.lock().unwrap().get(chat_id) {
+            return Some(name.clone()).filter(|name| !name.is_empty());
+        }
+        // Nameless chats and failed lookups (e.g. without the chat read
+        // permission) are cached too, as "", so they aren't looked up again
+        let name = match self.api.get_chat_info(chat_id).await {
+            Ok(info) => info["name"].as_str().unwrap_or("").trim().to_string(),
+            Err(e) => {
+                warn!("Failed to look up Feishu chat {chat_id}: {e}");
+                String::new()
+            }
+        };
+        self.chat_names
+            .lock()
+            .unwrap()
+            .insert(chat_id.to_string(), name.clone());
+        Some(name).filter(|name| !name.is_empty())
+    }
+
     pub async fn start_with_bridge(
         &self,
         tx: mpsc::Sender<TransportEvent>,
     ) -> Result<()> {
         let config = self.config.clone();
-        tokio::spawn(async move {
-            if let Err(e) = event::start_event_loop(&config, tx).await {
-                error!("Feishu event loop error: {e}");
+        match config.transport_mode.as_str() {
+            "websocket" => {
+                info!("Feishu transport started (websocket)");
+                tokio::spawn(async move {
+                    // Kept across retries so messages sent while the WebSocket
+                    // is retried are picked up by the next polling run
+                    let mut state = polling::PollingState::new(&config);
+                    loop {
+                        let mut connected = false;
+                        if let Err(e) =
+                            event::start_event_loop(&config, tx.clone(), &mut connected).await
+                        {
+                            warn!("Feishu {e}, polling until it is back");
+                        }
+                        if connected {
+                            // The WebSocket delivered the messages until it dropped
+                            state.skip_to_now();
+                        }
+                        // Poll only while the WebSocket is down, then try it again
+                        let polling = polling::start_polling_loop(&mut state, tx.clone());
+                        match tokio::time::timeout(WS_RETRY_INTERVAL, polling).await {
+                            Ok(Ok(())) => return,
+                            Ok(Err(e)) => error!("Feishu polling loop error: {e}"),
+                            Err(_) => info!("Retrying the Feishu WebSocket"),
+                        }
+                    }
+                });
             }
-        });
-        info!("Feishu transport started");
+            "polling" => {
+                info!("Feishu transport started (polling)");
+                tokio::spawn(async move {
+                    let mut state = polling::PollingState::new(&config);
+                    if let Err(e) = polling::start_polling_loop(&mut state, tx).await {
+                        error!("Feishu polling loop error: {e}");
+                    }
+                });
+            }
+            other => anyhow::bail!(
+                "Unknown Feishu transport_mode: {other} (expected websocket or polling)"
+            ),
+        }
         Ok(())
     }
 
@@ -104,50 +215,80 @@ impl FeishuTransport {
         Ok((msg_id, card_id))
     }
 
-    pub async fn update_card_content(
+    /// Stream `content` into the card's main markdown element, leaving any
+    /// other elements in place.
+    pub async fn stream_card_content(&self, card_id: &str, content: &str) -> Result<()> {
+        self.api
+            .streaming_update_text(card_id, CONTENT_ELEMENT_ID, content)
+            .await
+    }
+
+    /// Append a markdown element to the end of a card's body.
+    pub async fn append_card_markdown(
         &self,
         card_id: &str,
-        title: &str,
+        element_id: &str,
         content: &str,
     ) -> Result<()> {
-        let card_json = serde_json::json!({
-            "schema": "2.0",
-            "header": {
-                "title": { "tag": "plain_text", "content": title },
-                "template": "blue"
-            },
-            "config": {
-                "streaming_mode": true
-            },
-            "body": {
-                "elements": [
-                    {
-                        "tag": "markdown",
-                        "content": content,
-                        "element_id": CONTENT_ELEMENT_ID
-                    }
-                ]
-            }
+        let elements = serde_json::json!([{
+            "tag": "markdown",
+            "content": content,
+            "element_id": element_id
+        }]);
+        self.api
+            .create_card_element(card_id, "append", "", &elements.to_string())
+            .await
+    }
+
+    /// Replace the content of a markdown element added by `append_card_markdown`.
+    pub async fn update_card_markdown(
+        &self,
+        card_id: &str,
+        element_id: &str,
+        content: &str,
+    ) -> Result<()> {
+        let element = serde_json::json!({
+            "tag": "markdown",
+            "content": content,
+            "element_id": element_id
         });
         self.api
-            .update_card(card_id, &card_json.to_string())
+            .update_card_element(card_id, element_id, &element.to_string())
             .await
     }
 
+    pub async fn delete_card_element(&self, card_id: &str, element_id: &str) -> Result<()> {
+        self.api.delete_card_element(card_id, element_id).await
+    }
+
     pub async fn finish_card(
         &self,
         card_id: &str,
         title: &str,
         status: &str,
         content: &str,
+        footer: Option<&str>,
     ) -> Result<()> {
         let (emoji, template) = match status {
             "completed" => ("✅", "green"),
             "failed" => ("❌", "red"),
             "cancelled" => ("⏹", "grey"),
+            "reset" => ("🔄", "grey"),
             _ => ("📋", "blue"),
         };
 
+        let mut elements = vec![serde_json::json!({
+            "tag": "markdown",
+            "content": content,
+            "element_id": CONTENT_ELEMENT_ID
+        })];
+        if let Some(footer) = footer {
+            elements.push(serde_json::json!({
+                "tag": "note",
+                "elements": [{ "tag": "plain_text", "content": footer }]
+            }));
+        }
+
         let final_card = serde_json::json!({
             "schema": "2.0",
             "header": {
@@ -158,13 +299,7 @@ impl FeishuTransport {
                 "streaming_mode": false
             },
             "body": {
-                "elements": [
-                    {
-                        "tag": "markdown",
-                        "content": content,
-                        "element_id": CONTENT_ELEMENT_ID
-                    }
-                ]
+                "elements": elements
             }
         });
 
@@ -186,6 +321,18 @@ impl FeishuTransport {
         Ok(())
     }
 
+    /// Send a plain text message to a chat.
+    pub async fn send_text(&self, chat_id: &str, text: &str) -> Result<()> {
+        let content = serde_json::json!({ "text": text });
+        self.api.send_message(chat_id, "text", &content).await?;
+        Ok(())
+    }
+
+    /// React to a message with an emoji such as "THUMBSUP".
+    pub async fn add_reaction(&self, msg_id: &str, emoji_type: &str) -> Result<()> {
+        self.api.add_emoji_reaction(msg_id, emoji_type).await
+    }
+
     /// Reply to a message with plain text.
     pub async fn reply_text(&self, msg_id: &str, text: &str) -> Result<()> {
         let content = serde_json::json!({ "text": text });
@@ -193,6 +340,13 @@ impl FeishuTransport {
         Ok(())
     }
 
+    /// Download an attachment of a user message.
+    pub async fn download_attachment(&self, msg_id: &str, file: &Attachment) -> Result<Vec<u8>> {
+        self.api
+            .download_message_resource(msg_id, &file.file_key, file.resource_type)
+            .await
+    }
+
     /// Download a file by file_key and save to disk. Returns the saved path.
     pub async fn download_file_to(&self, file_key: &str, save_path: &str) -> Result<()> {
         let bytes = self.api.download_file(file_key).await?;
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::Result;
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+use crate::config::FeishuConfig;
+
+use super::api::FeishuApi;
+use super::event::parse_event_json;
+use super::TransportEvent;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Re-list the bot's chats every this many polls to pick up new ones.
+const CHAT_REFRESH_POLLS: u32 = 12;
+
+const PAGE_SIZE: usize = 20;
+
+/// Polling progress, kept across runs of `start_polling_loop` while the
+/// WebSocket is retried.
+pub struct PollingState {
+    api: FeishuApi,
+    /// Looked up at the start of a run until a lookup succeeds.
+    bot_id: Option<String>,
+    /// Whether each chat is a direct message, looked up once per chat.
+    p2p: HashMap<String, bool>,
+    /// Only messages sent after this (ms) are new; chats joined later start
+    /// from the same point.
+    started_ms: i64,
+    /// Create time (ms) of the newest message delivered per chat.
+    last_seen: HashMap<String, i64>,
+    chat_ids: Vec<String>,
+    polls: u32,
+}
+
+impl PollingState {
+    pub fn new(config: &FeishuConfig) -> Self {
+        Self {
+            api: FeishuApi::new(config),
+            bot_id: None,
+            p2p: HashMap::new(),
+            started_ms: chrono::Utc::now().timestamp_millis(),
+            last_seen: HashMap::new(),
+            chat_ids: Vec::new(),
+            polls: 0,
+        }
+    }
+
+    /// Treat only messages sent from now on as new, e.g. once another
+    /// channel has delivered the earlier ones.
+    pub fn skip_to_now(&mut self) {
+        self.started_ms = chrono::Utc::now().timestamp_millis();
+        self.last_seen.clear();
+    }
+}
+
+/// Poll every chat the bot is in for new messages, for networks where the
+/// WebSocket endpoint can't be reached. Like the WebSocket, only direct
+/// messages and group messages that @mention the bot are delivered.
+/// Reactions are not: the message list API doesn't report them.
+/// Picks up where the last run on `state` stopped.
+pub async fn start_polling_loop(
+    state: &mut PollingState,
+    tx: mpsc::Sender<TransportEvent>,
+) -> Result<()> {
+    if state.bot_id.is_none() {
+        // Without it no group message can be told apart as addressed to the bot
+        match state.api.bot_open_id().await {
+            Ok(id) => state.bot_id = Some(id),
+            Err(e) => warn!(
+                "Failed to look up the Feishu bot's open_id, polling direct messages only: {e}"
+            ),
+        }
+    }
+
+    info!("Feishu polling every {}s", POLL_INTERVAL.as_secs());
+    loop {
+        if state.polls.is_multiple_of(CHAT_REFRESH_POLLS) {
+            match state.api.list_chats().await {
+                Ok(ids) => {
+                    debug!("Feishu polling {} chat(s)", ids.len());
+                    for id in &ids {
+                        if state.p2p.contains_key(id) {
+                            continue;
+                        }
+                        match state.api.get_chat_info(id).await {
+                            Ok(info) => {
+                                let is_p2p = info["chat_mode"].as_str() == Some("p2p");
+                                state.p2p.insert(id.clone(), is_p2p);
+                            }
+                            Err(e) => warn!("Failed to look up Feishu chat {id}: {e}"),
+                        }
+                    }
+                    state.chat_ids = ids;
+                }
+                Err(e) => warn!("Failed to list Feishu chats: {e}"),
+            }
+        }
+        state.polls += 1;
+
+        for chat_id in &state.chat_ids {
+            // Chats that couldn't be looked up are retried at the next refresh
+            let Some(&is_p2p) = state.p2p.get(chat_id) else {
+                continue;
+            };
+            if !is_p2p && state.bot_id.is_none() {
+                continue;
+            }
+            let since = state.last_seen.get(chat_id).copied().unwrap_or(state.started_ms);
+            let mention = if is_p2p { None } else { state.bot_id.as_deref() };
+            match poll_chat(&state.api, chat_id, since, mention).await {
+                Ok((events, newest)) => {
+                    for evt in events {
+                        if tx.send(evt).await.is_err() {
+                            return Ok(());
+                        }
+                    }
+                    // Advanced only once delivered, in case the run is cut short
+                    state.last_seen.insert(chat_id.clone(), newest);
+                }
+                Err(e) => warn!("Failed to poll Feishu chat {chat_id}: {e}"),
+            }
+        }
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Fetch the messages of `chat_id` created after `since` (ms), oldest first,
+/// and the create time of the newest one (`since` if there are none). With
+/// `mention`, only messages that @mention that open_id are kept.
+async fn poll_chat(
+    api: &FeishuApi,
+    chat_id: &str,
+    since: i64,
+    mention: Option<&str>,
+) -> Result<(Vec<TransportEvent>, i64)> {
+    let mut items: Vec<Value> = Vec::new();
+    let mut page_token: Option<String> = None;
+
+    // Messages come newest first, so stop at the first one already seen
+    'pages: loop {
+        let (page, has_more, next_token) =
+            api.list_messages(chat_id, PAGE_SIZE, page_token.as_deref()).await?;
+        for item in page {
+            if create_time(&item) <= since {
+                break 'pages;
+            }
+            items.push(item);
+        }
+        if !has_more || next_token.is_none() {
+            break;
+        }
+        page_token = next_token;
+    }
+
+    let newest = items.first().map_or(since, create_time);
+    let events = items
+        .iter()
+        .rev()
+        .filter(|item| mention.is_none_or(|id| mentions(item, id)))
+        .filter_map(|item| parse_event_json(&to_event_json(item)?))
+        .collect();
+    Ok((events, newest))
+}
+
+fn create_time(item: &Value) -> i64 {
+    item["create_time"]
+        .as_str()
+        .and_then(|t| t.parse().ok())
+        .unwrap_or(0)
+}
+
+fn mentions(item: &Value, open_id: &str) -> bool {
+    item["mentions"]
+        .as_array()
+        .is_some_and(|list| list.iter().any(|m| m["id"].as_str() == Some(open_id)))
+}
+
+/// Reshape a message list item into the `im.message.receive_v1` event the
+/// WebSocket delivers. `None` for the bot's own and deleted messages.
+fn to_event_json(item: &Value) -> Option<Value> {
+    if item["sender"]["sender_type"].as_str() == Some("app")
+        || item["deleted"].as_bool() == Some(true)
+    {
+        return None;
+    }
+    let parent_id = item["parent_id"].as_str().filter(|p| !p.is_empty());
+    Some(serde_json::json!({
+        "header": { "event_type": "im.message.receive_v1" },
+        "event": {
+            "sender": { "sender_id": { "open_id": item["sender"]["id"] } },
+            "message": {
+                "message_id": item["message_id"],
+                "chat_id": item["chat_id"],
+                "message_type": item["msg_type"],
+                "content": item["body"]["content"],
+                "parent_id": parent_id,
+            },
+        },
+    }))
+}
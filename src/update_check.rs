@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::path::PathBuf;
 
 #[allow(unused_imports)]
@@ -8,9 +9,7 @@ use crate::config;
 
 pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
 const GITHUB_REPO: &str = "vecbase-labs/myagent";
-#[allow(dead_code)]
-const CHECK_INTERVAL_HOURS: i64 = 24;
-#[allow(dead_code)]
+pub const CHECK_INTERVAL_HOURS: i64 = 24;
 const VERSION_FILENAME: &str = "version.json";
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -19,21 +18,21 @@ pub struct VersionInfo {
     pub last_checked_at: DateTime<Utc>,
     #[serde(default)]
     pub dismissed_version: Option<String>,
+    /// Release channel `latest_version` was fetched from (missing: stable).
+    #[serde(default)]
+    pub channel: Option<String>,
 }
 
-#[allow(dead_code)]
 fn version_file_path() -> PathBuf {
     config::config_dir().join(VERSION_FILENAME)
 }
 
-#[allow(dead_code)]
 fn read_version_info() -> Option<VersionInfo> {
     let path = version_file_path();
     let content = std::fs::read_to_string(&path).ok()?;
     serde_json::from_str(&content).ok()
 }
 
-#[allow(dead_code)]
 fn write_version_info(info: &VersionInfo) -> anyhow::Result<()> {
     let path = version_file_path();
     if let Some(parent) = path.parent() {
@@ -44,70 +43,96 @@ fn write_version_info(info: &VersionInfo) -> anyhow::Result<()> {
     Ok(())
 }
 
-#[allow(dead_code)]
-fn parse_version(v: &str) -> Option<(u64, u64, u64)> {
-    let mut iter = v.trim().split('.');
+/// `(major, minor, patch)`
+type CoreVersion = (u64, u64, u64);
+
+/// Split `[v]MAJOR.MINOR.PATCH[-PRE][+BUILD]` into its core version and
+/// pre-release part. Build metadata is ignored.
+fn parse_version(v: &str) -> Option<(CoreVersion, Option<&str>)> {
+    let v = v.trim().trim_start_matches('v');
+    let v = v.split_once('+').map_or(v, |(version, _build)| version);
+    let (core, pre) = match v.split_once('-') {
+        Some((core, pre)) => (core, Some(pre)),
+        None => (v, None),
+    };
+    let mut iter = core.split('.');
     let maj = iter.next()?.parse::<u64>().ok()?;
     let min = iter.next()?.parse::<u64>().ok()?;
     let pat = iter.next()?.parse::<u64>().ok()?;
-    Some((maj, min, pat))
+    Some(((maj, min, pat), pre))
 }
 
-#[allow(dead_code)]
-fn is_newer(latest: &str, current: &str) -> bool {
-    match (parse_version(latest), parse_version(current)) {
-        (Some(l), Some(c)) => l > c,
-        _ => false,
+/// Order pre-release tags by semver rules: dot-separated identifiers compared
+/// left to right, numeric ones numerically and below alphanumeric ones.
+fn compare_prerelease(a: &str, b: &str) -> Ordering {
+    let mut left = a.split('.');
+    let mut right = b.split('.');
+    loop {
+        let ord = match (left.next(), right.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(l), Some(r)) => match (l.parse::<u64>(), r.parse::<u64>()) {
+                (Ok(l), Ok(r)) => l.cmp(&r),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => l.cmp(r),
+            },
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
     }
 }
 
-/// Check GitHub API for latest release version.
-#[allow(dead_code)]
-async fn fetch_latest_version() -> anyhow::Result<String> {
-    let url = format!("https://api.github.com/repos/{GITHUB_REPO}/releases/latest");
-    let client = reqwest::Client::new();
-    let resp: serde_json::Value = client
-        .get(&url)
-        .header("User-Agent", format!("myagent/{CURRENT_VERSION}"))
-        .header("Accept", "application/vnd.github.v3+json")
-        .send()
-        .await?
-        .error_for_status()?
-        .json()
-        .await?;
+/// Compare two version strings; `None` if either doesn't parse.
+/// A release sorts above its own pre-releases (1.3.0 > 1.3.0-beta.1 > 1.2.0).
+pub fn compare_versions(a: &str, b: &str) -> Option<Ordering> {
+    let (a_core, a_pre) = parse_version(a)?;
+    let (b_core, b_pre) = parse_version(b)?;
+    let ord = a_core.cmp(&b_core).then_with(|| match (a_pre, b_pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => compare_prerelease(a, b),
+    });
+    Some(ord)
+}
 
-    let tag = resp["tag_name"]
-        .as_str()
-        .ok_or_else(|| anyhow::anyhow!("No tag_name in release"))?;
-    Ok(tag.to_string())
+pub fn is_newer(latest: &str, current: &str) -> bool {
+    compare_versions(latest, current) == Some(Ordering::Greater)
 }
 
 /// Background check: fetch latest version and update cache file.
-#[allow(dead_code)]
-async fn do_check() {
-    match fetch_latest_version().await {
-        Ok(latest) => {
+/// Returns the release if it is newer than the running version.
+pub async fn do_check(channel: String) -> Option<GithubRelease> {
+    match fetch_release_info(&channel).await {
+        Ok(release) => {
             let prev = read_version_info();
             let info = VersionInfo {
-                latest_version: latest,
+                latest_version: release.tag_name.clone(),
                 last_checked_at: Utc::now(),
                 dismissed_version: prev.and_then(|p| p.dismissed_version),
+                channel: Some(channel),
             };
             if let Err(e) = write_version_info(&info) {
                 tracing::debug!("Failed to write version cache: {e}");
             }
+            is_newer(&release.tag_name, CURRENT_VERSION).then_some(release)
         }
         Err(e) => {
             tracing::debug!("Failed to check for updates: {e}");
+            None
         }
     }
 }
 
 /// Called on startup. Spawns background check if needed, returns update hint.
 /// Only active in release builds.
-pub fn check_on_startup() -> Option<String> {
+pub fn check_on_startup(channel: &str) -> Option<String> {
     #[cfg(debug_assertions)]
     {
+        let _ = channel;
         return None;
     }
 
@@ -115,7 +140,9 @@ pub fn check_on_startup() -> Option<String> {
     {
         use chrono::Duration;
 
-        let info = read_version_info();
+        // A cache filled from another channel is as good as missing
+        let info = read_version_info()
+            .filter(|i| i.channel.as_deref().unwrap_or("stable") == channel);
 
         // Spawn background refresh if stale or missing
         let needs_check = match &info {
@@ -123,7 +150,7 @@ pub fn check_on_startup() -> Option<String> {
             Some(i) => i.last_checked_at < Utc::now() - Duration::hours(CHECK_INTERVAL_HOURS),
         };
         if needs_check {
-            tokio::spawn(do_check());
+            tokio::spawn(do_check(channel.to_string()));
         }
 
         // Return update hint from cached info
@@ -172,27 +199,50 @@ pub fn asset_name() -> anyhow::Result<String> {
     }
 }
 
-/// Fetch latest release info from GitHub.
-pub async fn fetch_release_info() -> anyhow::Result<(String, Vec<ReleaseAsset>)> {
-    let url = format!("https://api.github.com/repos/{GITHUB_REPO}/releases/latest");
+/// Fetch latest release info from GitHub. The "beta" channel takes the
+/// newest pre-release; any other channel gets the latest stable release.
+pub async fn fetch_release_info(channel: &str) -> anyhow::Result<GithubRelease> {
     let client = reqwest::Client::new();
-    let resp: GithubRelease = client
-        .get(&url)
-        .header("User-Agent", format!("myagent/{CURRENT_VERSION}"))
-        .header("Accept", "application/vnd.github.v3+json")
+    let get = |url: String| {
+        client
+            .get(url)
+            .header("User-Agent", format!("myagent/{CURRENT_VERSION}"))
+            .header("Accept", "application/vnd.github.v3+json")
+    };
+
+    if channel == "beta" {
+        // The list endpoint returns releases newest first
+        let releases: Vec<GithubRelease> = get(format!("https://api.github.com/repos/{GITHUB_REPO}/releases"))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        return releases
+            .into_iter()
+            .find(|r| r.prerelease)
+            .ok_or_else(|| anyhow::anyhow!("No beta release found"));
+    }
+
+    let resp: GithubRelease = get(format!("https://api.github.com/repos/{GITHUB_REPO}/releases/latest"))
         .send()
         .await?
         .error_for_status()?
         .json()
         .await?;
 
-    Ok((resp.tag_name, resp.assets))
+    Ok(resp)
 }
 
 #[derive(Deserialize, Debug)]
 pub struct GithubRelease {
     pub tag_name: String,
     pub assets: Vec<ReleaseAsset>,
+    /// Markdown release notes.
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub prerelease: bool,
 }
 
 #[derive(Deserialize, Debug)]